@@ -10,22 +10,79 @@
 
 -- Individual swap events (main fact table)
 CREATE TABLE IF NOT EXISTS jupiter_swaps (
-    id String,
+    id String,  -- {tx_hash}:{instruction_index}[:{inner_index}]
     tx_hash String,
+    instruction_index UInt32,
+    inner_index Nullable(UInt32),
+    is_cpi Bool DEFAULT false,
+    caller_program Nullable(String),
+    stack_height UInt32,
     program_id LowCardinality(String),
     slot UInt64,
     block_time Int64,
     amount_in UInt256,
     amount_out UInt256,
+    quoted_amount_in UInt256,
+    quoted_amount_out UInt256,
     input_mint String,
     output_mint String,
     user_wallet String,
+    token_program LowCardinality(String),
+    output_transfer_fee UInt256 DEFAULT 0,
+    amount_in_normalized Nullable(Float64),
+    amount_out_normalized Nullable(Float64),
+    parse_method LowCardinality(Nullable(String)),
+    price_outlier Bool DEFAULT false,
+    outlier_reason LowCardinality(Nullable(String)),
+    execution_vs_oracle_bps Nullable(Float64),
     date Date MATERIALIZED toDate(fromUnixTimestamp(block_time))
 ) ENGINE = MergeTree()
 PARTITION BY toYYYYMM(date)
 ORDER BY (program_id, block_time, tx_hash)
 SETTINGS index_granularity = 8192;
 
+-- Failed (reverted) swaps with the decoded program error
+CREATE TABLE IF NOT EXISTS jupiter_failed_swaps (
+    id String,  -- {tx_hash}:{instruction_index}[:{inner_index}]
+    tx_hash String,
+    instruction_index UInt32,
+    inner_index Nullable(UInt32),
+    program_id LowCardinality(String),
+    slot UInt64,
+    block_time Int64,
+    quoted_amount_in UInt256,
+    quoted_amount_out UInt256,
+    input_mint String,
+    output_mint String,
+    user_wallet String,
+    error_code LowCardinality(String),
+    error_message String,
+    date Date MATERIALIZED toDate(fromUnixTimestamp(block_time))
+) ENGINE = MergeTree()
+PARTITION BY toYYYYMM(date)
+ORDER BY (error_code, block_time, tx_hash)
+SETTINGS index_granularity = 8192;
+
+-- Route hops per swap (one row per AMM hop, from v6 SwapEvents)
+CREATE TABLE IF NOT EXISTS swap_route_hops (
+    swap_id String,
+    hop_index UInt32,
+    tx_hash String,
+    slot UInt64,
+    block_time Int64,
+    percent UInt32,
+    amm LowCardinality(String),  -- AMM program ID that filled the hop
+    dex LowCardinality(String),
+    input_mint String,
+    output_mint String,
+    input_amount UInt256,
+    output_amount UInt256,
+    date Date MATERIALIZED toDate(fromUnixTimestamp(block_time))
+) ENGINE = MergeTree()
+PARTITION BY toYYYYMM(date)
+ORDER BY (amm, block_time, swap_id, hop_index)
+SETTINGS index_granularity = 8192;
+
 --------------------------------------------------------------------------------
 -- OHLCV CANDLES
 --------------------------------------------------------------------------------
@@ -45,6 +102,12 @@ CREATE TABLE IF NOT EXISTS candles (
     volume_in UInt256,
     volume_out UInt256,
     trade_count Int64,
+    open_normalized Nullable(Float64),
+    close_normalized Nullable(Float64),
+    high_normalized Nullable(Float64),
+    low_normalized Nullable(Float64),
+    volume_in_normalized Float64,
+    volume_out_normalized Float64,
     updated_at DateTime64(3) DEFAULT now64()
 ) ENGINE = ReplacingMergeTree(updated_at)
 PARTITION BY toYYYYMM(toDate(fromUnixTimestamp(timestamp)))
@@ -78,11 +141,47 @@ CREATE TABLE IF NOT EXISTS token_stats (
     total_swaps_as_output Int64,
     total_volume_as_input UInt256,
     total_volume_as_output UInt256,
+    total_volume_as_input_normalized Float64,
+    total_volume_as_output_normalized Float64,
     last_seen_slot UInt64,
     updated_at DateTime64(3) DEFAULT now64()
 ) ENGINE = ReplacingMergeTree(updated_at)
 ORDER BY mint_address;
 
+--------------------------------------------------------------------------------
+-- TOKEN METADATA
+--------------------------------------------------------------------------------
+
+CREATE TABLE IF NOT EXISTS token_metadata (
+    mint String,
+    metadata_account String,
+    program_id LowCardinality(String),
+    name String,
+    symbol String,
+    uri String,
+    update_authority String,
+    last_updated_slot UInt64,
+    updated_at DateTime64(3) DEFAULT now64()
+) ENGINE = ReplacingMergeTree(updated_at)
+ORDER BY mint;
+
+--------------------------------------------------------------------------------
+-- ORACLE PRICES
+--------------------------------------------------------------------------------
+
+CREATE TABLE IF NOT EXISTS oracle_prices (
+    tx_hash String,
+    feed_id String,
+    publish_time Int64,
+    mint String,
+    price Float64,
+    confidence Float64,
+    ema_price Float64,
+    price_account String,
+    slot UInt64
+) ENGINE = ReplacingMergeTree()
+ORDER BY (mint, publish_time, feed_id, tx_hash);
+
 --------------------------------------------------------------------------------
 -- TRADER STATISTICS
 --------------------------------------------------------------------------------
@@ -128,6 +227,30 @@ CREATE TABLE IF NOT EXISTS program_stats (
 ) ENGINE = SummingMergeTree()
 ORDER BY program_id;
 
+--------------------------------------------------------------------------------
+-- DEX STATISTICS
+--------------------------------------------------------------------------------
+
+-- Daily routed hops and USD volume per DEX; hop amounts are raw base units of
+-- different mints, so each hop is valued at its input (else output) mint's last price
+CREATE TABLE IF NOT EXISTS dex_daily_stats (
+    date String,
+    dex LowCardinality(String),
+    hop_count Int64,
+    volume_usd Float64
+) ENGINE = SummingMergeTree()
+ORDER BY (date, dex);
+
+-- Daily Jupiter swaps and USD volume per invoking program ("direct" for top-level
+-- swaps); each swap valued at its input (else output) mint's last price
+CREATE TABLE IF NOT EXISTS integrator_daily_stats (
+    date String,
+    caller_program String,
+    swap_count Int64,
+    volume_usd Float64
+) ENGINE = SummingMergeTree()
+ORDER BY (date, caller_program);
+
 --------------------------------------------------------------------------------
 -- PROTOCOL METRICS
 --------------------------------------------------------------------------------
@@ -214,7 +337,7 @@ AS SELECT
     sumState(amount_out) AS volume_out_state,
     countState() AS trade_count_state
 FROM jupiter_swaps
-WHERE amount_in > 0
+WHERE amount_in > 0 AND NOT price_outlier
 GROUP BY pair_id, input_mint, output_mint, candle_time;
 
 -- Materialized view: Hourly candles
@@ -235,7 +358,7 @@ AS SELECT
     sumState(amount_out) AS volume_out_state,
     countState() AS trade_count_state
 FROM jupiter_swaps
-WHERE amount_in > 0
+WHERE amount_in > 0 AND NOT price_outlier
 GROUP BY pair_id, input_mint, output_mint, candle_time;
 
 -- Materialized view: Daily candles
@@ -256,7 +379,7 @@ AS SELECT
     sumState(amount_out) AS volume_out_state,
     countState() AS trade_count_state
 FROM jupiter_swaps
-WHERE amount_in > 0
+WHERE amount_in > 0 AND NOT price_outlier
 GROUP BY pair_id, input_mint, output_mint, candle_time;
 
 --------------------------------------------------------------------------------
@@ -365,6 +488,19 @@ FROM program_stats
 GROUP BY program_id
 ORDER BY total_volume DESC;
 
+-- View: DEX market share of routed hops and USD volume (last 7 days)
+CREATE OR REPLACE VIEW v_dex_market_share_7d AS
+SELECT
+    dex,
+    sum(hop_count) AS total_hops,
+    round(100 * total_hops / sum(total_hops) OVER (), 2) AS hop_share_pct,
+    sum(volume_usd) AS total_volume_usd,
+    round(100 * total_volume_usd / sum(total_volume_usd) OVER (), 2) AS volume_share_pct
+FROM dex_daily_stats
+WHERE date >= toString(today() - 7)
+GROUP BY dex
+ORDER BY total_volume_usd DESC;
+
 -- View: Daily volume trend
 CREATE OR REPLACE VIEW v_daily_volume_trend AS
 SELECT
@@ -9,16 +9,37 @@
 
 -- Individual swap events (fact table)
 CREATE TABLE IF NOT EXISTS jupiter_swaps (
-    id VARCHAR(256) PRIMARY KEY,
+    id VARCHAR(256) PRIMARY KEY,  -- {tx_hash}:{instruction_index}[:{inner_index}]
     tx_hash VARCHAR(88) NOT NULL,
+    instruction_index INT NOT NULL,
+    inner_index INT,  -- NULL for top-level instructions
+    -- Invocation context: CPI swaps come from integrators (bots, other aggregators)
+    is_cpi BOOLEAN NOT NULL DEFAULT FALSE,
+    caller_program VARCHAR(44),  -- NULL for top-level instructions
+    stack_height INT,
     program_id VARCHAR(44) NOT NULL,
     slot BIGINT NOT NULL,
     block_time BIGINT NOT NULL,
     amount_in NUMERIC(78,0) NOT NULL,
     amount_out NUMERIC(78,0) NOT NULL,
+    quoted_amount_in NUMERIC(78,0),
+    quoted_amount_out NUMERIC(78,0),
     input_mint VARCHAR(44),
     output_mint VARCHAR(44),
     user_wallet VARCHAR(44),
+    token_program VARCHAR(44),
+    -- Token-2022 fee withheld from the output (TransferCheckedWithFee), excluded from amount_out
+    output_transfer_fee NUMERIC(78,0) DEFAULT 0,
+    -- Amounts in whole tokens, NULL when a mint's decimals are unknown
+    amount_in_normalized NUMERIC,
+    amount_out_normalized NUMERIC,
+    -- Source of the amounts: balance_delta, swap_event, exact_args, heuristic
+    parse_method VARCHAR(16),
+    -- Trades whose price was rejected (kept out of candles and token prices)
+    price_outlier BOOLEAN NOT NULL DEFAULT FALSE,
+    outlier_reason VARCHAR(32),  -- dust, price_deviation
+    -- USD received vs USD paid at Pyth prices, in bps (NULL without fresh oracle prices)
+    execution_vs_oracle_bps DOUBLE PRECISION,
     created_at TIMESTAMP DEFAULT NOW()
 );
 
@@ -31,6 +52,53 @@ CREATE INDEX IF NOT EXISTS idx_swaps_program ON jupiter_swaps(program_id);
 CREATE INDEX IF NOT EXISTS idx_swaps_input_mint ON jupiter_swaps(input_mint);
 CREATE INDEX IF NOT EXISTS idx_swaps_output_mint ON jupiter_swaps(output_mint);
 CREATE INDEX IF NOT EXISTS idx_swaps_pair ON jupiter_swaps(input_mint, output_mint);
+CREATE INDEX IF NOT EXISTS idx_swaps_caller ON jupiter_swaps(caller_program);
+
+-- Failed (reverted) swaps with the decoded program error
+CREATE TABLE IF NOT EXISTS jupiter_failed_swaps (
+    id VARCHAR(256) PRIMARY KEY,  -- {tx_hash}:{instruction_index}[:{inner_index}]
+    tx_hash VARCHAR(88) NOT NULL,
+    instruction_index INT NOT NULL,
+    inner_index INT,
+    program_id VARCHAR(44) NOT NULL,
+    slot BIGINT NOT NULL,
+    block_time BIGINT NOT NULL,
+    quoted_amount_in NUMERIC(78,0),
+    quoted_amount_out NUMERIC(78,0),
+    input_mint VARCHAR(44),
+    output_mint VARCHAR(44),
+    user_wallet VARCHAR(44),
+    error_code VARCHAR(64),
+    error_message TEXT,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_failed_swaps_tx_hash ON jupiter_failed_swaps(tx_hash);
+CREATE INDEX IF NOT EXISTS idx_failed_swaps_block_time ON jupiter_failed_swaps(block_time DESC);
+CREATE INDEX IF NOT EXISTS idx_failed_swaps_user ON jupiter_failed_swaps(user_wallet);
+CREATE INDEX IF NOT EXISTS idx_failed_swaps_error_code ON jupiter_failed_swaps(error_code);
+
+-- Route hops per swap (one row per AMM hop, from v6 SwapEvents)
+CREATE TABLE IF NOT EXISTS swap_route_hops (
+    swap_id VARCHAR(256) NOT NULL,
+    hop_index INT NOT NULL,
+    tx_hash VARCHAR(88) NOT NULL,
+    slot BIGINT NOT NULL,
+    block_time BIGINT NOT NULL,
+    percent INT,
+    amm VARCHAR(44),  -- AMM program ID that filled the hop
+    dex VARCHAR(32),
+    input_mint VARCHAR(44),
+    output_mint VARCHAR(44),
+    input_amount NUMERIC(78,0) NOT NULL,
+    output_amount NUMERIC(78,0) NOT NULL,
+    PRIMARY KEY (swap_id, hop_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_hops_tx_hash ON swap_route_hops(tx_hash);
+CREATE INDEX IF NOT EXISTS idx_hops_block_time ON swap_route_hops(block_time DESC);
+CREATE INDEX IF NOT EXISTS idx_hops_amm ON swap_route_hops(amm);
+CREATE INDEX IF NOT EXISTS idx_hops_dex ON swap_route_hops(dex);
 
 --------------------------------------------------------------------------------
 -- OHLCV CANDLES (Delta Updates)
@@ -51,6 +119,14 @@ CREATE TABLE IF NOT EXISTS candles (
     volume_in NUMERIC(78,0) DEFAULT 0,
     volume_out NUMERIC(78,0) DEFAULT 0,
     trade_count BIGINT DEFAULT 0,
+    -- Output tokens per input token and volumes in whole tokens, from trades
+    -- whose mint decimals are known
+    open_normalized DOUBLE PRECISION,
+    close_normalized DOUBLE PRECISION,
+    high_normalized DOUBLE PRECISION,
+    low_normalized DOUBLE PRECISION,
+    volume_in_normalized NUMERIC DEFAULT 0,
+    volume_out_normalized NUMERIC DEFAULT 0,
     PRIMARY KEY (pair_id, interval_seconds, timestamp)
 );
 
@@ -93,6 +169,9 @@ CREATE TABLE IF NOT EXISTS token_stats (
     total_swaps_as_output BIGINT DEFAULT 0,
     total_volume_as_input NUMERIC(78,0) DEFAULT 0,
     total_volume_as_output NUMERIC(78,0) DEFAULT 0,
+    -- Volumes in whole tokens, from trades whose mint decimals are known
+    total_volume_as_input_normalized NUMERIC DEFAULT 0,
+    total_volume_as_output_normalized NUMERIC DEFAULT 0,
     last_seen_slot BIGINT,
     created_at TIMESTAMP DEFAULT NOW()
 );
@@ -100,6 +179,46 @@ CREATE TABLE IF NOT EXISTS token_stats (
 CREATE INDEX IF NOT EXISTS idx_token_volume_in ON token_stats(total_volume_as_input DESC);
 CREATE INDEX IF NOT EXISTS idx_token_swaps ON token_stats(total_swaps_as_input DESC);
 
+--------------------------------------------------------------------------------
+-- TOKEN METADATA
+--------------------------------------------------------------------------------
+
+-- Name, symbol and uri per mint from Metaplex Token Metadata or the Token-2022
+-- metadata extension; columns keep their value until an instruction changes them
+CREATE TABLE IF NOT EXISTS token_metadata (
+    mint VARCHAR(44) PRIMARY KEY,
+    metadata_account VARCHAR(44),
+    program_id VARCHAR(44),
+    name TEXT,
+    symbol TEXT,
+    uri TEXT,
+    update_authority VARCHAR(44),
+    last_updated_slot BIGINT,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_metadata_symbol ON token_metadata(symbol);
+
+--------------------------------------------------------------------------------
+-- ORACLE PRICES
+--------------------------------------------------------------------------------
+
+-- Pyth price updates of the feeds mapped to a mint in the map_oracle_prices params
+CREATE TABLE IF NOT EXISTS oracle_prices (
+    tx_hash VARCHAR(88) NOT NULL,
+    feed_id VARCHAR(64) NOT NULL,
+    publish_time BIGINT NOT NULL,
+    mint VARCHAR(44) NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    confidence DOUBLE PRECISION,
+    ema_price DOUBLE PRECISION,
+    price_account VARCHAR(44),
+    slot BIGINT NOT NULL,
+    PRIMARY KEY (tx_hash, feed_id, publish_time)
+);
+
+CREATE INDEX IF NOT EXISTS idx_oracle_prices_mint_time ON oracle_prices(mint, publish_time DESC);
+
 --------------------------------------------------------------------------------
 -- TRADER STATISTICS (Delta Updates)
 --------------------------------------------------------------------------------
@@ -154,6 +273,39 @@ CREATE TABLE IF NOT EXISTS program_stats (
     updated_at TIMESTAMP DEFAULT NOW()
 );
 
+--------------------------------------------------------------------------------
+-- DEX STATISTICS (Delta Updates)
+--------------------------------------------------------------------------------
+
+-- Daily routed hops per DEX (from route hops, labels from the AMM registry).
+-- Hop amounts are raw base units of different mints, so volume is summed in
+-- USD: each hop valued at its input mint's last price, else its output mint's.
+-- Hops without a fresh price count in hop_count but add no volume_usd.
+CREATE TABLE IF NOT EXISTS dex_daily_stats (
+    date VARCHAR(10) NOT NULL,
+    dex VARCHAR(32) NOT NULL,
+    hop_count BIGINT DEFAULT 0,
+    volume_usd NUMERIC DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (date, dex)
+);
+
+CREATE INDEX IF NOT EXISTS idx_dex_daily_dex ON dex_daily_stats(dex, date DESC);
+
+-- Daily Jupiter swaps per invoking program ("direct" for top-level swaps).
+-- Swaps mix mints, so volume is summed in USD: each swap valued at its input
+-- mint's last price, else its output mint's; unpriced swaps add no volume_usd.
+CREATE TABLE IF NOT EXISTS integrator_daily_stats (
+    date VARCHAR(10) NOT NULL,
+    caller_program VARCHAR(44) NOT NULL,
+    swap_count BIGINT DEFAULT 0,
+    volume_usd NUMERIC DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (date, caller_program)
+);
+
+CREATE INDEX IF NOT EXISTS idx_integrator_daily_caller ON integrator_daily_stats(caller_program, date DESC);
+
 --------------------------------------------------------------------------------
 -- PROTOCOL METRICS (Delta Updates)
 --------------------------------------------------------------------------------
@@ -272,6 +424,19 @@ SELECT
 FROM program_stats
 ORDER BY total_volume DESC;
 
+-- DEX market share of routed hops and USD volume (7 days)
+CREATE OR REPLACE VIEW dex_market_share_7d AS
+SELECT
+    dex,
+    SUM(hop_count) AS hop_count,
+    ROUND(100.0 * SUM(hop_count) / NULLIF(SUM(SUM(hop_count)) OVER (), 0), 2) AS hop_share_pct,
+    SUM(volume_usd) AS volume_usd,
+    ROUND(100.0 * SUM(volume_usd) / NULLIF(SUM(SUM(volume_usd)) OVER (), 0), 2) AS volume_share_pct
+FROM dex_daily_stats
+WHERE date >= TO_CHAR(CURRENT_DATE - INTERVAL '7 days', 'YYYY-MM-DD')
+GROUP BY dex
+ORDER BY volume_usd DESC;
+
 -- Candle chart data helper (5-minute candles, last 24 hours)
 CREATE OR REPLACE VIEW candles_5m_24h AS
 SELECT
@@ -0,0 +1,269 @@
+//! AMM registry
+//!
+//! Maps the AMM program IDs named by v6 SwapEvents and Jupiter v6 route plan
+//! `Swap` variants to stable DEX labels, so hop records and per-DEX
+//! aggregations can attribute routed flow to the venue that filled it.
+
+use crate::jupiter_v6::Swap;
+
+/// Label used when neither the program ID nor the route plan identifies a DEX
+pub const UNKNOWN_DEX: &str = "unknown";
+
+/// Raydium AMM v4 Program
+pub const RAYDIUM_AMM_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+
+/// Raydium Concentrated Liquidity Program
+pub const RAYDIUM_CLMM_PROGRAM_ID: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+
+/// Raydium Constant Product (CPMM) Program
+pub const RAYDIUM_CPMM_PROGRAM_ID: &str = "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C";
+
+/// Raydium LaunchLab Program
+pub const RAYDIUM_LAUNCHLAB_PROGRAM_ID: &str = "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj";
+
+/// Orca Whirlpool Program
+pub const ORCA_WHIRLPOOL_PROGRAM_ID: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+
+/// Orca Token Swap v2 Program
+pub const ORCA_TOKEN_SWAP_V2_PROGRAM_ID: &str = "9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP";
+
+/// Meteora DLMM Program
+pub const METEORA_DLMM_PROGRAM_ID: &str = "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo";
+
+/// Meteora Dynamic AMM (pools) Program
+pub const METEORA_POOLS_PROGRAM_ID: &str = "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB";
+
+/// Meteora DAMM v2 Program
+pub const METEORA_DAMM_V2_PROGRAM_ID: &str = "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG";
+
+/// Meteora Dynamic Bonding Curve Program
+pub const METEORA_DBC_PROGRAM_ID: &str = "dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN";
+
+/// Phoenix Program
+pub const PHOENIX_PROGRAM_ID: &str = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY";
+
+/// Lifinity v1 Program
+pub const LIFINITY_PROGRAM_ID: &str = "EewxydAPCCVuNEyrVN68PuSYdQ7wKn27V9Gjeoi8dy3S";
+
+/// Lifinity v2 Program
+pub const LIFINITY_V2_PROGRAM_ID: &str = "2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c";
+
+/// OpenBook (Serum v3 fork) Program
+pub const OPENBOOK_PROGRAM_ID: &str = "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX";
+
+/// OpenBook v2 Program
+pub const OPENBOOK_V2_PROGRAM_ID: &str = "opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb";
+
+/// Serum DEX v3 Program
+pub const SERUM_PROGRAM_ID: &str = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+
+/// Saber Stable Swap Program
+pub const SABER_PROGRAM_ID: &str = "SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ";
+
+/// Mercurial Stable Swap Program
+pub const MERCURIAL_PROGRAM_ID: &str = "MERLuDFBMmsHnsBPZw2sDQZHvXFMwp8EdjudcU2HKky";
+
+/// SPL Token Swap Program
+pub const SPL_TOKEN_SWAP_PROGRAM_ID: &str = "SwaPpA9LAaLfeLi3a68M4DjnLqgtticKg6CnyNwgAC8";
+
+/// Marinade Finance Program
+pub const MARINADE_PROGRAM_ID: &str = "MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD";
+
+/// Pump.fun Bonding Curve Program
+pub const PUMPFUN_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+
+/// Pump.fun AMM (PumpSwap) Program
+pub const PUMPFUN_AMM_PROGRAM_ID: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
+
+/// Moonshot Program
+pub const MOONSHOT_PROGRAM_ID: &str = "MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG";
+
+/// GooseFX Gamma Program
+pub const GOOSEFX_GAMMA_PROGRAM_ID: &str = "GAMMA7meSFWaBXF25oSUgmGRwaW6sCMFLmBNiMSdbHVT";
+
+/// Sanctum Infinity Program
+pub const SANCTUM_INFINITY_PROGRAM_ID: &str = "5ocnV1qiCgaQR8Jb8xWnVbApfaygJ8tNoZfgPwsgx9kx";
+
+/// SolFi Program
+pub const SOLFI_PROGRAM_ID: &str = "SoLFiHG9TfgtdUXUjWAxi3LtvYuFyDLVhBWxdMZxyCe";
+
+/// Obric v2 Program
+pub const OBRIC_V2_PROGRAM_ID: &str = "obriQD1zbpyLz95G5n7nJe6a4DPjpFwa5XYPoNm113y";
+
+/// Stabble Stable Swap Program
+pub const STABBLE_STABLE_SWAP_PROGRAM_ID: &str = "swapNyd8XiQwJ6ianp9snpu4brUqFxadzvHebnAXjJZ";
+
+/// Stabble Weighted Swap Program
+pub const STABBLE_WEIGHTED_SWAP_PROGRAM_ID: &str = "swapFpHZwjELNnjvThjajtiVmkz3yPQEHjLtka2fwHW";
+
+/// Jupiter Perpetuals Program
+pub const JUPITER_PERPS_PROGRAM_ID: &str = "PERPHjGBqRHArX4DySjwM6UJHiR3sWAatqfdBS2qQJu";
+
+/// Resolve the DEX label for an AMM program ID
+pub fn dex_for_program(program_id: &str) -> Option<&'static str> {
+    let dex = match program_id {
+        RAYDIUM_AMM_PROGRAM_ID => "raydium_amm",
+        RAYDIUM_CLMM_PROGRAM_ID => "raydium_clmm",
+        RAYDIUM_CPMM_PROGRAM_ID => "raydium_cpmm",
+        RAYDIUM_LAUNCHLAB_PROGRAM_ID => "raydium_launchlab",
+        ORCA_WHIRLPOOL_PROGRAM_ID => "orca_whirlpool",
+        ORCA_TOKEN_SWAP_V2_PROGRAM_ID => "orca_token_swap",
+        METEORA_DLMM_PROGRAM_ID => "meteora_dlmm",
+        METEORA_POOLS_PROGRAM_ID => "meteora_pools",
+        METEORA_DAMM_V2_PROGRAM_ID => "meteora_damm_v2",
+        METEORA_DBC_PROGRAM_ID => "meteora_dbc",
+        PHOENIX_PROGRAM_ID => "phoenix",
+        LIFINITY_PROGRAM_ID => "lifinity",
+        LIFINITY_V2_PROGRAM_ID => "lifinity_v2",
+        OPENBOOK_PROGRAM_ID => "openbook",
+        OPENBOOK_V2_PROGRAM_ID => "openbook_v2",
+        SERUM_PROGRAM_ID => "serum",
+        SABER_PROGRAM_ID => "saber",
+        MERCURIAL_PROGRAM_ID => "mercurial",
+        SPL_TOKEN_SWAP_PROGRAM_ID => "spl_token_swap",
+        MARINADE_PROGRAM_ID => "marinade",
+        PUMPFUN_PROGRAM_ID => "pumpfun",
+        PUMPFUN_AMM_PROGRAM_ID => "pumpfun_amm",
+        MOONSHOT_PROGRAM_ID => "moonshot",
+        GOOSEFX_GAMMA_PROGRAM_ID => "goosefx_gamma",
+        SANCTUM_INFINITY_PROGRAM_ID => "sanctum_infinity",
+        SOLFI_PROGRAM_ID => "solfi",
+        OBRIC_V2_PROGRAM_ID => "obric",
+        STABBLE_STABLE_SWAP_PROGRAM_ID | STABBLE_WEIGHTED_SWAP_PROGRAM_ID => "stabble",
+        JUPITER_PERPS_PROGRAM_ID => "jupiter_perps",
+        _ => return None,
+    };
+    Some(dex)
+}
+
+/// Resolve the DEX label for a route plan step's `Swap` variant
+///
+/// The match is exhaustive so a new IDL variant cannot be added without
+/// assigning it a label.
+pub fn dex_for_swap(swap: &Swap) -> &'static str {
+    match swap {
+        Swap::Saber | Swap::SaberAddDecimalsDeposit | Swap::SaberAddDecimalsWithdraw => "saber",
+        Swap::TokenSwap => "orca_token_swap",
+        Swap::TokenSwapV2 => "orca_token_swap",
+        Swap::Sencha => "sencha",
+        Swap::Step => "step",
+        Swap::Cropper => "cropper",
+        Swap::Raydium => "raydium_amm",
+        Swap::RaydiumClmm | Swap::RaydiumClmmV2 => "raydium_clmm",
+        Swap::RaydiumCP => "raydium_cpmm",
+        Swap::RaydiumLaunchlabBuy { .. } | Swap::RaydiumLaunchlabSell { .. } => "raydium_launchlab",
+        Swap::Crema { .. } => "crema",
+        Swap::Lifinity => "lifinity",
+        Swap::LifinityV2 => "lifinity_v2",
+        Swap::Mercurial => "mercurial",
+        Swap::Cykura => "cykura",
+        Swap::Serum { .. } => "serum",
+        Swap::Openbook { .. } => "openbook",
+        Swap::OpenBookV2 { .. } => "openbook_v2",
+        Swap::MarinadeDeposit | Swap::MarinadeUnstake => "marinade",
+        Swap::Aldrin { .. } | Swap::AldrinV2 { .. } => "aldrin",
+        Swap::Whirlpool { .. } | Swap::WhirlpoolSwapV2 { .. } => "orca_whirlpool",
+        Swap::Invariant { .. } => "invariant",
+        Swap::Meteora => "meteora_pools",
+        Swap::MeteoraDlmm | Swap::MeteoraDlmmSwapV2 { .. } => "meteora_dlmm",
+        Swap::MeteoraDammV2 => "meteora_damm_v2",
+        Swap::MeteoraDynamicBondingCurveSwap => "meteora_dbc",
+        Swap::GooseFX => "goosefx",
+        Swap::GooseFXV2 | Swap::Gamma => "goosefx_gamma",
+        Swap::DeltaFi { .. } => "deltafi",
+        Swap::Balansol => "balansol",
+        Swap::MarcoPolo { .. } => "marcopolo",
+        Swap::Dradex { .. } => "dradex",
+        Swap::Phoenix { .. } => "phoenix",
+        Swap::Symmetry { .. } => "symmetry",
+        Swap::HeliumTreasuryManagementRedeemV0 => "helium_treasury",
+        Swap::StakeDexStakeWrappedSol
+        | Swap::StakeDexSwapViaStake { .. }
+        | Swap::StakeDexPrefundWithdrawStakeAndDepositStake { .. }
+        | Swap::StakeDexWithdrawWrappedSol => "sanctum_stakedex",
+        Swap::SanctumS { .. }
+        | Swap::SanctumSAddLiquidity { .. }
+        | Swap::SanctumSRemoveLiquidity { .. } => "sanctum_infinity",
+        Swap::Perps
+        | Swap::PerpsAddLiquidity
+        | Swap::PerpsRemoveLiquidity
+        | Swap::PerpsV2
+        | Swap::PerpsV2AddLiquidity
+        | Swap::PerpsV2RemoveLiquidity => "jupiter_perps",
+        Swap::Clone { .. } => "clone",
+        Swap::OneIntro => "one_intro",
+        Swap::PumpdotfunWrappedBuy | Swap::PumpdotfunWrappedSell => "pumpfun",
+        Swap::PumpdotfunAmmBuy | Swap::PumpdotfunAmmSell => "pumpfun_amm",
+        Swap::MoonshotWrappedBuy | Swap::MoonshotWrappedSell => "moonshot",
+        Swap::StabbleStableSwap
+        | Swap::StabbleWeightedSwap
+        | Swap::StabbleStableSwapV2
+        | Swap::StabbleWeightedSwapV2 => "stabble",
+        Swap::Obric { .. } => "obric",
+        Swap::FoxBuyFromEstimatedCost | Swap::FoxClaimPartial { .. } => "fox",
+        Swap::SolFi { .. } => "solfi",
+        Swap::SolayerDelegateNoInit | Swap::SolayerUndelegateNoInit => "solayer",
+        Swap::TokenMill { .. } => "token_mill",
+        Swap::DaosFunBuy | Swap::DaosFunSell => "daosfun",
+        Swap::ZeroFi => "zerofi",
+        Swap::VirtualsBuy | Swap::VirtualsSell => "virtuals",
+        Swap::Perena { .. } => "perena",
+        Swap::Woofi => "woofi",
+        Swap::BoopdotfunWrappedBuy | Swap::BoopdotfunWrappedSell => "boopdotfun",
+        Swap::Plasma { .. } => "plasma",
+        Swap::GoonFi { .. } => "goonfi",
+        Swap::HumidiFi { .. } => "humidifi",
+    }
+}
+
+/// Resolve a hop's DEX label from its AMM program ID, falling back to the route
+/// plan step for programs missing from the registry
+pub fn resolve_dex(swap: Option<&Swap>, program_id: &str) -> &'static str {
+    dex_for_program(program_id)
+        .or_else(|| swap.map(dex_for_swap))
+        .unwrap_or(UNKNOWN_DEX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jupiter_v6::Side;
+
+    #[test]
+    fn test_dex_for_program() {
+        assert_eq!(dex_for_program(RAYDIUM_AMM_PROGRAM_ID), Some("raydium_amm"));
+        assert_eq!(dex_for_program(RAYDIUM_CPMM_PROGRAM_ID), Some("raydium_cpmm"));
+        assert_eq!(dex_for_program(ORCA_WHIRLPOOL_PROGRAM_ID), Some("orca_whirlpool"));
+        assert_eq!(dex_for_program(METEORA_DLMM_PROGRAM_ID), Some("meteora_dlmm"));
+        assert_eq!(dex_for_program("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"), None);
+    }
+
+    #[test]
+    fn test_swap_and_program_labels_agree() {
+        // Variants whose program is registered must resolve to the same label
+        let pairs = [
+            (Swap::Raydium, RAYDIUM_AMM_PROGRAM_ID),
+            (Swap::RaydiumClmmV2, RAYDIUM_CLMM_PROGRAM_ID),
+            (Swap::RaydiumCP, RAYDIUM_CPMM_PROGRAM_ID),
+            (Swap::Whirlpool { a_to_b: true }, ORCA_WHIRLPOOL_PROGRAM_ID),
+            (Swap::MeteoraDlmm, METEORA_DLMM_PROGRAM_ID),
+            (Swap::Meteora, METEORA_POOLS_PROGRAM_ID),
+            (Swap::Phoenix { side: Side::Bid }, PHOENIX_PROGRAM_ID),
+            (Swap::LifinityV2, LIFINITY_V2_PROGRAM_ID),
+            (Swap::PumpdotfunAmmSell, PUMPFUN_AMM_PROGRAM_ID),
+        ];
+
+        for (swap, program_id) in pairs {
+            assert_eq!(Some(dex_for_swap(&swap)), dex_for_program(program_id));
+        }
+    }
+
+    #[test]
+    fn test_resolve_dex_fallbacks() {
+        assert_eq!(resolve_dex(Some(&Swap::MeteoraDammV2), ""), "meteora_damm_v2");
+        // The program ID wins over a route plan step that doesn't match it
+        assert_eq!(resolve_dex(Some(&Swap::Raydium), PHOENIX_PROGRAM_ID), "phoenix");
+        assert_eq!(resolve_dex(None, PHOENIX_PROGRAM_ID), "phoenix");
+        assert_eq!(resolve_dex(None, "unknown_program"), UNKNOWN_DEX);
+    }
+}
@@ -6,6 +6,18 @@
 /// Solana Token Program ID
 pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
 
+/// Token-2022 (Token Extensions) Program ID
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+/// Associated Token Account Program ID
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+/// Metaplex Token Metadata Program ID
+pub const TOKEN_METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+/// Pyth Solana Receiver Program (pull oracle price updates)
+pub const PYTH_RECEIVER_PROGRAM_ID: &str = "rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ";
+
 /// Jupiter v6 Aggregator Program (latest)
 pub const JUPITER_V6_PROGRAM_ID: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
 
@@ -24,6 +36,15 @@ pub const JUPITER_LIMIT_ORDERS_PROGRAM_ID: &str = "jupoNjAxXgZ4rjzxzPMP4oxduvQsQ
 /// Jupiter DCA (Dollar Cost Averaging) Program
 pub const JUPITER_DCA_PROGRAM_ID: &str = "DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M";
 
+/// Wrapped SOL mint
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// USDC mint
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+/// USDT mint
+pub const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H8FYD8qfKYBsBGsN1s98G7P9VP";
+
 /// All Jupiter Program IDs for filtering
 pub const JUPITER_PROGRAM_IDS: [&str; 6] = [
     JUPITER_V6_PROGRAM_ID,
@@ -34,6 +55,12 @@ pub const JUPITER_PROGRAM_IDS: [&str; 6] = [
     JUPITER_DCA_PROGRAM_ID,
 ];
 
+/// Check if a program ID is an SPL token program (Token or Token-2022)
+#[inline]
+pub fn is_token_program(program_id: &str) -> bool {
+    matches!(program_id, TOKEN_PROGRAM_ID | TOKEN_2022_PROGRAM_ID)
+}
+
 /// Check if a program ID is a Jupiter swap program (v2-v6)
 #[inline]
 pub fn is_jupiter_swap_program(program_id: &str) -> bool {
@@ -64,6 +91,16 @@ pub fn is_any_jupiter_program(program_id: &str) -> bool {
     JUPITER_PROGRAM_IDS.contains(&program_id)
 }
 
+/// Rank of a mint as the quote side of a pair: stablecoins over SOL over anything else
+#[inline]
+pub fn quote_rank(mint: &str) -> u8 {
+    match mint {
+        USDC_MINT | USDT_MINT => 2,
+        WSOL_MINT => 1,
+        _ => 0,
+    }
+}
+
 /// Get the Jupiter program version from a program ID
 pub fn get_jupiter_version(program_id: &str) -> Option<&'static str> {
     match program_id {
@@ -98,6 +135,20 @@ mod tests {
         assert!(!is_any_jupiter_program("not_jupiter"));
     }
 
+    #[test]
+    fn test_is_token_program() {
+        assert!(is_token_program(TOKEN_PROGRAM_ID));
+        assert!(is_token_program(TOKEN_2022_PROGRAM_ID));
+        assert!(!is_token_program(JUPITER_V6_PROGRAM_ID));
+    }
+
+    #[test]
+    fn test_quote_rank() {
+        assert!(quote_rank(USDC_MINT) > quote_rank(WSOL_MINT));
+        assert!(quote_rank(WSOL_MINT) > quote_rank("random_mint"));
+        assert_eq!(quote_rank(USDT_MINT), quote_rank(USDC_MINT));
+    }
+
     #[test]
     fn test_get_jupiter_version() {
         assert_eq!(get_jupiter_version(JUPITER_V6_PROGRAM_ID), Some("v6"));
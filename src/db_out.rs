@@ -1,3 +1,6 @@
+// The handler macro's generated export takes one argument per module input
+#![allow(clippy::too_many_arguments)]
+
 //! Database sink module for Jupiter DEX Substreams
 //!
 //! Transforms Jupiter analytics data into DatabaseChanges for SQL sinks.
@@ -5,16 +8,38 @@
 //!
 //! Features:
 //! - Individual swap events
+//! - Failed (reverted) swaps with their error, kept out of volume and candles
+//! - Route hops per swap (AMM venue, percent split, per-hop amounts)
+//! - Per-DEX daily hops and USD volume for routed market share
+//! - Invocation context (top-level or CPI) and daily USD volume per integrator program
 //! - OHLCV candles with delta updates (5min, 1hr, 4hr, 1day)
+//! - Parse method (exact decode, balance delta, SwapEvent or heuristic) of each swap
+//! - Price outliers tagged on the swap and kept out of candles
+//! - Decimal-normalized amounts, prices and volumes alongside the raw base units
 //! - Token pair statistics
 //! - Trader activity tracking
+//! - Token metadata (name, symbol, uri, update authority) per mint
+//! - Pyth oracle prices of mapped mints, and each swap's execution against them
 //! - Protocol-wide metrics
 
-use crate::pb::sf::jupiter::v1::{JupiterAnalytics, TradingDataList};
+use std::str::FromStr;
+
+use crate::oracle_prices::{execution_vs_oracle_bps, oracle_price_key};
+use crate::pb::sf::jupiter::v1::{
+    JupiterAnalytics, OraclePrice, OraclePriceList, OutlierReason, ParseMethod, TokenMetadataList,
+    TokenPriceState, TradingData, TradingDataList,
+};
+use crate::token_metadata::metadata_mint_key;
+use crate::token_price_store::{is_stale, stored_usd_value};
 use substreams::errors::Error;
+use substreams::scalar::BigDecimal;
+use substreams::store::{StoreGet, StoreGetInt64, StoreGetProto, StoreGetString};
 use substreams_database_change::pb::sf::substreams::sink::database::v1::DatabaseChanges;
 use substreams_database_change::tables::Tables;
 
+/// `integrator_daily_stats.caller_program` of swaps invoked as top-level instructions
+const DIRECT_CALLER: &str = "direct";
+
 /// Candle intervals in seconds
 const CANDLE_INTERVALS: [i64; 4] = [
     300,    // 5 minutes
@@ -37,31 +62,147 @@ const CANDLE_INTERVALS: [i64; 4] = [
 pub fn db_out(
     trading_data: TradingDataList,
     analytics: JupiterAnalytics,
+    token_metadata: TokenMetadataList,
+    metadata_mints: StoreGetString,
+    oracle_prices: OraclePriceList,
+    latest_oracle_prices: StoreGetProto<OraclePrice>,
+    last_prices: StoreGetProto<TokenPriceState>,
+    mint_decimals: StoreGetInt64,
 ) -> Result<DatabaseChanges, Error> {
     let mut tables = Tables::new();
 
     // Process individual swap events and candles
     for trade in &trading_data.items {
+        // Unique per instruction: signature, instruction index and inner index
+        let swap_id = &trade.swap_id;
+
+        // Reverted swaps only go to the failed swaps table, even without a quoted
+        // input (token ledger routes, v2/v3 swaps of the whole balance)
+        if !trade.success {
+            if !is_parsed_swap(trade) {
+                continue;
+            }
+            let failed = tables
+                .create_row("jupiter_failed_swaps", swap_id)
+                .set("tx_hash", &trade.transaction_id)
+                .set("instruction_index", trade.instruction_index)
+                .set("program_id", &trade.program_id)
+                .set("slot", trade.slot)
+                .set("block_time", trade.block_time as i64)
+                .set("quoted_amount_in", trade.quoted_amount_in.to_string())
+                .set("quoted_amount_out", trade.quoted_amount_out.to_string())
+                .set("input_mint", &trade.input_mint)
+                .set("output_mint", &trade.output_mint)
+                .set("user_wallet", &trade.user_wallet)
+                .set("error_code", &trade.error_code)
+                .set("error_message", &trade.error_message);
+            // Inner index stays NULL for top-level instructions
+            if let Some(inner_index) = trade.inner_index {
+                failed.set("inner_index", inner_index);
+            }
+            continue;
+        }
+
         // Skip trades with no amount (non-swap instructions)
         if trade.amount_in == 0 {
             continue;
         }
 
-        // Create unique swap ID: transaction_id:slot:program
-        let swap_id = format!("{}:{}:{}", trade.transaction_id, trade.slot, trade.program_id);
-
         // Insert individual swap record
-        tables
-            .create_row("jupiter_swaps", &swap_id)
+        let swap = tables
+            .create_row("jupiter_swaps", swap_id)
             .set("tx_hash", &trade.transaction_id)
+            .set("instruction_index", trade.instruction_index)
             .set("program_id", &trade.program_id)
             .set("slot", trade.slot)
             .set("block_time", trade.block_time as i64)
             .set("amount_in", trade.amount_in.to_string())
             .set("amount_out", trade.amount_out.to_string())
+            .set("quoted_amount_in", trade.quoted_amount_in.to_string())
+            .set("quoted_amount_out", trade.quoted_amount_out.to_string())
             .set("input_mint", &trade.input_mint)
             .set("output_mint", &trade.output_mint)
-            .set("user_wallet", &trade.user_wallet);
+            .set("user_wallet", &trade.user_wallet)
+            .set("token_program", &trade.token_program)
+            .set("output_transfer_fee", trade.output_transfer_fee.to_string())
+            .set("is_cpi", trade.is_cpi)
+            .set("stack_height", trade.stack_height);
+        if let Some(inner_index) = trade.inner_index {
+            swap.set("inner_index", inner_index);
+        }
+        if !trade.caller_program.is_empty() {
+            swap.set("caller_program", &trade.caller_program);
+        }
+        // Normalized amounts stay NULL when a mint's decimals are unknown
+        if !trade.amount_in_normalized.is_empty() {
+            swap.set("amount_in_normalized", &trade.amount_in_normalized);
+        }
+        if !trade.amount_out_normalized.is_empty() {
+            swap.set("amount_out_normalized", &trade.amount_out_normalized);
+        }
+        if let Some(method) = parse_method_label(trade) {
+            swap.set("parse_method", method);
+        }
+        let outlier_reason = outlier_label(trade);
+        swap.set("price_outlier", outlier_reason.is_some());
+        if let Some(reason) = outlier_reason {
+            swap.set("outlier_reason", reason);
+        }
+        // Execution quality needs fresh oracle prices for both mints
+        let oracle_price = |mint: &str| {
+            latest_oracle_prices
+                .get_last(oracle_price_key(mint))
+                .filter(|price| !is_stale(price.publish_time.max(0) as u64, trade.block_time))
+                .map(|price| price.price)
+        };
+        if let (Some(input_price), Some(output_price)) =
+            (oracle_price(&trade.input_mint), oracle_price(&trade.output_mint))
+        {
+            if let Some(bps) = execution_vs_oracle_bps(trade, input_price, output_price) {
+                swap.set("execution_vs_oracle_bps", bps.to_string());
+            }
+        }
+
+        // USD value of an amount at the mint's last price, None when unpriced
+        let value_usd = |mint: &str, amount: u64| {
+            stored_usd_value(mint, amount, trade.block_time, &mint_decimals, &last_prices)
+        };
+
+        // Insert route hops (one row per AMM hop, in execution order)
+        for hop in &trade.hops {
+            tables
+                .create_row(
+                    "swap_route_hops",
+                    [
+                        ("swap_id", swap_id.clone()),
+                        ("hop_index", hop.hop_index.to_string()),
+                    ],
+                )
+                .set("tx_hash", &trade.transaction_id)
+                .set("slot", trade.slot)
+                .set("block_time", trade.block_time as i64)
+                .set("percent", hop.percent)
+                .set("amm", &hop.amm)
+                .set("dex", &hop.dex)
+                .set("input_mint", &hop.input_mint)
+                .set("output_mint", &hop.output_mint)
+                .set("input_amount", hop.input_amount.to_string())
+                .set("output_amount", hop.output_amount.to_string());
+
+            // Per-DEX daily hops and USD volume for market share; hop amounts are
+            // raw base units of different mints, so only their USD value is summed
+            let date = format_date(trade.block_time);
+            let dex_stats = tables
+                .upsert_row("dex_daily_stats", [("date", date.clone()), ("dex", hop.dex.clone())])
+                .set("date", &date)
+                .set("dex", &hop.dex)
+                .add("hop_count", 1i64);
+            if let Some(volume_usd) = value_usd(&hop.input_mint, hop.input_amount)
+                .or_else(|| value_usd(&hop.output_mint, hop.output_amount))
+            {
+                dex_stats.add("volume_usd", volume_usd.to_string());
+            }
+        }
 
         // Calculate "price" as ratio (amount_out / amount_in scaled by 1e6)
         // This gives us a relative price for candle tracking
@@ -71,31 +212,72 @@ pub fn db_out(
             0
         };
 
-        // Build candles for each trading pair at multiple intervals
+        let price_normalized = normalized_price(trade);
+
+        // Build candles for each trading pair at multiple intervals. Outliers are
+        // skipped entirely: a bad high/low can't be undone once upserted.
         let pair_id = format!("{}:{}", trade.input_mint, trade.output_mint);
         let timestamp = trade.block_time as i64;
 
-        for &interval in &CANDLE_INTERVALS {
-            let window_start = (timestamp / interval) * interval;
+        if outlier_reason.is_none() {
+            for &interval in &CANDLE_INTERVALS {
+                let window_start = (timestamp / interval) * interval;
+
+                let candle = tables
+                    .upsert_row(
+                        "candles",
+                        [
+                            ("pair_id", pair_id.clone()),
+                            ("interval_seconds", interval.to_string()),
+                            ("timestamp", window_start.to_string()),
+                        ],
+                    )
+                    .set("input_mint", &trade.input_mint)
+                    .set("output_mint", &trade.output_mint)
+                    .set_if_null("open", price_ratio)
+                    .set("close", price_ratio)
+                    .max("high", price_ratio)
+                    .min("low", price_ratio)
+                    .add("volume_in", trade.amount_in.to_string())
+                    .add("volume_out", trade.amount_out.to_string())
+                    .add("trade_count", 1i64);
+
+                if let Some(price) = &price_normalized {
+                    candle
+                        .set_if_null("open_normalized", price)
+                        .set("close_normalized", price)
+                        .max("high_normalized", price)
+                        .min("low_normalized", price);
+                }
+                if !trade.amount_in_normalized.is_empty() {
+                    candle.add("volume_in_normalized", trade.amount_in_normalized.as_str());
+                }
+                if !trade.amount_out_normalized.is_empty() {
+                    candle.add("volume_out_normalized", trade.amount_out_normalized.as_str());
+                }
+            }
+        }
 
-            tables
-                .upsert_row(
-                    "candles",
-                    [
-                        ("pair_id", pair_id.clone()),
-                        ("interval_seconds", interval.to_string()),
-                        ("timestamp", window_start.to_string()),
-                    ],
-                )
-                .set("input_mint", &trade.input_mint)
-                .set("output_mint", &trade.output_mint)
-                .set_if_null("open", price_ratio)
-                .set("close", price_ratio)
-                .max("high", price_ratio)
-                .min("low", price_ratio)
-                .add("volume_in", trade.amount_in.to_string())
-                .add("volume_out", trade.amount_out.to_string())
-                .add("trade_count", 1i64);
+        // Jupiter USD volume per integrator program (or direct user swaps)
+        let caller_program = if trade.caller_program.is_empty() {
+            DIRECT_CALLER
+        } else {
+            trade.caller_program.as_str()
+        };
+        let date = format_date(trade.block_time);
+        let integrator_stats = tables
+            .upsert_row(
+                "integrator_daily_stats",
+                [("date", date.clone()), ("caller_program", caller_program.to_string())],
+            )
+            .set("date", &date)
+            .set("caller_program", caller_program)
+            .add("swap_count", 1i64);
+        // Swaps of different mints only add up in USD
+        if let Some(volume_usd) = value_usd(&trade.input_mint, trade.amount_in)
+            .or_else(|| value_usd(&trade.output_mint, trade.amount_out))
+        {
+            integrator_stats.add("volume_usd", volume_usd.to_string());
         }
 
         // Update token pair statistics using delta operations
@@ -111,22 +293,28 @@ pub fn db_out(
 
         // Update token statistics (input token)
         if !trade.input_mint.is_empty() {
-            tables
+            let stats = tables
                 .upsert_row("token_stats", &trade.input_mint)
                 .set("mint_address", &trade.input_mint)
                 .add("total_swaps_as_input", 1i64)
                 .add("total_volume_as_input", trade.amount_in.to_string())
                 .set("last_seen_slot", trade.slot);
+            if !trade.amount_in_normalized.is_empty() {
+                stats.add("total_volume_as_input_normalized", trade.amount_in_normalized.as_str());
+            }
         }
 
         // Update token statistics (output token)
         if !trade.output_mint.is_empty() {
-            tables
+            let stats = tables
                 .upsert_row("token_stats", &trade.output_mint)
                 .set("mint_address", &trade.output_mint)
                 .add("total_swaps_as_output", 1i64)
                 .add("total_volume_as_output", trade.amount_out.to_string())
                 .set("last_seen_slot", trade.slot);
+            if !trade.amount_out_normalized.is_empty() {
+                stats.add("total_volume_as_output_normalized", trade.amount_out_normalized.as_str());
+            }
         }
 
         // Update trader (wallet) statistics
@@ -188,9 +376,102 @@ pub fn db_out(
             .max("unique_mints", analytics.unique_mints as i64);
     }
 
+    // Upsert token metadata; fields an instruction didn't change keep their value
+    for metadata in &token_metadata.items {
+        // Updates that name only the metadata account resolve the mint from creation
+        let mint = if metadata.mint.is_empty() {
+            match metadata_mints.get_last(metadata_mint_key(&metadata.metadata_account)) {
+                Some(mint) => mint,
+                None => continue,
+            }
+        } else {
+            metadata.mint.clone()
+        };
+
+        let row = tables
+            .upsert_row("token_metadata", &mint)
+            .set("mint", &mint)
+            .set("metadata_account", &metadata.metadata_account)
+            .set("program_id", &metadata.program_id)
+            .set("last_updated_slot", metadata.slot);
+        for (column, value) in [
+            ("name", &metadata.name),
+            ("symbol", &metadata.symbol),
+            ("uri", &metadata.uri),
+            ("update_authority", &metadata.update_authority),
+        ] {
+            if let Some(value) = value {
+                row.set(column, value);
+            }
+        }
+    }
+
+    // Oracle reference prices of mapped mints (unmapped feeds can't be joined to swaps)
+    for price in oracle_prices.items.iter().filter(|price| !price.mint.is_empty()) {
+        tables
+            .create_row(
+                "oracle_prices",
+                [
+                    ("tx_hash", price.transaction_id.clone()),
+                    ("feed_id", price.feed_id.clone()),
+                    ("publish_time", price.publish_time.to_string()),
+                ],
+            )
+            .set("mint", &price.mint)
+            .set("price", price.price.to_string())
+            .set("confidence", price.confidence.to_string())
+            .set("ema_price", price.ema_price.to_string())
+            .set("price_account", &price.price_account)
+            .set("slot", price.slot);
+    }
+
     Ok(tables.to_database_changes())
 }
 
+/// Output tokens per input token in whole token units
+///
+/// `None` unless both mints' decimals are known. Rounded to 18 significant digits.
+fn normalized_price(trade: &TradingData) -> Option<BigDecimal> {
+    if trade.amount_in == 0 {
+        return None;
+    }
+    let amount_in = BigDecimal::from_str(&trade.amount_in_normalized).ok()?;
+    let amount_out = BigDecimal::from_str(&trade.amount_out_normalized).ok()?;
+
+    Some((amount_out / amount_in).with_prec(18))
+}
+
+/// Whether a trade was parsed as a swap, whatever its amounts
+///
+/// Limit order and DCA instructions, and unknown instructions in strict mode, have
+/// neither a parse method nor mints.
+#[inline]
+fn is_parsed_swap(trade: &TradingData) -> bool {
+    trade.parse_method != ParseMethod::Unspecified as i32
+        || !trade.input_mint.is_empty()
+        || !trade.output_mint.is_empty()
+}
+
+/// Label of a trade's parse method for `jupiter_swaps.parse_method`, `None` when no amounts were parsed
+fn parse_method_label(trade: &TradingData) -> Option<&'static str> {
+    match ParseMethod::try_from(trade.parse_method).unwrap_or(ParseMethod::Unspecified) {
+        ParseMethod::Unspecified => None,
+        ParseMethod::BalanceDelta => Some("balance_delta"),
+        ParseMethod::SwapEvent => Some("swap_event"),
+        ParseMethod::ExactArgs => Some("exact_args"),
+        ParseMethod::Heuristic => Some("heuristic"),
+    }
+}
+
+/// Label of a trade's outlier reason for `jupiter_swaps.outlier_reason`, `None` when accepted
+fn outlier_label(trade: &TradingData) -> Option<&'static str> {
+    match OutlierReason::try_from(trade.outlier_reason).unwrap_or(OutlierReason::None) {
+        OutlierReason::None => None,
+        OutlierReason::Dust => Some("dust"),
+        OutlierReason::PriceDeviation => Some("price_deviation"),
+    }
+}
+
 /// Format Unix timestamp to YYYY-MM-DD date string
 fn format_date(timestamp: u64) -> String {
     let days = timestamp / 86400;
@@ -280,6 +561,58 @@ mod tests {
         assert_eq!(price_ratio, 500_000);
     }
 
+    #[test]
+    fn test_normalized_price() {
+        // 2 USDC (6 decimals) for 0.0125 SOL (9 decimals)
+        let mut trade = TradingData {
+            amount_in: 2_000_000,
+            amount_out: 12_500_000,
+            amount_in_normalized: "2".to_string(),
+            amount_out_normalized: "0.0125".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(normalized_price(&trade), Some(BigDecimal::from_str("0.00625").unwrap()));
+
+        trade.amount_out_normalized.clear();
+        assert_eq!(normalized_price(&trade), None);
+    }
+
+    #[test]
+    fn test_is_parsed_swap() {
+        let mut trade = TradingData::default();
+        assert!(!is_parsed_swap(&trade));
+
+        // A token ledger route quotes no input but is still a swap
+        trade.parse_method = ParseMethod::ExactArgs as i32;
+        assert!(is_parsed_swap(&trade));
+
+        trade.parse_method = ParseMethod::Unspecified as i32;
+        trade.output_mint = "mintB".to_string();
+        assert!(is_parsed_swap(&trade));
+    }
+
+    #[test]
+    fn test_parse_method_label() {
+        let mut trade = TradingData::default();
+        assert_eq!(parse_method_label(&trade), None);
+
+        trade.parse_method = ParseMethod::BalanceDelta as i32;
+        assert_eq!(parse_method_label(&trade), Some("balance_delta"));
+        trade.parse_method = ParseMethod::Heuristic as i32;
+        assert_eq!(parse_method_label(&trade), Some("heuristic"));
+    }
+
+    #[test]
+    fn test_outlier_label() {
+        let mut trade = TradingData::default();
+        assert_eq!(outlier_label(&trade), None);
+
+        trade.outlier_reason = OutlierReason::PriceDeviation as i32;
+        assert_eq!(outlier_label(&trade), Some("price_deviation"));
+        trade.outlier_reason = OutlierReason::Dust as i32;
+        assert_eq!(outlier_label(&trade), Some("dust"));
+    }
+
     #[test]
     fn test_candle_window_calculation() {
         let timestamp: i64 = 1705276800; // 2024-01-15 00:00:00
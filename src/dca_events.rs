@@ -0,0 +1,397 @@
+//! DCA events
+//!
+//! Decodes Jupiter DCA program instructions into `jupiter.events.v1.DCAEvent`
+//! records covering the position lifecycle: open, deposit, fill, withdraw and
+//! close. Filled and refunded amounts come from the token balance deltas of the
+//! position's input and output token accounts.
+//!
+//! Fills don't carry the plan, so `store_dca_positions` keeps the parameters of
+//! every open position, keyed by its DCA account. Both modules number the block's
+//! decoded DCA instructions the same way and use that as the store ordinal, so a
+//! fill reads its position as of its own instruction, even when the position is
+//! opened or closed in the same block.
+
+use std::collections::HashMap;
+
+use crate::constants::is_jupiter_dca;
+use crate::jupiter_dca::{decode_dca, DcaInstruction, OpenDca, Withdrawal};
+use crate::jupiter_v6::DecodeError;
+use crate::pb::jupiter::events::v1::{DcaEvent, DcaEvents, DcaStatus};
+use crate::pb::sf::jupiter::v1::{DcaPosition, DcaPositionList};
+use crate::token_balances::{build_token_balance_index, TokenBalanceChange};
+use crate::transaction_view::TransactionAccounts;
+use substreams::errors::Error;
+use substreams::log;
+use substreams::store::{StoreDelete, StoreGet, StoreGetProto, StoreNew, StoreSet, StoreSetProto};
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
+
+#[substreams::handlers::map]
+pub fn map_dca_positions(block: Block) -> Result<DcaPositionList, Error> {
+    let mut items = Vec::new();
+    let mut ordinal = 0u64;
+
+    for trx in block.transactions() {
+        for instruction in trx.walk_instructions() {
+            if !is_jupiter_dca(&instruction.program_id().to_string()) {
+                continue;
+            }
+            let decoded = match decode_dca_instruction(instruction.data()) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+            ordinal += 1;
+
+            let accounts: Vec<String> = instruction
+                .accounts()
+                .iter()
+                .map(|address| address.to_string())
+                .collect();
+            if let Some(mut position) = position_change(&decoded, &accounts) {
+                position.ordinal = ordinal;
+                items.push(position);
+            }
+        }
+    }
+
+    Ok(DcaPositionList { items })
+}
+
+/// Store handler for the plan parameters of every open DCA position
+///
+/// Key format: `dca:{address}:position`
+/// Value: DcaPosition, deleted when the position is closed
+#[substreams::handlers::store]
+pub fn store_dca_positions(positions: DcaPositionList, store: StoreSetProto<DcaPosition>) {
+    for position in &positions.items {
+        let key = position_key(&position.dca_id);
+        if position.closed {
+            store.delete_prefix(position.ordinal as i64, &key);
+        } else {
+            store.set(position.ordinal, key, position);
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_dca_events(
+    block: Block,
+    positions: StoreGetProto<DcaPosition>,
+) -> Result<DcaEvents, Error> {
+    let mut events = Vec::new();
+    let mut ordinal = 0u64;
+
+    let timestamp = block
+        .block_time
+        .as_ref()
+        .map(|ts| ts.timestamp.max(0) as u64)
+        .unwrap_or_default();
+
+    for trx in block.transactions() {
+        let mut balances: Option<HashMap<String, TokenBalanceChange>> = None;
+
+        for instruction in trx.walk_instructions() {
+            if !is_jupiter_dca(&instruction.program_id().to_string()) {
+                continue;
+            }
+
+            let decoded = match decode_dca_instruction(instruction.data()) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+            ordinal += 1;
+
+            let accounts: Vec<String> = instruction
+                .accounts()
+                .iter()
+                .map(|address| address.to_string())
+                .collect();
+
+            // Only build the balance index for transactions that touch a position
+            let balances = balances.get_or_insert_with(|| {
+                build_token_balance_index(trx, &TransactionAccounts::new(trx))
+            });
+
+            // The position as of this instruction, with the block's earlier opens and closes
+            let open_position = |dca_id: &str| positions.get_at(ordinal, position_key(dca_id));
+
+            let event = to_dca_event(&decoded, &accounts, balances, &open_position, timestamp);
+            if let Some(mut event) = event {
+                event.transaction_signature = trx.id();
+                event.slot = block.slot;
+                event.timestamp = timestamp;
+                events.push(event);
+            }
+        }
+    }
+
+    Ok(DcaEvents {
+        events,
+        block_number: block.slot,
+        block_hash: block.blockhash,
+        timestamp,
+    })
+}
+
+/// Decode a DCA program instruction, `None` for other instructions
+fn decode_dca_instruction(data: &[u8]) -> Option<DcaInstruction> {
+    match decode_dca(data) {
+        Ok(decoded) => Some(decoded),
+        Err(DecodeError::UnknownDiscriminator(_)) | Err(DecodeError::TooShort(_)) => None,
+        Err(err) => {
+            log::info!("Failed to decode Jupiter DCA instruction: {}", err);
+            None
+        }
+    }
+}
+
+/// Position opened or closed by a decoded instruction, `None` for other instructions
+fn position_change(decoded: &DcaInstruction, accounts: &[String]) -> Option<DcaPosition> {
+    let dca_id = decoded.accounts(accounts)?.dca.to_string();
+    match decoded {
+        DcaInstruction::OpenDca(open) | DcaInstruction::OpenDcaV2(open) => Some(DcaPosition {
+            dca_id,
+            in_amount_per_cycle: open.in_amount_per_cycle,
+            cycle_frequency: open.cycle_frequency.max(0) as u64,
+            ..Default::default()
+        }),
+        DcaInstruction::CloseDca => Some(DcaPosition {
+            dca_id,
+            closed: true,
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// Key of a position's plan parameters in `store_dca_positions`
+#[inline]
+pub fn position_key(dca_id: &str) -> String {
+    format!("dca:{}:position", dca_id)
+}
+
+/// Build a DCAEvent from a decoded instruction (extracted for testability)
+///
+/// Returns `None` for `initiate_flash_fill`; the fill is reported once by the
+/// `fulfill_flash_fill` that completes it. `open_position` looks up the plan
+/// parameters of a position opened in an earlier instruction.
+fn to_dca_event(
+    decoded: &DcaInstruction,
+    accounts: &[String],
+    balances: &HashMap<String, TokenBalanceChange>,
+    open_position: &dyn Fn(&str) -> Option<DcaPosition>,
+    block_time: u64,
+) -> Option<DcaEvent> {
+    let dca_accounts = decoded.accounts(accounts)?;
+    let in_ata = dca_accounts.in_ata.and_then(|address| balances.get(address));
+    let out_ata = dca_accounts.out_ata.and_then(|address| balances.get(address));
+
+    let mut event = DcaEvent {
+        user: dca_accounts.user.unwrap_or_default().to_string(),
+        dca_id: dca_accounts.dca.to_string(),
+        input_mint: position_mint(dca_accounts.input_mint, in_ata),
+        output_mint: position_mint(dca_accounts.output_mint, out_ata),
+        instruction: decoded.as_str().to_string(),
+        status: DcaStatus::Active as i32,
+        ..Default::default()
+    };
+
+    match decoded {
+        DcaInstruction::OpenDca(open) | DcaInstruction::OpenDcaV2(open) => {
+            apply_open(&mut event, open, block_time);
+        }
+        DcaInstruction::InitiateFlashFill => return None,
+        DcaInstruction::FulfillFlashFill(args) => {
+            // One cycle: input leaves the position, output (repaid by the keeper) arrives
+            event.in_amount = in_ata.map(TokenBalanceChange::decrease).unwrap_or_default();
+            event.out_amount = out_ata.map(TokenBalanceChange::increase).unwrap_or(args.repay_amount);
+            if in_ata.is_some_and(|change| change.post == 0) {
+                event.status = DcaStatus::Completed as i32;
+            }
+            // Plan parameters stay unknown for positions opened before the initial block
+            if let Some(position) = open_position(&event.dca_id) {
+                event.amount_per_interval = position.in_amount_per_cycle;
+                event.interval_seconds = position.cycle_frequency;
+                if event.status == DcaStatus::Active as i32 {
+                    event.next_execution = block_time + position.cycle_frequency;
+                }
+            }
+        }
+        DcaInstruction::CloseDca => {
+            // Closing with unspent input is a cancellation, otherwise the plan ran out
+            event.in_amount = in_ata.map(TokenBalanceChange::decrease).unwrap_or_default();
+            event.out_amount = out_ata.map(TokenBalanceChange::decrease).unwrap_or_default();
+            event.status = if event.in_amount > 0 {
+                DcaStatus::Cancelled as i32
+            } else {
+                DcaStatus::Completed as i32
+            };
+        }
+        DcaInstruction::Withdraw(args) => match args.withdrawal {
+            Withdrawal::In => event.in_amount = args.withdraw_amount,
+            Withdrawal::Out => event.out_amount = args.withdraw_amount,
+        },
+        DcaInstruction::Deposit(args) => event.in_amount = args.deposit_in,
+    }
+
+    Some(event)
+}
+
+/// Apply the plan parameters of an `open_dca`/`open_dca_v2`
+///
+/// Without `start_at` the first cycle is executable immediately.
+fn apply_open(event: &mut DcaEvent, open: &OpenDca, block_time: u64) {
+    event.in_amount = open.in_amount;
+    event.amount_per_interval = open.in_amount_per_cycle;
+    event.interval_seconds = open.cycle_frequency.max(0) as u64;
+    event.next_execution = open
+        .start_at
+        .map(|start_at| start_at.max(0) as u64)
+        .unwrap_or(block_time);
+}
+
+/// Mint from the instruction accounts, falling back to the position token account
+#[inline]
+fn position_mint(mint: Option<&str>, token_account: Option<&TokenBalanceChange>) -> String {
+    match (mint, token_account) {
+        (Some(mint), _) => mint.to_string(),
+        (None, Some(change)) => change.mint.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jupiter_dca::{DepositArgs, FulfillFlashFillArgs};
+
+    fn create_test_accounts(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("account{}", i)).collect()
+    }
+
+    fn balance_change(mint: &str, pre: u64, post: u64) -> TokenBalanceChange {
+        TokenBalanceChange {
+            mint: mint.to_string(),
+            owner: "dca".to_string(),
+            pre,
+            post,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_open_dca_event() {
+        let decoded = DcaInstruction::OpenDcaV2(OpenDca {
+            in_amount: 10_000_000,
+            in_amount_per_cycle: 1_000_000,
+            cycle_frequency: 3_600,
+            start_at: None,
+        });
+
+        let accounts = create_test_accounts(12);
+        let event =
+            to_dca_event(&decoded, &accounts, &HashMap::new(), &|_| None, 1_700_000_000).unwrap();
+
+        assert_eq!(event.dca_id, "account0");
+        assert_eq!(event.user, "account1");
+        assert_eq!(event.input_mint, "account3");
+        assert_eq!(event.output_mint, "account4");
+        assert_eq!(event.in_amount, 10_000_000);
+        assert_eq!(event.amount_per_interval, 1_000_000);
+        assert_eq!(event.interval_seconds, 3_600);
+        assert_eq!(event.next_execution, 1_700_000_000);
+        assert_eq!(event.instruction, "open_dca_v2");
+        assert_eq!(event.status, DcaStatus::Active as i32);
+    }
+
+    #[test]
+    fn test_fulfill_flash_fill_uses_balance_deltas() {
+        let decoded = DcaInstruction::FulfillFlashFill(FulfillFlashFillArgs { repay_amount: 1 });
+        let accounts = create_test_accounts(12);
+        let mut balances = HashMap::new();
+        balances.insert("account5".to_string(), balance_change("mintA", 2_000, 1_000));
+        balances.insert("account6".to_string(), balance_change("mintB", 0, 48_000));
+
+        let open_position = |dca_id: &str| {
+            (dca_id == "account1").then(|| DcaPosition {
+                dca_id: dca_id.to_string(),
+                in_amount_per_cycle: 1_000,
+                cycle_frequency: 3_600,
+                ..Default::default()
+            })
+        };
+
+        let event =
+            to_dca_event(&decoded, &accounts, &balances, &open_position, 1_700_000_000).unwrap();
+
+        assert_eq!(event.user, "");
+        assert_eq!(event.dca_id, "account1");
+        assert_eq!(event.in_amount, 1_000);
+        assert_eq!(event.out_amount, 48_000);
+        assert_eq!(event.status, DcaStatus::Active as i32);
+        // Plan parameters from the stored position
+        assert_eq!(event.amount_per_interval, 1_000);
+        assert_eq!(event.interval_seconds, 3_600);
+        assert_eq!(event.next_execution, 1_700_003_600);
+
+        // Positions opened before the initial block have no stored plan
+        let event = to_dca_event(&decoded, &accounts, &balances, &|_| None, 1_700_000_000).unwrap();
+        assert_eq!(event.amount_per_interval, 0);
+        assert_eq!(event.next_execution, 0);
+
+        // Last cycle drains the input account: no next execution
+        balances.insert("account5".to_string(), balance_change("mintA", 1_000, 0));
+        let event =
+            to_dca_event(&decoded, &accounts, &balances, &open_position, 1_700_000_000).unwrap();
+        assert_eq!(event.status, DcaStatus::Completed as i32);
+        assert_eq!(event.interval_seconds, 3_600);
+        assert_eq!(event.next_execution, 0);
+    }
+
+    #[test]
+    fn test_close_and_deposit_events() {
+        let accounts = create_test_accounts(8);
+        let mut balances = HashMap::new();
+        balances.insert("account4".to_string(), balance_change("mintA", 3_000, 0));
+        balances.insert("account5".to_string(), balance_change("mintB", 500, 0));
+
+        let close =
+            to_dca_event(&DcaInstruction::CloseDca, &accounts, &balances, &|_| None, 0).unwrap();
+        assert_eq!(close.in_amount, 3_000);
+        assert_eq!(close.out_amount, 500);
+        assert_eq!(close.status, DcaStatus::Cancelled as i32);
+
+        let deposit = DcaInstruction::Deposit(DepositArgs { deposit_in: 700 });
+        assert_eq!(deposit.accounts(&accounts).unwrap().in_ata, Some("account2"));
+        let event = to_dca_event(&deposit, &accounts, &HashMap::new(), &|_| None, 0).unwrap();
+        assert_eq!(event.in_amount, 700);
+
+        let flash_fill = DcaInstruction::InitiateFlashFill;
+        assert!(to_dca_event(&flash_fill, &accounts, &balances, &|_| None, 0).is_none());
+    }
+
+    #[test]
+    fn test_position_changes() {
+        let open = DcaInstruction::OpenDca(OpenDca {
+            in_amount: 10_000_000,
+            in_amount_per_cycle: 1_000_000,
+            cycle_frequency: 86_400,
+            start_at: Some(1_700_000_000),
+        });
+        let accounts = create_test_accounts(12);
+
+        let position = position_change(&open, &accounts).unwrap();
+        assert_eq!(position.dca_id, "account0");
+        assert_eq!(position.in_amount_per_cycle, 1_000_000);
+        assert_eq!(position.cycle_frequency, 86_400);
+        assert!(!position.closed);
+
+        // close_dca names the position second
+        let close = position_change(&DcaInstruction::CloseDca, &accounts).unwrap();
+        assert_eq!(close.dca_id, "account1");
+        assert!(close.closed);
+
+        let deposit = DcaInstruction::Deposit(DepositArgs { deposit_in: 700 });
+        assert!(position_change(&deposit, &accounts).is_none());
+        assert_eq!(position_key("account0"), "dca:account0:position");
+    }
+}
@@ -96,6 +96,9 @@ mod tests {
             amount_out: amount_in / 2,
             input_mint: "input_mint".to_string(),
             output_mint: "output_mint".to_string(),
+            instruction_index: 0,
+            inner_index: None,
+            swap_id: "test_tx_123:0".to_string(),
         }
     }
 
@@ -0,0 +1,330 @@
+//! Jupiter DCA instruction decoder
+//!
+//! Decodes the position lifecycle instructions of the DCA program
+//! (`open_dca`/`open_dca_v2`, `initiate_flash_fill`/`fulfill_flash_fill`,
+//! `deposit`, `withdraw`, `close_dca`) together with the accounts each one
+//! touches. Decoding is strict, like the v6 route decoder.
+
+use borsh::BorshDeserialize;
+
+use crate::jupiter_v6::{deserialize, DecodeError};
+
+// DCA instruction discriminators (sha256("global:<name>")[..8])
+pub const OPEN_DCA_DISCRIMINATOR: [u8; 8] = [36, 65, 185, 54, 1, 210, 100, 163];
+pub const OPEN_DCA_V2_DISCRIMINATOR: [u8; 8] = [142, 119, 43, 109, 162, 52, 11, 177];
+pub const INITIATE_FLASH_FILL_DISCRIMINATOR: [u8; 8] = [143, 205, 3, 191, 162, 215, 245, 49];
+pub const FULFILL_FLASH_FILL_DISCRIMINATOR: [u8; 8] = [115, 64, 226, 78, 33, 211, 105, 162];
+pub const CLOSE_DCA_DISCRIMINATOR: [u8; 8] = [22, 7, 33, 98, 168, 183, 34, 243];
+pub const WITHDRAW_DISCRIMINATOR: [u8; 8] = [183, 18, 70, 156, 148, 109, 161, 34];
+pub const DEPOSIT_DISCRIMINATOR: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+
+/// Arguments of `open_dca`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OpenDcaArgs {
+    pub application_idx: u64,
+    pub in_amount: u64,
+    pub in_amount_per_cycle: u64,
+    pub cycle_frequency: i64,
+    pub min_out_amount: Option<u64>,
+    pub max_out_amount: Option<u64>,
+    pub start_at: Option<i64>,
+    pub close_wsol_in_ata: Option<bool>,
+}
+
+/// Arguments of `open_dca_v2`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OpenDcaV2Args {
+    pub application_idx: u64,
+    pub in_amount: u64,
+    pub in_amount_per_cycle: u64,
+    pub cycle_frequency: i64,
+    pub min_out_amount: Option<u64>,
+    pub max_out_amount: Option<u64>,
+    pub start_at: Option<i64>,
+}
+
+/// Arguments of `fulfill_flash_fill`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FulfillFlashFillArgs {
+    pub repay_amount: u64,
+}
+
+/// Arguments of `deposit`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DepositArgs {
+    pub deposit_in: u64,
+}
+
+/// Which side of the position a `withdraw` takes from
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Withdrawal {
+    In,
+    Out,
+}
+
+/// Arguments of `withdraw`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawArgs {
+    pub withdraw_amount: u64,
+    pub withdrawal: Withdrawal,
+}
+
+/// `open_dca` and `open_dca_v2` normalized to the fields both carry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenDca {
+    pub in_amount: u64,
+    pub in_amount_per_cycle: u64,
+    pub cycle_frequency: i64,
+    pub start_at: Option<i64>,
+}
+
+/// Decoded DCA program instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DcaInstruction {
+    OpenDca(OpenDca),
+    OpenDcaV2(OpenDca),
+    InitiateFlashFill,
+    FulfillFlashFill(FulfillFlashFillArgs),
+    CloseDca,
+    Withdraw(WithdrawArgs),
+    Deposit(DepositArgs),
+}
+
+impl DcaInstruction {
+    /// Stable name matching the IDL instruction name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DcaInstruction::OpenDca(_) => "open_dca",
+            DcaInstruction::OpenDcaV2(_) => "open_dca_v2",
+            DcaInstruction::InitiateFlashFill => "initiate_flash_fill",
+            DcaInstruction::FulfillFlashFill(_) => "fulfill_flash_fill",
+            DcaInstruction::CloseDca => "close_dca",
+            DcaInstruction::Withdraw(_) => "withdraw",
+            DcaInstruction::Deposit(_) => "deposit",
+        }
+    }
+
+    /// Resolve the position accounts from the instruction's account list
+    ///
+    /// open_dca layout:
+    /// [0] dca, [1] user, [2] input_mint, [3] output_mint, [4] user_ata, [5] in_ata, [6] out_ata
+    ///
+    /// open_dca_v2 layout:
+    /// [0] dca, [1] user, [2] payer, [3] input_mint, [4] output_mint, [5] user_ata,
+    /// [6] in_ata, [7] out_ata
+    ///
+    /// initiate_flash_fill layout:
+    /// [0] keeper, [1] dca, [2] input_mint, [3] keeper_in_ata, [4] in_ata, [5] out_ata
+    ///
+    /// fulfill_flash_fill layout:
+    /// [0] keeper, [1] dca, [2] input_mint, [3] output_mint, [4] keeper_in_ata,
+    /// [5] in_ata, [6] out_ata
+    ///
+    /// close_dca layout:
+    /// [0] user, [1] dca, [2] input_mint, [3] output_mint, [4] in_ata, [5] out_ata
+    ///
+    /// withdraw layout:
+    /// [0] user, [1] dca, [2] input_mint, [3] output_mint, [4] dca_ata (in or out
+    /// side, per `Withdrawal`)
+    ///
+    /// deposit layout:
+    /// [0] user, [1] dca, [2] in_ata, [3] user_in_ata
+    pub fn accounts<'a>(&self, accounts: &'a [String]) -> Option<DcaAccounts<'a>> {
+        let get = |index: usize| accounts.get(index).map(String::as_str);
+
+        let dca_accounts = match self {
+            DcaInstruction::OpenDca(_) => DcaAccounts {
+                dca: get(0)?,
+                user: get(1),
+                input_mint: get(2),
+                output_mint: get(3),
+                in_ata: Some(get(5)?),
+                out_ata: Some(get(6)?),
+            },
+            DcaInstruction::OpenDcaV2(_) => DcaAccounts {
+                dca: get(0)?,
+                user: get(1),
+                input_mint: get(3),
+                output_mint: get(4),
+                in_ata: Some(get(6)?),
+                out_ata: Some(get(7)?),
+            },
+            DcaInstruction::InitiateFlashFill => DcaAccounts {
+                dca: get(1)?,
+                user: None,
+                input_mint: get(2),
+                output_mint: None,
+                in_ata: Some(get(4)?),
+                out_ata: Some(get(5)?),
+            },
+            DcaInstruction::FulfillFlashFill(_) => DcaAccounts {
+                dca: get(1)?,
+                user: None,
+                input_mint: get(2),
+                output_mint: get(3),
+                in_ata: Some(get(5)?),
+                out_ata: Some(get(6)?),
+            },
+            DcaInstruction::CloseDca => DcaAccounts {
+                dca: get(1)?,
+                user: get(0),
+                input_mint: get(2),
+                output_mint: get(3),
+                in_ata: Some(get(4)?),
+                out_ata: Some(get(5)?),
+            },
+            DcaInstruction::Withdraw(args) => {
+                let dca_ata = get(4)?;
+                DcaAccounts {
+                    dca: get(1)?,
+                    user: get(0),
+                    input_mint: get(2),
+                    output_mint: get(3),
+                    in_ata: (args.withdrawal == Withdrawal::In).then_some(dca_ata),
+                    out_ata: (args.withdrawal == Withdrawal::Out).then_some(dca_ata),
+                }
+            }
+            DcaInstruction::Deposit(_) => DcaAccounts {
+                dca: get(1)?,
+                user: get(0),
+                input_mint: None,
+                output_mint: None,
+                in_ata: Some(get(2)?),
+                out_ata: None,
+            },
+        };
+
+        Some(dca_accounts)
+    }
+}
+
+/// Position accounts of a DCA instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DcaAccounts<'a> {
+    /// DCA position account, used as the position id
+    pub dca: &'a str,
+    /// Position owner; keeper fills do not pass it
+    pub user: Option<&'a str>,
+    pub input_mint: Option<&'a str>,
+    pub output_mint: Option<&'a str>,
+    /// Position token account holding the unspent input
+    pub in_ata: Option<&'a str>,
+    /// Position token account accumulating the output
+    pub out_ata: Option<&'a str>,
+}
+
+/// Decode a DCA program instruction from raw instruction data
+pub fn decode_dca(data: &[u8]) -> Result<DcaInstruction, DecodeError> {
+    if data.len() < 8 {
+        return Err(DecodeError::TooShort(data.len()));
+    }
+
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&data[0..8]);
+    let args = &data[8..];
+
+    match discriminator {
+        OPEN_DCA_DISCRIMINATOR => {
+            let a: OpenDcaArgs = deserialize(args)?;
+            Ok(DcaInstruction::OpenDca(OpenDca {
+                in_amount: a.in_amount,
+                in_amount_per_cycle: a.in_amount_per_cycle,
+                cycle_frequency: a.cycle_frequency,
+                start_at: a.start_at,
+            }))
+        }
+        OPEN_DCA_V2_DISCRIMINATOR => {
+            let a: OpenDcaV2Args = deserialize(args)?;
+            Ok(DcaInstruction::OpenDcaV2(OpenDca {
+                in_amount: a.in_amount,
+                in_amount_per_cycle: a.in_amount_per_cycle,
+                cycle_frequency: a.cycle_frequency,
+                start_at: a.start_at,
+            }))
+        }
+        INITIATE_FLASH_FILL_DISCRIMINATOR => Ok(DcaInstruction::InitiateFlashFill),
+        FULFILL_FLASH_FILL_DISCRIMINATOR => deserialize(args).map(DcaInstruction::FulfillFlashFill),
+        CLOSE_DCA_DISCRIMINATOR => Ok(DcaInstruction::CloseDca),
+        WITHDRAW_DISCRIMINATOR => deserialize(args).map(DcaInstruction::Withdraw),
+        DEPOSIT_DISCRIMINATOR => deserialize(args).map(DcaInstruction::Deposit),
+        _ => Err(DecodeError::UnknownDiscriminator(discriminator)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_accounts(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("account{}", i)).collect()
+    }
+
+    /// Encode open_dca_v2 args with no optional limits
+    fn create_open_dca_v2_data(start_at: Option<i64>) -> Vec<u8> {
+        let mut data = OPEN_DCA_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&7u64.to_le_bytes());
+        data.extend_from_slice(&10_000_000u64.to_le_bytes());
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&3_600i64.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        match start_at {
+            Some(ts) => {
+                data.push(1);
+                data.extend_from_slice(&ts.to_le_bytes());
+            }
+            None => data.push(0),
+        }
+        data
+    }
+
+    #[test]
+    fn test_decode_open_dca_v2() {
+        let decoded = decode_dca(&create_open_dca_v2_data(Some(1_700_000_000))).unwrap();
+
+        assert_eq!(
+            decoded,
+            DcaInstruction::OpenDcaV2(OpenDca {
+                in_amount: 10_000_000,
+                in_amount_per_cycle: 1_000_000,
+                cycle_frequency: 3_600,
+                start_at: Some(1_700_000_000),
+            })
+        );
+        assert_eq!(decoded.as_str(), "open_dca_v2");
+
+        let accounts = create_test_accounts(12);
+        let dca_accounts = decoded.accounts(&accounts).unwrap();
+        assert_eq!(dca_accounts.dca, "account0");
+        assert_eq!(dca_accounts.user, Some("account1"));
+        assert_eq!(dca_accounts.input_mint, Some("account3"));
+        assert_eq!(dca_accounts.in_ata, Some("account6"));
+    }
+
+    #[test]
+    fn test_decode_withdraw_side() {
+        let mut data = WITHDRAW_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&250u64.to_le_bytes());
+        data.push(1); // Withdrawal::Out
+
+        let decoded = decode_dca(&data).unwrap();
+        let accounts = create_test_accounts(6);
+        let dca_accounts = decoded.accounts(&accounts).unwrap();
+
+        assert_eq!(dca_accounts.in_ata, None);
+        assert_eq!(dca_accounts.out_ata, Some("account4"));
+    }
+
+    #[test]
+    fn test_decode_dca_errors() {
+        assert_eq!(decode_dca(&[0; 4]), Err(DecodeError::TooShort(4)));
+        assert_eq!(decode_dca(&[0xFF; 8]), Err(DecodeError::UnknownDiscriminator([0xFF; 8])));
+
+        let mut data = create_open_dca_v2_data(None);
+        data.truncate(data.len() - 1);
+        assert!(matches!(decode_dca(&data), Err(DecodeError::InvalidArgs(_))));
+
+        // Too few accounts for the fulfill layout
+        let fulfill = DcaInstruction::FulfillFlashFill(FulfillFlashFillArgs { repay_amount: 1 });
+        assert!(fulfill.accounts(&create_test_accounts(6)).is_none());
+    }
+}
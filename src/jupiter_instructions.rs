@@ -2,23 +2,29 @@ use std::collections::{HashMap, HashSet};
 
 use crate::constants::JUPITER_PROGRAM_IDS;
 use crate::pb::sf::jupiter::v1::{
-    AccountOwnerRecords, EnrichedAccount, JupiterInstruction, JupiterInstructions, TokenPriceList,
+    EnrichedAccount, JupiterInstruction, JupiterInstructions, TokenPriceList, TradingData,
     TradingDataList,
 };
+use crate::spl_account_store::{mint_key, owner_key};
+use crate::transaction_view::{
+    instruction_id, references_any, walk_instructions, TransactionAccounts,
+};
 use substreams::errors::Error;
-use substreams_solana::{base58, pb::sf::solana::r#type::v1::Block};
+use substreams::log;
+use substreams::store::{StoreGet, StoreGetString};
+use substreams_solana::base58;
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
 
 #[substreams::handlers::map]
 pub fn map_jupiter_instructions(
     block: Block,
-    owner_records: AccountOwnerRecords,
+    account_owners: StoreGetString,
     trading_data: TradingDataList,
     token_prices: TokenPriceList,
 ) -> Result<JupiterInstructions, Error> {
     // Build indexes once, reuse for all instructions
-    let owner_index = build_owner_index(owner_records);
     let price_index = build_price_index(token_prices);
-    let trades_by_tx = group_trades_by_tx(&trading_data);
+    let trades_by_instruction = index_trades_by_instruction(&trading_data);
 
     // Pre-allocate with estimated capacity
     let mut instructions = Vec::with_capacity(32);
@@ -33,45 +39,55 @@ pub fn map_jupiter_instructions(
 
     let slot = block.slot;
 
-    for trx in block.transactions() {
-        // Cache tx_id once per transaction
+    // Skip transactions that never load a Jupiter program before resolving their keys
+    let jupiter_keys: Vec<Vec<u8>> = JUPITER_PROGRAM_IDS
+        .iter()
+        .filter_map(|program_id| base58::decode(program_id).ok())
+        .collect();
+
+    for trx in block
+        .transactions()
+        .filter(|trx| references_any(trx, &jupiter_keys))
+    {
+        // Cache tx_id and account keys (including lookup table addresses) once per transaction
         let tx_id = trx.id();
-        let trade_data = trades_by_tx.get(&tx_id);
+        let account_keys = TransactionAccounts::new(trx);
 
-        for instruction in trx.walk_instructions() {
-            let program_id_str = instruction.program_id().to_string();
-            if !is_jupiter_program(&program_id_str) {
-                continue;
-            }
+        for instruction in walk_instructions(trx) {
+            let program_id_str = match account_keys.get(instruction.program_id_index) {
+                Some(program_id) if is_jupiter_program(program_id) => program_id.to_string(),
+                _ => continue,
+            };
 
             // Enrich accounts with ownership info
-            let enriched_accounts: Vec<EnrichedAccount> = instruction
-                .accounts()
-                .iter()
-                .map(|address| {
-                    let addr_str = address.to_string();
-                    enrich_account(addr_str, &owner_index, &price_index)
-                })
-                .collect();
+            let enriched_accounts: Vec<EnrichedAccount> = match account_keys
+                .resolve(instruction.accounts)
+            {
+                Some(accounts) => accounts
+                    .into_iter()
+                    .map(|address| enrich_account(address, &account_owners, &price_index))
+                    .collect(),
+                None => {
+                    log::info!("Account index out of range in transaction {}", tx_id);
+                    continue;
+                }
+            };
 
             // Get instruction data
-            let data = instruction.data().to_vec();
+            let data = instruction.data.to_vec();
 
-            // Extract swap info from trading data if available
+            // Swap info of the trade parsed from this exact instruction; other
+            // instructions of the transaction (e.g. SwapEvent self-CPIs) carry none
+            let trade_key = (tx_id.as_str(), instruction.instruction_index, instruction.inner_index);
             let (amount_in, amount_out, input_mint, output_mint) =
-                if let Some(trades) = trade_data {
-                    if let Some(first_trade) = trades.first() {
-                        (
-                            first_trade.amount_in,
-                            first_trade.amount_out,
-                            first_trade.input_mint.clone(),
-                            first_trade.output_mint.clone(),
-                        )
-                    } else {
-                        (0, 0, String::new(), String::new())
-                    }
-                } else {
-                    (0, 0, String::new(), String::new())
+                match trades_by_instruction.get(&trade_key) {
+                    Some(trade) => (
+                        trade.amount_in,
+                        trade.amount_out,
+                        trade.input_mint.clone(),
+                        trade.output_mint.clone(),
+                    ),
+                    None => (0, 0, String::new(), String::new()),
                 };
 
             if amount_in > 0 {
@@ -90,6 +106,9 @@ pub fn map_jupiter_instructions(
                 amount_out,
                 input_mint,
                 output_mint,
+                instruction_index: instruction.instruction_index,
+                inner_index: instruction.inner_index,
+                swap_id: instruction_id(&tx_id, instruction.instruction_index, instruction.inner_index),
             });
         }
     }
@@ -101,21 +120,6 @@ pub fn map_jupiter_instructions(
     })
 }
 
-/// Build an index mapping account addresses to their (owner, mint) pairs
-fn build_owner_index(records: AccountOwnerRecords) -> HashMap<String, (String, String)> {
-    let capacity = records.records.len();
-    let mut index = HashMap::with_capacity(capacity);
-
-    for record in records.records {
-        let account = base58::encode(&record.account);
-        let owner = base58::encode(&record.owner);
-        let mint = base58::encode(&record.mint);
-        index.insert(account, (owner, mint));
-    }
-
-    index
-}
-
 /// Build a set of known token mint addresses for price lookups
 fn build_price_index(token_prices: TokenPriceList) -> HashSet<String> {
     token_prices
@@ -125,30 +129,38 @@ fn build_price_index(token_prices: TokenPriceList) -> HashSet<String> {
         .collect()
 }
 
-/// Group trading data by transaction ID for efficient lookup
-fn group_trades_by_tx(trading_data: &TradingDataList) -> HashMap<String, Vec<&crate::pb::sf::jupiter::v1::TradingData>> {
-    let mut map: HashMap<String, Vec<_>> = HashMap::with_capacity(trading_data.items.len());
-    for trade in &trading_data.items {
-        map.entry(trade.transaction_id.clone())
-            .or_default()
-            .push(trade);
-    }
-    map
+/// (transaction ID, instruction index, inner index) of a trade's instruction
+type InstructionPath<'a> = (&'a str, u32, Option<u32>);
+
+/// Index trading data by the instruction it was parsed from, for an exact join
+fn index_trades_by_instruction(trading_data: &TradingDataList) -> HashMap<InstructionPath<'_>, &TradingData> {
+    trading_data
+        .items
+        .iter()
+        .map(|trade| {
+            let path = (trade.transaction_id.as_str(), trade.instruction_index, trade.inner_index);
+            (path, trade)
+        })
+        .collect()
 }
 
 /// Enrich an account address with ownership and mint information
+///
+/// Owners come from `store_account_owners`, which covers token accounts
+/// initialized in any earlier block as well as this one.
 #[inline]
 fn enrich_account(
     address: String,
-    owner_index: &HashMap<String, (String, String)>,
+    account_owners: &StoreGetString,
     price_index: &HashSet<String>,
 ) -> EnrichedAccount {
     // Check if we have owner info for this account
-    if let Some((owner, mint)) = owner_index.get(&address) {
+    if let Some(owner) = account_owners.get_last(owner_key(&address)) {
+        let mint = account_owners.get_last(mint_key(&address)).unwrap_or_default();
         return EnrichedAccount {
             address,
-            owner: owner.clone(),
-            mint: mint.clone(),
+            owner,
+            mint,
         };
     }
 
@@ -171,3 +183,40 @@ fn enrich_account(
 fn is_jupiter_program(program_id: &str) -> bool {
     JUPITER_PROGRAM_IDS.iter().any(|entry| entry == &program_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_trade(tx: &str, instruction_index: u32, inner_index: Option<u32>, amount_in: u64) -> TradingData {
+        TradingData {
+            transaction_id: tx.to_string(),
+            instruction_index,
+            inner_index,
+            amount_in,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_index_trades_by_instruction() {
+        // Two routes in one transaction, one of them a CPI from another program
+        let trading_data = TradingDataList {
+            items: vec![
+                create_test_trade("tx1", 2, None, 100),
+                create_test_trade("tx1", 3, Some(1), 250),
+                create_test_trade("tx2", 2, None, 7),
+            ],
+            ..Default::default()
+        };
+
+        let index = index_trades_by_instruction(&trading_data);
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index[&("tx1", 2, None)].amount_in, 100);
+        assert_eq!(index[&("tx1", 3, Some(1))].amount_in, 250);
+        assert_eq!(index[&("tx2", 2, None)].amount_in, 7);
+        // The route's SwapEvent self-CPI has no trade of its own
+        assert!(!index.contains_key(&("tx1", 2, Some(0))));
+    }
+}
@@ -0,0 +1,301 @@
+//! Jupiter v2/v3/v4 instruction argument decoders
+//!
+//! The pre-v6 aggregators use different instruction sets and account orders
+//! from v6, so they get their own decoders, chosen by `get_jupiter_version`:
+//!
+//! - v4 has a single `route` instruction taking a recursive `SwapLeg` plan.
+//! - v2 and v3 expose one instruction per AMM (`raydium_swap_v2`, `whirlpool_swap`,
+//!   `serum_swap`, ...), each passing that AMM's own accounts, so user accounts
+//!   have no fixed position and are resolved from signers and token balances.
+//!
+//! Decoding is strict, like the v6 route decoder.
+
+use borsh::BorshDeserialize;
+
+use crate::jupiter_v6::{deserialize, DecodeError, Side, Swap};
+
+// Jupiter v4 route discriminator (same instruction name, and hash, as v6 `route`)
+pub const V4_ROUTE_DISCRIMINATOR: [u8; 8] = [229, 23, 203, 151, 122, 227, 173, 42];
+
+/// Leg of a Jupiter v4 route: a single swap, or a chain/split of nested legs
+///
+/// v6's `Swap` enum kept the v4 variant order, so v4 swaps decode with it.
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SwapLeg {
+    Chain { swap_legs: Vec<SwapLeg> },
+    Split { split_legs: Vec<SplitLeg> },
+    Swap { swap: Swap },
+}
+
+/// Branch of a split leg with its share (0-100) of the input amount
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitLeg {
+    pub percent: u8,
+    pub swap_leg: SwapLeg,
+}
+
+impl SwapLeg {
+    /// Number of AMM swaps in the leg, across all chains and splits
+    pub fn swap_count(&self) -> usize {
+        match self {
+            SwapLeg::Chain { swap_legs } => swap_legs.iter().map(SwapLeg::swap_count).sum(),
+            SwapLeg::Split { split_legs } => {
+                split_legs.iter().map(|split| split.swap_leg.swap_count()).sum()
+            }
+            SwapLeg::Swap { .. } => 1,
+        }
+    }
+}
+
+/// Arguments of the v4 `route` instruction
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct V4RouteArgs {
+    pub swap_leg: SwapLeg,
+    pub in_amount: u64,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// User-facing accounts of a v4 `route`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V4RouteAccounts<'a> {
+    pub user_wallet: &'a str,
+    pub destination_token_account: &'a str,
+}
+
+impl V4RouteArgs {
+    /// Resolve the user-facing accounts of a v4 `route`
+    ///
+    /// Layout: [0] token_program, [1] user_transfer_authority,
+    /// [2] destination_token_account, [3+] AMM accounts of each leg
+    ///
+    /// The source token account is not at a fixed position; recover it from
+    /// the user's token balances.
+    pub fn accounts<'a>(&self, accounts: &'a [String]) -> Option<V4RouteAccounts<'a>> {
+        if accounts.len() < 3 {
+            return None;
+        }
+        Some(V4RouteAccounts {
+            user_wallet: &accounts[1],
+            destination_token_account: &accounts[2],
+        })
+    }
+}
+
+/// Decode a Jupiter v4 `route` instruction
+pub fn decode_v4_route(data: &[u8]) -> Result<V4RouteArgs, DecodeError> {
+    let (discriminator, args) = split_discriminator(data)?;
+    if discriminator != V4_ROUTE_DISCRIMINATOR {
+        return Err(DecodeError::UnknownDiscriminator(discriminator));
+    }
+    deserialize(args)
+}
+
+/// Direction argument that precedes the amounts of some v2/v3 AMM instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirectionArg {
+    None,
+    /// Order book side (Serum, Aldrin, Dradex)
+    Side,
+    /// Pool direction flag (`a_to_b` / `x_to_y`)
+    Bool,
+}
+
+// v2/v3 per-AMM swap instructions: (name, discriminator, direction argument)
+const AMM_SWAP_INSTRUCTIONS: [(&str, [u8; 8], DirectionArg); 23] = [
+    ("mercurial_exchange", [31, 248, 60, 226, 215, 168, 55, 199], DirectionArg::None),
+    ("saber_exchange", [145, 158, 184, 212, 3, 74, 156, 118], DirectionArg::None),
+    ("saber_swap", [64, 62, 98, 226, 52, 74, 37, 178], DirectionArg::None),
+    ("sencha_exchange", [94, 31, 159, 252, 144, 67, 4, 100], DirectionArg::None),
+    ("token_swap", [187, 192, 118, 212, 62, 109, 28, 213], DirectionArg::None),
+    ("step_token_swap", [55, 100, 17, 243, 242, 181, 43, 165], DirectionArg::None),
+    ("cropper_token_swap", [167, 38, 59, 37, 132, 60, 95, 68], DirectionArg::None),
+    ("raydium_swap", [177, 173, 42, 240, 184, 4, 124, 81], DirectionArg::None),
+    ("raydium_swap_v2", [69, 227, 98, 93, 237, 202, 223, 140], DirectionArg::None),
+    ("crema_token_swap", [235, 160, 175, 122, 61, 177, 2, 247], DirectionArg::None),
+    ("lifinity_token_swap", [0, 49, 246, 1, 36, 153, 11, 93], DirectionArg::None),
+    ("cykura_swap", [38, 241, 21, 107, 120, 59, 184, 249], DirectionArg::None),
+    ("meteora_swap", [127, 125, 226, 12, 81, 24, 204, 35], DirectionArg::None),
+    ("goosefx_swap", [222, 136, 46, 123, 189, 125, 124, 122], DirectionArg::None),
+    ("balansol_swap", [137, 109, 253, 253, 70, 109, 11, 100], DirectionArg::None),
+    ("lifinity_v2_swap", [19, 152, 195, 245, 187, 144, 74, 227], DirectionArg::None),
+    ("whirlpool_swap", [123, 229, 184, 63, 12, 0, 92, 145], DirectionArg::Bool),
+    ("invariant_swap", [187, 193, 40, 121, 47, 73, 144, 177], DirectionArg::Bool),
+    ("marco_polo_swap", [241, 147, 94, 15, 58, 108, 179, 68], DirectionArg::Bool),
+    ("serum_swap", [88, 183, 70, 249, 214, 118, 82, 210], DirectionArg::Side),
+    ("aldrin_swap", [251, 232, 119, 166, 225, 185, 169, 161], DirectionArg::Side),
+    ("aldrin_v2_swap", [190, 166, 89, 139, 33, 152, 16, 10], DirectionArg::Side),
+    ("dradex_swap", [34, 146, 160, 38, 51, 85, 58, 151], DirectionArg::Side),
+];
+
+/// Amount arguments shared by every v2/v3 AMM swap instruction
+///
+/// `in_amount` is `None` when the leg consumes the amount recorded in the
+/// token ledger (the output of the previous leg).
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AmmSwapAmounts {
+    pub in_amount: Option<u64>,
+    pub minimum_out_amount: u64,
+    pub platform_fee_bps: u8,
+}
+
+#[derive(BorshDeserialize)]
+struct SideAmmSwapArgs {
+    _side: Side,
+    amounts: AmmSwapAmounts,
+}
+
+#[derive(BorshDeserialize)]
+struct BoolAmmSwapArgs {
+    _direction: bool,
+    amounts: AmmSwapAmounts,
+}
+
+/// Decoded v2/v3 AMM swap instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmmSwap {
+    /// IDL instruction name, e.g. `raydium_swap_v2`
+    pub instruction: &'static str,
+    pub amounts: AmmSwapAmounts,
+}
+
+/// Decode a Jupiter v2/v3 per-AMM swap instruction
+///
+/// Non-swap instructions (`set_token_ledger`, `risk_check_and_fee`, ...) are
+/// reported as unknown discriminators.
+pub fn decode_amm_swap(data: &[u8]) -> Result<AmmSwap, DecodeError> {
+    let (discriminator, args) = split_discriminator(data)?;
+
+    let (instruction, direction) = AMM_SWAP_INSTRUCTIONS
+        .iter()
+        .find(|(_, disc, _)| *disc == discriminator)
+        .map(|(name, _, direction)| (*name, *direction))
+        .ok_or(DecodeError::UnknownDiscriminator(discriminator))?;
+
+    let amounts = match direction {
+        DirectionArg::None => deserialize::<AmmSwapAmounts>(args)?,
+        DirectionArg::Side => deserialize::<SideAmmSwapArgs>(args)?.amounts,
+        DirectionArg::Bool => deserialize::<BoolAmmSwapArgs>(args)?.amounts,
+    };
+
+    Ok(AmmSwap { instruction, amounts })
+}
+
+/// Split the 8-byte discriminator from the instruction arguments
+#[inline]
+fn split_discriminator(data: &[u8]) -> Result<([u8; 8], &[u8]), DecodeError> {
+    if data.len() < 8 {
+        return Err(DecodeError::TooShort(data.len()));
+    }
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&data[0..8]);
+    Ok((discriminator, &data[8..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Find an AMM instruction's discriminator by name
+    fn amm_discriminator(name: &str) -> [u8; 8] {
+        AMM_SWAP_INSTRUCTIONS
+            .iter()
+            .find(|(n, _, _)| *n == name)
+            .map(|(_, disc, _)| *disc)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_decode_v4_route_split_chain() {
+        let mut data = V4_ROUTE_DISCRIMINATOR.to_vec();
+        // Split { [60% Swap{Whirlpool{a_to_b: true}}, 40% Chain{[Swap{Raydium}, Swap{Serum{Ask}}]}] }
+        data.push(1);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[60, 2, 17, 1]);
+        data.extend_from_slice(&[40, 0]);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[2, 7, 2, 12, 1]);
+        data.extend_from_slice(&5_000_000u64.to_le_bytes());
+        data.extend_from_slice(&4_950_000u64.to_le_bytes());
+        data.extend_from_slice(&50u16.to_le_bytes());
+        data.push(0);
+
+        let route = decode_v4_route(&data).unwrap();
+
+        assert_eq!(route.in_amount, 5_000_000);
+        assert_eq!(route.quoted_out_amount, 4_950_000);
+        assert_eq!(route.slippage_bps, 50);
+        assert_eq!(route.swap_leg.swap_count(), 3);
+        match &route.swap_leg {
+            SwapLeg::Split { split_legs } => {
+                assert_eq!(split_legs[0].percent, 60);
+                assert_eq!(
+                    split_legs[0].swap_leg,
+                    SwapLeg::Swap { swap: Swap::Whirlpool { a_to_b: true } }
+                );
+            }
+            other => panic!("expected split leg, got {:?}", other),
+        }
+
+        let accounts: Vec<String> = (0..5).map(|i| format!("account{}", i)).collect();
+        let route_accounts = route.accounts(&accounts).unwrap();
+        assert_eq!(route_accounts.user_wallet, "account1");
+        assert_eq!(route_accounts.destination_token_account, "account2");
+    }
+
+    #[test]
+    fn test_decode_amm_swap_variants() {
+        // raydium_swap_v2(in_amount: Some(1_000_000), minimum_out_amount: 990, platform_fee_bps: 0)
+        let mut data = amm_discriminator("raydium_swap_v2").to_vec();
+        data.push(1);
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&990u64.to_le_bytes());
+        data.push(0);
+
+        let swap = decode_amm_swap(&data).unwrap();
+        assert_eq!(swap.instruction, "raydium_swap_v2");
+        assert_eq!(swap.amounts.in_amount, Some(1_000_000));
+        assert_eq!(swap.amounts.minimum_out_amount, 990);
+
+        // serum_swap(side: Ask, in_amount: None, minimum_out_amount: 42, platform_fee_bps: 20)
+        let mut data = amm_discriminator("serum_swap").to_vec();
+        data.extend_from_slice(&[1, 0]);
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.push(20);
+
+        let swap = decode_amm_swap(&data).unwrap();
+        assert_eq!(swap.instruction, "serum_swap");
+        assert_eq!(swap.amounts.in_amount, None);
+        assert_eq!(swap.amounts.platform_fee_bps, 20);
+
+        // whirlpool_swap(a_to_b: true, ...)
+        let mut data = amm_discriminator("whirlpool_swap").to_vec();
+        data.extend_from_slice(&[1, 1]);
+        data.extend_from_slice(&7_000u64.to_le_bytes());
+        data.extend_from_slice(&6_900u64.to_le_bytes());
+        data.push(0);
+        assert_eq!(decode_amm_swap(&data).unwrap().amounts.in_amount, Some(7_000));
+    }
+
+    #[test]
+    fn test_decode_legacy_errors() {
+        assert_eq!(decode_amm_swap(&[1, 2]), Err(DecodeError::TooShort(2)));
+        assert_eq!(
+            decode_amm_swap(&[0xAB; 8]),
+            Err(DecodeError::UnknownDiscriminator([0xAB; 8]))
+        );
+
+        // Serum swap missing its side argument
+        let mut data = amm_discriminator("serum_swap").to_vec();
+        data.push(0);
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.push(20);
+        assert!(matches!(decode_amm_swap(&data), Err(DecodeError::InvalidArgs(_))));
+
+        assert!(matches!(
+            decode_v4_route(&amm_discriminator("raydium_swap")),
+            Err(DecodeError::UnknownDiscriminator(_))
+        ));
+    }
+}
@@ -0,0 +1,265 @@
+//! Jupiter Limit Order instruction decoder
+//!
+//! Decodes the order lifecycle instructions of the Limit Order program
+//! (`initialize_order`, `fill_order`, `pre_flash_fill_order`/`flash_fill_order`,
+//! `cancel_order`, `cancel_expired_order`) and `withdraw_fee`, together with the
+//! accounts each one touches. Decoding is strict, like the v6 route decoder.
+
+use borsh::BorshDeserialize;
+
+use crate::jupiter_v6::{deserialize, DecodeError};
+
+// Limit Order instruction discriminators (sha256("global:<name>")[..8])
+pub const INITIALIZE_ORDER_DISCRIMINATOR: [u8; 8] = [133, 110, 74, 175, 112, 159, 245, 159];
+pub const FILL_ORDER_DISCRIMINATOR: [u8; 8] = [232, 122, 115, 25, 199, 143, 136, 162];
+pub const PRE_FLASH_FILL_ORDER_DISCRIMINATOR: [u8; 8] = [240, 47, 153, 68, 13, 190, 225, 42];
+pub const FLASH_FILL_ORDER_DISCRIMINATOR: [u8; 8] = [252, 104, 18, 134, 164, 78, 18, 140];
+pub const CANCEL_ORDER_DISCRIMINATOR: [u8; 8] = [95, 129, 237, 240, 8, 49, 223, 132];
+pub const CANCEL_EXPIRED_ORDER_DISCRIMINATOR: [u8; 8] = [216, 120, 64, 235, 155, 19, 229, 99];
+pub const WITHDRAW_FEE_DISCRIMINATOR: [u8; 8] = [14, 122, 231, 218, 31, 238, 223, 150];
+
+/// Arguments of `initialize_order`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InitializeOrderArgs {
+    pub making_amount: u64,
+    pub taking_amount: u64,
+    pub expired_at: Option<i64>,
+}
+
+/// Arguments of `fill_order`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FillOrderArgs {
+    pub making_amount: u64,
+    pub max_taking_amount: u64,
+}
+
+/// Arguments of `pre_flash_fill_order`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PreFlashFillOrderArgs {
+    pub making_amount: u64,
+}
+
+/// Arguments of `flash_fill_order`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FlashFillOrderArgs {
+    pub max_taking_amount: u64,
+}
+
+/// Decoded Limit Order program instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitOrderInstruction {
+    InitializeOrder(InitializeOrderArgs),
+    FillOrder(FillOrderArgs),
+    PreFlashFillOrder(PreFlashFillOrderArgs),
+    FlashFillOrder(FlashFillOrderArgs),
+    CancelOrder,
+    CancelExpiredOrder,
+    /// Admin fee withdrawal; touches no order
+    WithdrawFee,
+}
+
+impl LimitOrderInstruction {
+    /// Stable name matching the IDL instruction name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitOrderInstruction::InitializeOrder(_) => "initialize_order",
+            LimitOrderInstruction::FillOrder(_) => "fill_order",
+            LimitOrderInstruction::PreFlashFillOrder(_) => "pre_flash_fill_order",
+            LimitOrderInstruction::FlashFillOrder(_) => "flash_fill_order",
+            LimitOrderInstruction::CancelOrder => "cancel_order",
+            LimitOrderInstruction::CancelExpiredOrder => "cancel_expired_order",
+            LimitOrderInstruction::WithdrawFee => "withdraw_fee",
+        }
+    }
+
+    /// Resolve the order accounts from the instruction's account list
+    ///
+    /// initialize_order layout:
+    /// [0] base, [1] maker, [2] order, [3] reserve, [4] maker_input_account,
+    /// [5] maker_output_account, [6] referral, [7] input_mint, [8] output_mint
+    ///
+    /// fill_order layout:
+    /// [0] order, [1] reserve, [2] maker, [3] taker, [4] taker_output_account,
+    /// [5] maker_output_account, ...
+    ///
+    /// flash_fill_order layout:
+    /// [0] order, [1] reserve, [2] maker, [3] taker, [4] maker_output_account, ...
+    ///
+    /// cancel_order / cancel_expired_order layout:
+    /// [0] order, [1] reserve, [2] maker, [3] maker_input_account
+    ///
+    /// `pre_flash_fill_order` and `withdraw_fee` have no maker-side accounts.
+    pub fn accounts<'a>(&self, accounts: &'a [String]) -> Option<OrderAccounts<'a>> {
+        match self {
+            LimitOrderInstruction::InitializeOrder(_) => {
+                if accounts.len() < 9 {
+                    return None;
+                }
+                Some(OrderAccounts {
+                    order: &accounts[2],
+                    reserve: &accounts[3],
+                    maker: &accounts[1],
+                    maker_token_account: &accounts[5],
+                    input_mint: Some(&accounts[7]),
+                    output_mint: Some(&accounts[8]),
+                })
+            }
+            LimitOrderInstruction::FillOrder(_) => {
+                if accounts.len() < 6 {
+                    return None;
+                }
+                Some(fill_accounts(accounts, 5))
+            }
+            LimitOrderInstruction::FlashFillOrder(_) => {
+                if accounts.len() < 5 {
+                    return None;
+                }
+                Some(fill_accounts(accounts, 4))
+            }
+            LimitOrderInstruction::CancelOrder | LimitOrderInstruction::CancelExpiredOrder => {
+                if accounts.len() < 4 {
+                    return None;
+                }
+                Some(fill_accounts(accounts, 3))
+            }
+            LimitOrderInstruction::PreFlashFillOrder(_) | LimitOrderInstruction::WithdrawFee => {
+                None
+            }
+        }
+    }
+}
+
+/// Order-side accounts of a Limit Order instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderAccounts<'a> {
+    pub order: &'a str,
+    /// Escrow token account holding the maker's unfilled input
+    pub reserve: &'a str,
+    pub maker: &'a str,
+    /// Maker output account for inits and fills, maker input account for cancels
+    pub maker_token_account: &'a str,
+    /// Only passed to `initialize_order`; recover it from token balances otherwise
+    pub input_mint: Option<&'a str>,
+    pub output_mint: Option<&'a str>,
+}
+
+/// Accounts shared by fills and cancels: order, reserve and maker lead the list
+#[inline]
+fn fill_accounts(accounts: &[String], maker_token_index: usize) -> OrderAccounts<'_> {
+    OrderAccounts {
+        order: &accounts[0],
+        reserve: &accounts[1],
+        maker: &accounts[2],
+        maker_token_account: &accounts[maker_token_index],
+        input_mint: None,
+        output_mint: None,
+    }
+}
+
+/// Decode a Limit Order program instruction from raw instruction data
+pub fn decode_limit_order(data: &[u8]) -> Result<LimitOrderInstruction, DecodeError> {
+    if data.len() < 8 {
+        return Err(DecodeError::TooShort(data.len()));
+    }
+
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&data[0..8]);
+    let args = &data[8..];
+
+    match discriminator {
+        INITIALIZE_ORDER_DISCRIMINATOR => {
+            deserialize(args).map(LimitOrderInstruction::InitializeOrder)
+        }
+        FILL_ORDER_DISCRIMINATOR => deserialize(args).map(LimitOrderInstruction::FillOrder),
+        PRE_FLASH_FILL_ORDER_DISCRIMINATOR => {
+            deserialize(args).map(LimitOrderInstruction::PreFlashFillOrder)
+        }
+        FLASH_FILL_ORDER_DISCRIMINATOR => {
+            deserialize(args).map(LimitOrderInstruction::FlashFillOrder)
+        }
+        CANCEL_ORDER_DISCRIMINATOR => Ok(LimitOrderInstruction::CancelOrder),
+        CANCEL_EXPIRED_ORDER_DISCRIMINATOR => Ok(LimitOrderInstruction::CancelExpiredOrder),
+        WITHDRAW_FEE_DISCRIMINATOR => Ok(LimitOrderInstruction::WithdrawFee),
+        _ => Err(DecodeError::UnknownDiscriminator(discriminator)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_accounts(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("account{}", i)).collect()
+    }
+
+    #[test]
+    fn test_decode_initialize_order() {
+        let mut data = INITIALIZE_ORDER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&25_000_000u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&1_700_086_400i64.to_le_bytes());
+
+        let decoded = decode_limit_order(&data).unwrap();
+
+        assert_eq!(
+            decoded,
+            LimitOrderInstruction::InitializeOrder(InitializeOrderArgs {
+                making_amount: 1_000_000,
+                taking_amount: 25_000_000,
+                expired_at: Some(1_700_086_400),
+            })
+        );
+        assert_eq!(decoded.as_str(), "initialize_order");
+
+        let accounts = create_test_accounts(12);
+        let order_accounts = decoded.accounts(&accounts).unwrap();
+        assert_eq!(order_accounts.maker, "account1");
+        assert_eq!(order_accounts.order, "account2");
+        assert_eq!(order_accounts.input_mint, Some("account7"));
+        assert_eq!(order_accounts.output_mint, Some("account8"));
+    }
+
+    #[test]
+    fn test_decode_fill_and_cancel() {
+        let mut data = FILL_ORDER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&500u64.to_le_bytes());
+        data.extend_from_slice(&12_500u64.to_le_bytes());
+
+        let fill = decode_limit_order(&data).unwrap();
+        let accounts = create_test_accounts(12);
+        let fill_accounts = fill.accounts(&accounts).unwrap();
+        assert_eq!(fill_accounts.maker, "account2");
+        assert_eq!(fill_accounts.maker_token_account, "account5");
+        assert_eq!(fill_accounts.input_mint, None);
+
+        let cancel = decode_limit_order(&CANCEL_ORDER_DISCRIMINATOR).unwrap();
+        assert_eq!(cancel, LimitOrderInstruction::CancelOrder);
+        assert_eq!(cancel.accounts(&accounts).unwrap().maker_token_account, "account3");
+
+        let withdraw = decode_limit_order(&WITHDRAW_FEE_DISCRIMINATOR).unwrap();
+        assert!(withdraw.accounts(&accounts).is_none());
+    }
+
+    #[test]
+    fn test_decode_limit_order_errors() {
+        assert_eq!(decode_limit_order(&[1, 2, 3]), Err(DecodeError::TooShort(3)));
+        assert_eq!(
+            decode_limit_order(&[0xFF; 8]),
+            Err(DecodeError::UnknownDiscriminator([0xFF; 8]))
+        );
+
+        // Truncated fill args
+        let mut data = FILL_ORDER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&500u64.to_le_bytes());
+        assert!(matches!(decode_limit_order(&data), Err(DecodeError::InvalidArgs(_))));
+
+        // Too few accounts for the layout
+        let init = LimitOrderInstruction::InitializeOrder(InitializeOrderArgs {
+            making_amount: 1,
+            taking_amount: 1,
+            expired_at: None,
+        });
+        assert!(init.accounts(&create_test_accounts(8)).is_none());
+    }
+}
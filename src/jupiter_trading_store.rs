@@ -1,13 +1,30 @@
-use crate::constants::JUPITER_PROGRAM_IDS;
-use crate::pb::sf::jupiter::v1::{TradingData, TradingDataList};
+// The handler macro reads the `params` string from a raw pointer in the generated export
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use std::collections::HashMap;
+
+use crate::amm_registry::resolve_dex;
+use crate::constants::{get_jupiter_version, JUPITER_PROGRAM_IDS, JUPITER_V6_PROGRAM_ID, TOKEN_2022_PROGRAM_ID};
+use crate::jupiter_legacy::{decode_amm_swap, decode_v4_route};
+use crate::jupiter_v6::{
+    decode_route, decode_swap_event, is_event_cpi, DecodeError, DecodedRoute, RoutePlanStep,
+};
+use crate::mint_decimals::{decimals_key, format_units};
+use crate::pb::sf::jupiter::v1::{OutlierReason, ParseMethod, SwapHop, TradingData, TradingDataList};
+use crate::token_balances::{
+    build_token_balance_index, decode_transfer_checked_fee, find_mint_decimals,
+    find_owner_token_accounts, TokenBalanceChange,
+};
+use crate::transaction_error::transaction_failure;
+use crate::transaction_view::{
+    instruction_id, references_any, walk_instructions, CallStack, InstructionRef,
+    TransactionAccounts,
+};
 use substreams::errors::Error;
-use substreams_solana::pb::sf::solana::r#type::v1::Block;
-
-// Jupiter v6 instruction discriminators
-const ROUTE_DISCRIMINATOR: [u8; 8] = [229, 23, 203, 151, 122, 227, 173, 42];
-const SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
-const EXACT_OUT_ROUTE_DISCRIMINATOR: [u8; 8] = [208, 51, 239, 151, 123, 43, 237, 92];
-const SHARED_ACCOUNTS_EXACT_OUT_ROUTE_DISCRIMINATOR: [u8; 8] = [176, 209, 105, 168, 154, 125, 69, 62];
+use substreams::log;
+use substreams::store::{StoreGet, StoreGetInt64};
+use substreams_solana::base58;
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, ConfirmedTransaction};
 
 // Maximum reasonable token amount (10^18 - prevents parsing garbage data as amounts)
 const MAX_REASONABLE_AMOUNT: u64 = 1_000_000_000_000_000_000;
@@ -15,6 +32,8 @@ const MAX_REASONABLE_AMOUNT: u64 = 1_000_000_000_000_000_000;
 const MIN_VALID_AMOUNT: u64 = 1000;
 
 /// Parsed swap result with all extracted fields
+///
+/// `amount_in`/`amount_out` are the quoted amounts from the instruction arguments.
 #[derive(Default)]
 struct ParsedSwap {
     amount_in: u64,
@@ -22,14 +41,66 @@ struct ParsedSwap {
     input_mint: String,
     output_mint: String,
     user_wallet: String,
+    /// User token accounts, known only for exactly decoded routes
+    source_token_account: String,
+    destination_token_account: String,
+    /// Set for exactly decoded v6 routes, which own the SwapEvents that follow them
+    route_plan: Option<Vec<RoutePlanStep>>,
+    /// v2/v3 leg spending the previous leg's output recorded in the token ledger
+    ledger_input: bool,
+    /// How the quoted amounts were found: exact decode or heuristic scan
+    method: ParseMethod,
 }
 
+/// v2/v3 route whose legs are being merged into one swap while walking a transaction
+///
+/// v2/v3 routes run one instruction per AMM leg: the first spends the user's
+/// input, later legs spend the previous leg's output through the token ledger.
+struct LegacyRoute {
+    /// Index of the route in the output items
+    item_index: usize,
+    /// Index of the route in the transaction's settlements
+    settlement_index: usize,
+    program_id: String,
+    user_wallet: String,
+}
+
+/// Route currently receiving SwapEvent hops while walking a transaction
+struct RouteContext {
+    /// Index of the route in the output items
+    item_index: usize,
+    /// Top-level instruction the route executes in
+    instruction_index: u32,
+    stack_height: u32,
+    route_plan: Vec<RoutePlanStep>,
+}
+
+/// Balance deltas of a swap's user token accounts, applied once the whole
+/// transaction has been walked
+struct Settlement {
+    /// Index of the swap in the output items
+    item_index: usize,
+    /// Top-level instruction of the swap's last leg, whose inner transfers deliver the output
+    instruction_index: u32,
+    source_token_account: String,
+    destination_token_account: String,
+    executed_in: Option<u64>,
+    executed_out: Option<u64>,
+    /// Token-2022 fee withheld from the transfers into the destination
+    output_transfer_fee: u64,
+}
+
+/// Module params: `strict=true` drops the heuristic fallback for unknown v6
+/// instructions, so every recorded amount comes from an exact decode
 #[substreams::handlers::map]
-pub fn map_jupiter_trading_data(block: Block) -> Result<TradingDataList, Error> {
+pub fn map_jupiter_trading_data(
+    params: String,
+    block: Block,
+    mint_decimals: StoreGetInt64,
+) -> Result<TradingDataList, Error> {
+    let strict = parse_strict(&params)?;
     // Pre-allocate with estimated capacity to avoid reallocations
-    let mut items = Vec::with_capacity(64);
-    let mut total_volume: u64 = 0;
-    let mut swap_count: u32 = 0;
+    let mut items: Vec<TradingData> = Vec::with_capacity(64);
 
     let block_time = block
         .block_time
@@ -38,203 +109,663 @@ pub fn map_jupiter_trading_data(block: Block) -> Result<TradingDataList, Error>
         .unwrap_or_default();
 
     let slot = block.slot;
+    let stored_decimals = |mint: &str| {
+        mint_decimals
+            .get_last(decimals_key(mint))
+            .and_then(|decimals| u32::try_from(decimals).ok())
+    };
 
-    for trx in block.transactions() {
-        // Cache tx_id once per transaction (avoid repeated clones)
-        let tx_id = trx.id();
+    // Raw keys of the Jupiter programs, so transactions that never load one are
+    // skipped before any key is base58-encoded
+    let jupiter_keys: Vec<Vec<u8>> = JUPITER_PROGRAM_IDS
+        .iter()
+        .filter_map(|program_id| base58::decode(program_id).ok())
+        .collect();
+
+    // Include failed transactions so reverted swaps are recorded with their error
+    for trx in block
+        .transactions
+        .iter()
+        .filter(|trx| trx.meta.is_some() && references_any(trx, &jupiter_keys))
+    {
+        parse_transaction(trx, slot, block_time, strict, &stored_decimals, &mut items);
+    }
 
-        for instruction in trx.walk_instructions() {
-            // Check program ID without converting to String first
-            let program_id_bytes = instruction.program_id();
-            let program_id_str = program_id_bytes.to_string();
+    let swaps = items.iter().filter(|trade| trade.success && trade.amount_in > 0);
+    let swap_count = swaps.clone().count() as u32;
+    let total_volume = swaps.fold(0u64, |total, trade| total.saturating_add(trade.amount_in));
 
-            if !is_jupiter_program(&program_id_str) {
+    Ok(TradingDataList {
+        items,
+        total_volume,
+        swap_count,
+    })
+}
+
+/// Parse the Jupiter instructions of a transaction into `items`
+///
+/// `stored_decimals` looks up mint decimals missing from the transaction's token balances.
+fn parse_transaction(
+    trx: &ConfirmedTransaction,
+    slot: u64,
+    block_time: u64,
+    strict: bool,
+    stored_decimals: &dyn Fn(&str) -> Option<u32>,
+    items: &mut Vec<TradingData>,
+) {
+    // Cache tx_id, account keys (including lookup table addresses), signers,
+    // failure and token balance deltas once per transaction
+    let tx_id = trx.id();
+    let account_keys = TransactionAccounts::new(trx);
+    let failure = transaction_failure(trx, &account_keys).unwrap_or_default();
+    let success = trx.is_successful();
+    let signers = transaction_signers(trx);
+    let balances = build_token_balance_index(trx, &account_keys);
+    // Route that owns subsequent SwapEvent self-CPIs
+    let mut current_route: Option<RouteContext> = None;
+    // v2/v3 route that subsequent token ledger legs continue
+    let mut legacy_route: Option<LegacyRoute> = None;
+    let mut settlements: Vec<Settlement> = Vec::new();
+    let mut call_stack = CallStack::default();
+
+    for instruction in walk_instructions(trx) {
+        let program_id_str = match account_keys.get(instruction.program_id_index) {
+            Some(program_id) => program_id,
+            None => {
+                log::info!("Program index out of range in transaction {}", tx_id);
                 continue;
             }
+        };
+        let stack_height = instruction.stack_height;
+        let caller_program = call_stack.enter(&instruction, program_id_str);
+        // A route's SwapEvents never outlive its top-level instruction
+        if instruction.is_root {
+            current_route = None;
+        }
 
-            // Convert accounts to strings (required for output)
-            let accounts: Vec<String> = instruction
-                .accounts()
-                .iter()
-                .map(|address| address.to_string())
-                .collect();
+        // Token-2022 fees withheld from the transfers delivering a swap's output
+        if program_id_str == TOKEN_2022_PROGRAM_ID {
+            if let Some(fee) = decode_transfer_checked_fee(instruction.data) {
+                let destination = instruction
+                    .accounts
+                    .get(2)
+                    .and_then(|index| account_keys.get(u32::from(*index)));
+                if let Some(destination) = destination {
+                    let instruction_index = instruction.instruction_index;
+                    add_output_transfer_fee(&mut settlements, instruction_index, destination, fee);
+                }
+            }
+            continue;
+        }
 
-            let data = instruction.data();
+        if !is_jupiter_program(program_id_str) {
+            continue;
+        }
+
+        let data = instruction.data;
+
+        // v6 SwapEvent self-CPIs are hops of the route that emitted them, not swaps
+        if program_id_str == JUPITER_V6_PROGRAM_ID && is_event_cpi(data) {
+            if let Some(route) = current_route.as_ref().filter(|route| route.emitted(&instruction)) {
+                let hop_index = items[route.item_index].hops.len();
+                if let Some(hop) = parse_swap_event(data, hop_index, route) {
+                    items[route.item_index].hops.push(hop);
+                }
+            }
+            continue;
+        }
 
-            // Parse swap amounts from instruction data
-            let parsed = parse_jupiter_instruction(data, &accounts);
+        // Any other v6 instruction ends the current route; only a decoded route
+        // starts a new one, so its SwapEvents can't attach to an earlier route
+        if program_id_str == JUPITER_V6_PROGRAM_ID {
+            current_route = None;
+        }
 
-            if parsed.amount_in > 0 {
-                total_volume = total_volume.saturating_add(parsed.amount_in);
-                swap_count += 1;
+        // Resolve account indices against static keys and lookup table addresses
+        let accounts = match account_keys.resolve(instruction.accounts) {
+            Some(accounts) => accounts,
+            None => {
+                log::info!("Account index out of range in transaction {}", tx_id);
+                continue;
+            }
+        };
+
+        // Parse quoted swap amounts with the decoder for the program's version
+        let version = get_jupiter_version(program_id_str);
+        let is_legacy = matches!(version, Some("v3") | Some("v2"));
+        let mut parsed = match version {
+            Some("v6") => parse_jupiter_instruction(data, &accounts, !strict),
+            Some("v4") => parse_v4_instruction(data, &accounts, &balances),
+            Some("v3") | Some("v2") => {
+                parse_amm_swap_instruction(data, &accounts, &signers, &balances)
             }
+            // Limit order and DCA instructions are decoded by their own event
+            // modules and carry no swap amounts here, so they stay out of swap volume
+            _ => ParsedSwap::default(),
+        };
+
+        // A token ledger leg is part of the v2/v3 route before it, not a swap of its own
+        let continued_route = legacy_route
+            .as_ref()
+            .filter(|route| parsed.ledger_input && route.continued_by(program_id_str, &parsed));
+        if let Some(route) = continued_route {
+            let trade = &mut items[route.item_index];
+            let settlement = &mut settlements[route.settlement_index];
+            settlement.instruction_index = instruction.instruction_index;
+            extend_legacy_route(trade, settlement, parsed, &balances, stored_decimals);
+            continue;
+        }
+        let is_legacy_leg = is_legacy && parsed.method == ParseMethod::ExactArgs;
+
+        // Quoted amounts until the transaction is settled with its balance deltas
+        // (a failed transaction moves no tokens, so it keeps the quoted amounts)
+        let (executed_in, executed_out) = executed_amounts(&mut parsed, &balances);
+        let amount_in = parsed.amount_in;
+        let amount_out = parsed.amount_out;
+        let token_program = swap_token_program(&parsed, &balances);
+        let input_decimals = resolve_decimals(&parsed.input_mint, &balances, stored_decimals);
+        let output_decimals = resolve_decimals(&parsed.output_mint, &balances, stored_decimals);
+        settlements.push(Settlement {
+            item_index: items.len(),
+            instruction_index: instruction.instruction_index,
+            source_token_account: std::mem::take(&mut parsed.source_token_account),
+            destination_token_account: std::mem::take(&mut parsed.destination_token_account),
+            executed_in,
+            executed_out,
+            output_transfer_fee: 0,
+        });
+
+        items.push(TradingData {
+            program_id: program_id_str.to_string(),
+            transaction_id: tx_id.clone(),
+            accounts,
+            data: data.to_vec(),
+            slot,
+            block_time,
+            amount_in,
+            amount_out,
+            input_mint: parsed.input_mint,
+            output_mint: parsed.output_mint,
+            user_wallet: parsed.user_wallet,
+            quoted_amount_in: parsed.amount_in,
+            quoted_amount_out: parsed.amount_out,
+            hops: Vec::new(),
+            success,
+            error_code: failure.error_code.clone(),
+            error_message: failure.error_message.clone(),
+            token_program,
+            input_decimals,
+            output_decimals,
+            amount_in_normalized: normalized_amount(amount_in, input_decimals),
+            amount_out_normalized: normalized_amount(amount_out, output_decimals),
+            // Tagged downstream by map_validated_trading_data
+            outlier_reason: OutlierReason::None as i32,
+            instruction_index: instruction.instruction_index,
+            inner_index: instruction.inner_index,
+            swap_id: instruction_id(&tx_id, instruction.instruction_index, instruction.inner_index),
+            is_cpi: !instruction.is_root,
+            caller_program: caller_program.unwrap_or_default().to_string(),
+            stack_height,
+            parse_method: parsed.method as i32,
+            // Set when the transaction is settled
+            output_transfer_fee: 0,
+        });
+
+        if let Some(route_plan) = parsed.route_plan {
+            current_route = Some(RouteContext {
+                item_index: items.len() - 1,
+                instruction_index: instruction.instruction_index,
+                stack_height,
+                route_plan,
+            });
+        }
 
-            items.push(TradingData {
-                program_id: program_id_str,
-                transaction_id: tx_id.clone(),
-                accounts,
-                data: data.to_vec(),
-                slot,
-                block_time,
-                amount_in: parsed.amount_in,
-                amount_out: parsed.amount_out,
-                input_mint: parsed.input_mint,
-                output_mint: parsed.output_mint,
-                user_wallet: parsed.user_wallet,
+        // Other v2/v3 instructions (set_token_ledger, fees) sit between legs of a route
+        if is_legacy_leg {
+            let trade = &items[items.len() - 1];
+            legacy_route = Some(LegacyRoute {
+                item_index: items.len() - 1,
+                settlement_index: settlements.len() - 1,
+                program_id: trade.program_id.clone(),
+                user_wallet: trade.user_wallet.clone(),
             });
+        } else if !is_legacy {
+            legacy_route = None;
         }
     }
 
-    Ok(TradingDataList {
-        items,
-        total_volume,
-        swap_count,
-    })
+    settle_transaction(items, &settlements);
+}
+
+/// Parse `strict=true|false` from the module params (default false)
+fn parse_strict(params: &str) -> Result<bool, Error> {
+    let mut strict = false;
+    for pair in params.split('&').map(str::trim).filter(|pair| !pair.is_empty()) {
+        match pair.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+            Some(("strict", value)) => {
+                strict = value
+                    .parse()
+                    .map_err(|_| Error::msg(format!("invalid value {:?} for param strict", value)))?;
+            }
+            _ => return Err(Error::msg(format!("unknown trading data param {:?}", pair))),
+        }
+    }
+    Ok(strict)
 }
 
 /// Parse Jupiter instruction data to extract swap amounts
 /// Returns a ParsedSwap struct with all extracted fields
-fn parse_jupiter_instruction(data: &[u8], accounts: &[String]) -> ParsedSwap {
-    if data.len() < 8 {
-        return ParsedSwap::default();
+///
+/// Unknown instructions fall back to `parse_generic_swap` when `heuristics` is set.
+fn parse_jupiter_instruction(data: &[u8], accounts: &[String], heuristics: bool) -> ParsedSwap {
+    match decode_route(data) {
+        Ok(route) => parse_route(&route, accounts),
+        Err(DecodeError::TooShort(_)) => ParsedSwap::default(),
+        Err(DecodeError::UnknownDiscriminator(_)) if heuristics => {
+            // Try to extract amount from generic instruction format
+            parse_generic_swap(data, accounts)
+        }
+        Err(DecodeError::UnknownDiscriminator(_)) => ParsedSwap::default(),
+        Err(err) => {
+            // A known route discriminator whose args don't match the layout:
+            // report it and record no amounts rather than guessing
+            log::info!("Failed to decode Jupiter v6 route: {}", err);
+            ParsedSwap::default()
+        }
+    }
+}
+
+/// Build a ParsedSwap from a decoded v6 route instruction
+///
+/// For ExactIn routes `amount_in` is the exact input and `amount_out` the quoted
+/// output; for ExactOut routes `amount_out` is exact and `amount_in` the quoted maximum.
+fn parse_route(route: &DecodedRoute, accounts: &[String]) -> ParsedSwap {
+    let route_accounts = match route.kind.accounts(accounts) {
+        Some(route_accounts) => route_accounts,
+        None => return ParsedSwap::default(),
+    };
+
+    ParsedSwap {
+        amount_in: route.in_amount,
+        amount_out: route.out_amount,
+        input_mint: route_accounts.input_mint.unwrap_or_default().to_string(),
+        output_mint: route_accounts.output_mint.to_string(),
+        user_wallet: route_accounts.user_wallet.to_string(),
+        source_token_account: route_accounts.source_token_account.to_string(),
+        destination_token_account: route_accounts.destination_token_account.to_string(),
+        route_plan: Some(route.route_plan.clone()),
+        method: ParseMethod::ExactArgs,
+        ..Default::default()
     }
+}
 
-    // Safe discriminator extraction
-    let discriminator: [u8; 8] = match data[0..8].try_into() {
-        Ok(d) => d,
+/// Parse a Jupiter v4 `route` instruction
+///
+/// v4 passes no source token account or mints; the source is the user's token
+/// account the transaction spent from, and mints come from the balance deltas.
+fn parse_v4_instruction(
+    data: &[u8],
+    accounts: &[String],
+    balances: &HashMap<String, TokenBalanceChange>,
+) -> ParsedSwap {
+    let route = match decode_v4_route(data) {
+        Ok(route) => route,
+        Err(DecodeError::InvalidArgs(reason)) => {
+            log::info!("Failed to decode Jupiter v4 route: {}", reason);
+            return ParsedSwap::default();
+        }
         Err(_) => return ParsedSwap::default(),
     };
+    let route_accounts = match route.accounts(accounts) {
+        Some(route_accounts) => route_accounts,
+        None => return ParsedSwap::default(),
+    };
 
-    // Route instruction: discriminator(8) + route_plan_len(1) + ...
-    // SharedAccountsRoute: discriminator(8) + id(1) + route_plan_len(1) + ...
-    // The amount is typically a u64 after the route plan
+    let (source, _) = find_owner_token_accounts(balances, route_accounts.user_wallet, accounts);
 
-    match discriminator {
-        ROUTE_DISCRIMINATOR => parse_route_instruction(data, accounts),
-        SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR => parse_shared_accounts_route(data, accounts),
-        EXACT_OUT_ROUTE_DISCRIMINATOR => parse_exact_out_route(data, accounts),
-        SHARED_ACCOUNTS_EXACT_OUT_ROUTE_DISCRIMINATOR => parse_shared_accounts_exact_out(data, accounts),
-        _ => {
-            // Try to extract amount from generic instruction format
-            parse_generic_swap(data, accounts)
+    ParsedSwap {
+        amount_in: route.in_amount,
+        amount_out: route.quoted_out_amount,
+        user_wallet: route_accounts.user_wallet.to_string(),
+        source_token_account: source.unwrap_or_default().to_string(),
+        destination_token_account: route_accounts.destination_token_account.to_string(),
+        method: ParseMethod::ExactArgs,
+        ..Default::default()
+    }
+}
+
+/// Parse a Jupiter v2/v3 per-AMM swap instruction
+///
+/// Each instruction passes its AMM's own account list, so the user is the
+/// instruction account that signed the transaction, and the source/destination
+/// are that user's token accounts whose balances moved. `amount_out` is the
+/// minimum output, as v2/v3 carry no quote.
+fn parse_amm_swap_instruction(
+    data: &[u8],
+    accounts: &[String],
+    signers: &[String],
+    balances: &HashMap<String, TokenBalanceChange>,
+) -> ParsedSwap {
+    let swap = match decode_amm_swap(data) {
+        Ok(swap) => swap,
+        Err(DecodeError::InvalidArgs(reason)) => {
+            log::info!("Failed to decode Jupiter v2/v3 AMM swap: {}", reason);
+            return ParsedSwap::default();
         }
+        Err(_) => return ParsedSwap::default(),
+    };
+    let user_wallet = match accounts.iter().find(|account| signers.contains(account)) {
+        Some(user_wallet) => user_wallet,
+        None => return ParsedSwap::default(),
+    };
+
+    let (source, destination) = find_owner_token_accounts(balances, user_wallet, accounts);
+
+    ParsedSwap {
+        amount_in: swap.amounts.in_amount.unwrap_or_default(),
+        amount_out: swap.amounts.minimum_out_amount,
+        user_wallet: user_wallet.clone(),
+        source_token_account: source.unwrap_or_default().to_string(),
+        destination_token_account: destination.unwrap_or_default().to_string(),
+        ledger_input: swap.amounts.in_amount.is_none(),
+        method: ParseMethod::ExactArgs,
+        ..Default::default()
     }
 }
 
-/// Parse Route instruction (Jupiter v6)
-/// Format: discriminator(8) + route_plan + in_amount(8) + quoted_out_amount(8) + slippage_bps(2) + platform_fee_bps(1)
-fn parse_route_instruction(data: &[u8], accounts: &[String]) -> ParsedSwap {
-    // Route instructions require at least 9 accounts (same layout as SharedAccountsRoute)
-    if accounts.len() < 9 || data.len() < 24 {
-        return ParsedSwap::default();
+impl LegacyRoute {
+    /// Whether a token ledger leg continues this route: same program and user
+    fn continued_by(&self, program_id: &str, leg: &ParsedSwap) -> bool {
+        self.program_id == program_id && self.user_wallet == leg.user_wallet
     }
+}
 
-    let len = data.len();
+/// Extend a v2/v3 route with its next leg
+///
+/// The route keeps its first leg's source and input; its output becomes the new
+/// leg's destination, quoted at the new leg's minimum output.
+fn extend_legacy_route(
+    trade: &mut TradingData,
+    settlement: &mut Settlement,
+    mut leg: ParsedSwap,
+    balances: &HashMap<String, TokenBalanceChange>,
+    stored_decimals: &dyn Fn(&str) -> Option<u32>,
+) {
+    let (_, executed_out) = executed_amounts(&mut leg, balances);
+    let route_accounts = ParsedSwap {
+        source_token_account: std::mem::take(&mut settlement.source_token_account),
+        destination_token_account: leg.destination_token_account,
+        ..Default::default()
+    };
 
-    // Amounts are usually at specific offsets after the route plan
-    // Common pattern: last 18-19 bytes contain amounts + slippage
-    if len >= 26 {
-        let amount_in = read_u64_le(&data[len - 18..len - 10]);
-        let amount_out = read_u64_le(&data[len - 10..len - 2]);
+    trade.output_mint = leg.output_mint;
+    trade.amount_out = leg.amount_out;
+    trade.quoted_amount_out = leg.amount_out;
+    trade.output_decimals = resolve_decimals(&trade.output_mint, balances, stored_decimals);
+    trade.amount_out_normalized = normalized_amount(trade.amount_out, trade.output_decimals);
+    trade.token_program = swap_token_program(&route_accounts, balances);
+
+    settlement.source_token_account = route_accounts.source_token_account;
+    settlement.destination_token_account = route_accounts.destination_token_account;
+    settlement.executed_out = executed_out;
+    settlement.output_transfer_fee = 0;
+}
 
-        if is_valid_amount(amount_in) && amount_out < MAX_REASONABLE_AMOUNT {
-            let (input_mint, output_mint, user_wallet) = extract_mints_from_accounts(accounts);
-            return ParsedSwap {
-                amount_in,
-                amount_out,
-                input_mint,
-                output_mint,
-                user_wallet,
-            };
+/// Signer addresses of a transaction (the first `num_required_signatures` keys)
+fn transaction_signers(trx: &ConfirmedTransaction) -> Vec<String> {
+    let message = match trx.transaction.as_ref().and_then(|tx| tx.message.as_ref()) {
+        Some(message) => message,
+        None => return Vec::new(),
+    };
+    let count = message
+        .header
+        .as_ref()
+        .map(|header| header.num_required_signatures as usize)
+        .unwrap_or(1);
+
+    message.account_keys.iter().take(count).map(base58::encode).collect()
+}
+
+/// Decode a v6 SwapEvent self-CPI into a route hop
+///
+/// The event names the AMM program that filled the hop, which labels its DEX; the
+/// matching route plan step gives the percent split. Returns `None` for other event
+/// types; malformed SwapEvents are logged and skipped.
+fn parse_swap_event(data: &[u8], hop_index: usize, route: &RouteContext) -> Option<SwapHop> {
+    match decode_swap_event(data) {
+        Ok(Some(event)) => {
+            let step = route.route_plan.get(hop_index);
+            let amm = base58::encode(event.amm);
+            Some(SwapHop {
+                dex: resolve_dex(step.map(|step| &step.swap), &amm).to_string(),
+                amm,
+                input_mint: base58::encode(event.input_mint),
+                input_amount: event.input_amount,
+                output_mint: base58::encode(event.output_mint),
+                output_amount: event.output_amount,
+                hop_index: hop_index as u32,
+                percent: step.map(|step| step.percent as u32).unwrap_or_default(),
+            })
+        }
+        Ok(None) => None,
+        Err(err) => {
+            log::info!("Failed to decode Jupiter v6 SwapEvent: {}", err);
+            None
         }
     }
+}
 
-    ParsedSwap::default()
+impl RouteContext {
+    /// Whether an event self-CPI was emitted by this route: one level below it,
+    /// within the same top-level instruction (heights are 0 in older blocks)
+    fn emitted(&self, event: &InstructionRef) -> bool {
+        if event.instruction_index != self.instruction_index {
+            return false;
+        }
+        event.stack_height == 0 || self.stack_height == 0 || event.stack_height == self.stack_height + 1
+    }
 }
 
-/// Parse SharedAccountsRoute instruction (Jupiter v6)
-/// Most common instruction type
-fn parse_shared_accounts_route(data: &[u8], accounts: &[String]) -> ParsedSwap {
-    // SharedAccountsRoute requires at least 9 accounts:
-    // [0] token_program, [1] program_authority, [2] user_transfer_authority,
-    // [3-6] token accounts, [7] source_mint, [8] destination_mint
-    if accounts.len() < 9 || data.len() < 30 {
-        return ParsedSwap::default();
+/// Settle a transaction's swaps with their token balance deltas
+///
+/// The balance index holds one delta per token account for the whole transaction,
+/// so a delta is a swap's own only when no other swap of the transaction spends from
+/// (or delivers to) the same account. Other sides fall back to the swap's SwapEvent
+/// hops, then to the quoted amounts.
+fn settle_transaction(items: &mut [TradingData], settlements: &[Settlement]) {
+    let sources = account_uses(settlements.iter().map(|settlement| &settlement.source_token_account));
+    let destinations =
+        account_uses(settlements.iter().map(|settlement| &settlement.destination_token_account));
+
+    for settlement in settlements {
+        let executed_in = settlement
+            .executed_in
+            .filter(|_| sources.get(settlement.source_token_account.as_str()) == Some(&1));
+        let executed_out = settlement
+            .executed_out
+            .filter(|_| destinations.get(settlement.destination_token_account.as_str()) == Some(&1));
+
+        settle_swap(
+            &mut items[settlement.item_index],
+            executed_in,
+            executed_out,
+            settlement.output_transfer_fee,
+        );
     }
+}
 
-    let len = data.len();
+/// Charge a Token-2022 transfer fee to the latest swap of the top-level instruction
+/// delivering into `destination`
+fn add_output_transfer_fee(
+    settlements: &mut [Settlement],
+    instruction_index: u32,
+    destination: &str,
+    fee: u64,
+) {
+    let settlement = settlements.iter_mut().rev().find(|settlement| {
+        settlement.instruction_index == instruction_index
+            && settlement.destination_token_account == destination
+    });
+    if let Some(settlement) = settlement {
+        settlement.output_transfer_fee = settlement.output_transfer_fee.saturating_add(fee);
+    }
+}
 
-    // Find in_amount and quoted_out_amount (they're 8 bytes each, near the end)
-    // Pattern: ...route_plan_data... + in_amount(8) + quoted_out_amount(8) + slippage(2) + platform_fee(1)
-    if len >= 19 {
-        let amount_in = read_u64_le(&data[len - 19..len - 11]);
-        let amount_out = read_u64_le(&data[len - 11..len - 3]);
+/// Number of swaps using each (non-empty) token account
+fn account_uses<'a>(accounts: impl Iterator<Item = &'a String>) -> HashMap<&'a str, usize> {
+    let mut uses = HashMap::new();
+    for account in accounts.filter(|account| !account.is_empty()) {
+        *uses.entry(account.as_str()).or_insert(0) += 1;
+    }
+    uses
+}
 
-        if is_valid_amount(amount_in) && amount_out < MAX_REASONABLE_AMOUNT {
-            let (input_mint, output_mint, user_wallet) = extract_mints_from_accounts(accounts);
-            return ParsedSwap {
-                amount_in,
-                amount_out,
-                input_mint,
-                output_mint,
-                user_wallet,
-            };
-        }
+/// Replace a swap's quoted amounts with its own balance deltas and SwapEvent hops
+///
+/// A balance delta already excludes the Token-2022 fee withheld from the output;
+/// SwapEvent and quoted outputs are what left the AMM, so the fee is deducted.
+fn settle_swap(
+    trade: &mut TradingData,
+    executed_in: Option<u64>,
+    executed_out: Option<u64>,
+    output_transfer_fee: u64,
+) {
+    if let Some(amount) = executed_in {
+        trade.amount_in = amount;
+    }
+    if let Some(amount) = executed_out {
+        trade.amount_out = amount;
     }
+    if executed_in.is_some() && executed_out.is_some() {
+        trade.parse_method = ParseMethod::BalanceDelta as i32;
+    }
+    apply_swap_event_amounts(trade, executed_in.is_none(), executed_out.is_none());
+    if executed_out.is_none() {
+        trade.amount_out = trade.amount_out.saturating_sub(output_transfer_fee);
+    }
+    trade.output_transfer_fee = output_transfer_fee;
 
-    // Alternative: amounts might be at a fixed offset after discriminator
-    if len >= 25 {
-        let amount_in = read_u64_le(&data[9..17]);
-        let amount_out = read_u64_le(&data[17..25]);
+    trade.amount_in_normalized = normalized_amount(trade.amount_in, trade.input_decimals);
+    trade.amount_out_normalized = normalized_amount(trade.amount_out, trade.output_decimals);
+}
 
-        if is_valid_amount(amount_in) && amount_out < MAX_REASONABLE_AMOUNT {
-            let (input_mint, output_mint, user_wallet) = extract_mints_from_accounts(accounts);
-            return ParsedSwap {
-                amount_in,
-                amount_out,
-                input_mint,
-                output_mint,
-                user_wallet,
-            };
+/// Settle a route's quoted sides with the amounts of its SwapEvent hops
+///
+/// A side without a balance delta of its own (e.g. wrapped SOL created and closed in
+/// the same transaction) sums the hops spending the input mint or delivering the
+/// output mint. The trade is attributed to SwapEvents once no side is left quoted.
+fn apply_swap_event_amounts(trade: &mut TradingData, mut quoted_in: bool, mut quoted_out: bool) {
+    if !trade.success || trade.hops.is_empty() || !(quoted_in || quoted_out) {
+        return;
+    }
+
+    if quoted_in {
+        let amount = sum_hops(&trade.hops, |hop| hop.input_mint == trade.input_mint, |hop| hop.input_amount);
+        if amount > 0 {
+            trade.amount_in = amount;
+            quoted_in = false;
+        }
+    }
+    if quoted_out {
+        let amount = sum_hops(&trade.hops, |hop| hop.output_mint == trade.output_mint, |hop| hop.output_amount);
+        if amount > 0 {
+            trade.amount_out = amount;
+            quoted_out = false;
         }
     }
 
-    ParsedSwap::default()
+    if !quoted_in && !quoted_out {
+        trade.parse_method = ParseMethod::SwapEvent as i32;
+    }
 }
 
-/// Parse ExactOutRoute instruction
-fn parse_exact_out_route(data: &[u8], accounts: &[String]) -> ParsedSwap {
-    if accounts.len() < 9 || data.len() < 24 {
-        return ParsedSwap::default();
+/// Sum an amount over the hops matching `filter`
+#[inline]
+fn sum_hops(hops: &[SwapHop], filter: impl Fn(&SwapHop) -> bool, amount: impl Fn(&SwapHop) -> u64) -> u64 {
+    hops.iter()
+        .filter(|hop| filter(hop))
+        .fold(0u64, |total, hop| total.saturating_add(amount(hop)))
+}
+
+/// Compute settled input/output amounts from the transaction's token balance deltas
+///
+/// Input is what left the user's source token account, output is what arrived in
+/// the destination token account. Returns `None` for a side whose account has no
+/// balance entry or no movement (e.g. wrapped SOL created and closed in the same
+/// transaction), so the caller can fall back to the quoted amount. Also fills in
+/// mints the instruction accounts don't carry (`route` has no source mint).
+///
+/// For Token-2022 mints with a transfer fee the input includes the fee charged to
+/// the user and the output excludes the fee withheld from the destination; sides
+/// settled otherwise deduct the fee in `settle_swap`.
+fn executed_amounts(
+    parsed: &mut ParsedSwap,
+    balances: &HashMap<String, TokenBalanceChange>,
+) -> (Option<u64>, Option<u64>) {
+    let source = balances.get(&parsed.source_token_account);
+    let destination = balances.get(&parsed.destination_token_account);
+
+    if parsed.input_mint.is_empty() {
+        if let Some(change) = source {
+            parsed.input_mint = change.mint.clone();
+        }
+    }
+    if parsed.output_mint.is_empty() {
+        if let Some(change) = destination {
+            parsed.output_mint = change.mint.clone();
+        }
     }
 
-    // ExactOut has out_amount specified, in_amount is maximum
-    let len = data.len();
-    if len >= 19 {
-        let amount_out = read_u64_le(&data[len - 19..len - 11]);
-        let max_amount_in = read_u64_le(&data[len - 11..len - 3]);
+    let executed_in = source.map(TokenBalanceChange::decrease).filter(|amount| *amount > 0);
+    let executed_out = destination.map(TokenBalanceChange::increase).filter(|amount| *amount > 0);
 
-        if is_valid_amount(max_amount_in) && amount_out < MAX_REASONABLE_AMOUNT {
-            let (input_mint, output_mint, user_wallet) = extract_mints_from_accounts(accounts);
-            return ParsedSwap {
-                amount_in: max_amount_in,
-                amount_out,
-                input_mint,
-                output_mint,
-                user_wallet,
-            };
+    (executed_in, executed_out)
+}
+
+/// Token program of the user's swap token accounts
+///
+/// Token-2022 if either side is a Token-2022 account, otherwise the program
+/// recorded for the accounts; empty when neither account has a balance entry.
+fn swap_token_program(
+    parsed: &ParsedSwap,
+    balances: &HashMap<String, TokenBalanceChange>,
+) -> String {
+    let programs = [&parsed.source_token_account, &parsed.destination_token_account]
+        .into_iter()
+        .filter_map(|account| balances.get(account))
+        .map(|change| change.program_id.as_str());
+
+    let mut token_program = "";
+    for program_id in programs {
+        if program_id == TOKEN_2022_PROGRAM_ID {
+            return program_id.to_string();
+        }
+        if token_program.is_empty() {
+            token_program = program_id;
         }
     }
+    token_program.to_string()
+}
 
-    ParsedSwap::default()
+/// Decimals of a swap mint, from the transaction's token balances or `store_mint_decimals`
+fn resolve_decimals(
+    mint: &str,
+    balances: &HashMap<String, TokenBalanceChange>,
+    stored_decimals: &dyn Fn(&str) -> Option<u32>,
+) -> Option<u32> {
+    if mint.is_empty() {
+        return None;
+    }
+    find_mint_decimals(balances, mint).or_else(|| stored_decimals(mint))
 }
 
-/// Parse SharedAccountsExactOutRoute instruction
-fn parse_shared_accounts_exact_out(data: &[u8], accounts: &[String]) -> ParsedSwap {
-    parse_exact_out_route(data, accounts)
+/// Amount in whole tokens, empty when the mint's decimals are unknown
+#[inline]
+fn normalized_amount(amount: u64, decimals: Option<u32>) -> String {
+    decimals.map(|decimals| format_units(amount, decimals)).unwrap_or_default()
 }
 
-/// Generic swap parsing for unknown instruction formats
+/// Generic swap parsing for unknown v6 instruction formats
 /// Uses heuristics to find likely swap amounts in instruction data
 fn parse_generic_swap(data: &[u8], accounts: &[String]) -> ParsedSwap {
     // Require at least 9 accounts to ensure this is a real swap instruction
@@ -259,6 +790,8 @@ fn parse_generic_swap(data: &[u8], accounts: &[String]) -> ParsedSwap {
                 input_mint,
                 output_mint,
                 user_wallet,
+                method: ParseMethod::Heuristic,
+                ..Default::default()
             };
         }
     }
@@ -269,7 +802,7 @@ fn parse_generic_swap(data: &[u8], accounts: &[String]) -> ParsedSwap {
 /// Validate that an amount looks like a real token amount
 #[inline]
 fn is_valid_amount(amount: u64) -> bool {
-    amount >= MIN_VALID_AMOUNT && amount < MAX_REASONABLE_AMOUNT
+    (MIN_VALID_AMOUNT..MAX_REASONABLE_AMOUNT).contains(&amount)
 }
 
 /// Extract mint addresses and user wallet from accounts
@@ -325,32 +858,47 @@ fn is_jupiter_program(program_id: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::amm_registry::ORCA_WHIRLPOOL_PROGRAM_ID;
+    use crate::constants::{
+        JUPITER_V2_PROGRAM_ID, JUPITER_V3_PROGRAM_ID, JUPITER_V4_PROGRAM_ID, TOKEN_PROGRAM_ID,
+    };
+    use crate::jupiter_legacy::V4_ROUTE_DISCRIMINATOR;
+    use crate::jupiter_v6::{
+        EVENT_IX_TAG, ROUTE_DISCRIMINATOR, SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR, SWAP_EVENT_DISCRIMINATOR,
+    };
+    use substreams_solana::pb::sf::solana::r#type::v1::{
+        CompiledInstruction, InnerInstruction, InnerInstructions, Message, TokenBalance, Transaction,
+        TransactionStatusMeta, UiTokenAmount,
+    };
 
     /// Helper to create test instruction data with route discriminator
     fn create_route_instruction(amount_in: u64, amount_out: u64) -> Vec<u8> {
-        let mut data = Vec::with_capacity(34);
+        let mut data = Vec::with_capacity(35);
         // Route discriminator
         data.extend_from_slice(&ROUTE_DISCRIMINATOR);
-        // Padding (route plan placeholder)
-        data.extend_from_slice(&[0u8; 8]);
+        // Route plan: one Raydium step (variant 7) with 100%, input 0 -> output 1
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[7, 100, 0, 1]);
         // Amount in (little-endian)
         data.extend_from_slice(&amount_in.to_le_bytes());
         // Amount out (little-endian)
         data.extend_from_slice(&amount_out.to_le_bytes());
-        // Slippage (2 bytes)
-        data.extend_from_slice(&[0u8, 0u8]);
+        // Slippage (2 bytes) + platform fee (1 byte)
+        data.extend_from_slice(&[0u8, 0u8, 0u8]);
         data
     }
 
     /// Helper to create test instruction data with shared accounts route discriminator
     fn create_shared_accounts_instruction(amount_in: u64, amount_out: u64) -> Vec<u8> {
-        let mut data = Vec::with_capacity(38);
+        let mut data = Vec::with_capacity(41);
         // Shared accounts route discriminator
         data.extend_from_slice(&SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR);
         // ID byte
         data.push(0);
-        // Padding (route plan placeholder)
-        data.extend_from_slice(&[0u8; 10]);
+        // Route plan: Whirlpool { a_to_b: true } (variant 17) 50% + Raydium 50%
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[17, 1, 50, 0, 1]);
+        data.extend_from_slice(&[7, 50, 0, 1]);
         // Amount in (little-endian)
         data.extend_from_slice(&amount_in.to_le_bytes());
         // Amount out (little-endian)
@@ -427,12 +975,731 @@ mod tests {
         let short_data = vec![0u8; 5];
         let accounts = create_test_accounts();
 
-        let result = parse_jupiter_instruction(&short_data, &accounts);
+        let result = parse_jupiter_instruction(&short_data, &accounts, true);
+
+        assert_eq!(result.amount_in, 0);
+        assert_eq!(result.amount_out, 0);
+    }
+
+    #[test]
+    fn test_parse_route_instruction() {
+        let data = create_route_instruction(1_000_000_000, 150_000_000);
+        // Route layout: token_program, user_transfer_authority, source, user destination,
+        // destination (unset), destination_mint, platform_fee, event_authority, program
+        let accounts: Vec<String> = vec![
+            "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string(),
+            "UserWallet123456789012345678901234567890AB".to_string(),
+            "SourceTokenAccount12345678901234567890ABCD".to_string(),
+            "DestTokenAccount123456789012345678901234AB".to_string(),
+            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string(),
+            "OutputMintAddress12345678901234567890ABCD".to_string(),
+            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string(),
+            "EventAuthority1234567890123456789012345AB".to_string(),
+            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string(),
+        ];
+
+        let result = parse_jupiter_instruction(&data, &accounts, true);
+
+        assert_eq!(result.amount_in, 1_000_000_000);
+        assert_eq!(result.amount_out, 150_000_000);
+        assert_eq!(result.user_wallet, "UserWallet123456789012345678901234567890AB");
+        assert_eq!(result.source_token_account, "SourceTokenAccount12345678901234567890ABCD");
+        assert_eq!(result.destination_token_account, "DestTokenAccount123456789012345678901234AB");
+        assert!(result.input_mint.is_empty());
+        assert_eq!(result.output_mint, "OutputMintAddress12345678901234567890ABCD");
+    }
+
+    #[test]
+    fn test_executed_amounts_from_balances() {
+        let data = create_shared_accounts_instruction(1_000_000, 2_000_000);
+        let accounts = create_test_accounts();
+        let mut parsed = parse_jupiter_instruction(&data, &accounts, true);
+
+        let mut balances = HashMap::new();
+        balances.insert(
+            accounts[3].clone(),
+            TokenBalanceChange {
+                mint: accounts[7].clone(),
+                owner: accounts[2].clone(),
+                pre: 5_000_000,
+                post: 4_000_000,
+                ..Default::default()
+            },
+        );
+        balances.insert(
+            accounts[6].clone(),
+            TokenBalanceChange {
+                mint: accounts[8].clone(),
+                owner: accounts[2].clone(),
+                pre: 0,
+                post: 1_985_000,
+                ..Default::default()
+            },
+        );
+
+        let (executed_in, executed_out) = executed_amounts(&mut parsed, &balances);
+
+        assert_eq!(executed_in, Some(1_000_000));
+        assert_eq!(executed_out, Some(1_985_000));
+        // Quotes are left untouched
+        assert_eq!(parsed.amount_out, 2_000_000);
+    }
+
+    #[test]
+    fn test_route_accounts_from_lookup_tables() {
+        let key = |byte: u8| vec![byte; 32];
+        let program = base58::decode(JUPITER_V6_PROGRAM_ID).unwrap();
+        let token_balance = |account_index: u32, mint: &[u8], amount: u64| TokenBalance {
+            account_index,
+            mint: base58::encode(mint),
+            ui_token_amount: Some(UiTokenAmount {
+                amount: amount.to_string(),
+                ..Default::default()
+            }),
+            owner: base58::encode(key(1)),
+            program_id: TOKEN_PROGRAM_ID.to_string(),
+        };
+
+        // Only the wallet, program and token program are static keys: the user token
+        // accounts are loaded writable and the mints/event authority loaded readonly
+        // [0] wallet, [1] program, [2] token program, [3] source, [4] destination,
+        // [5] input mint, [6] output mint, [7] event authority
+        let trx = ConfirmedTransaction {
+            transaction: Some(Transaction {
+                signatures: vec![vec![9; 64]],
+                message: Some(Message {
+                    account_keys: vec![key(1), program.clone(), key(2)],
+                    instructions: vec![CompiledInstruction {
+                        program_id_index: 1,
+                        accounts: vec![2, 0, 3, 4, 1, 6, 1, 7, 1],
+                        data: create_route_instruction(1_000_000_000, 150_000_000),
+                    }],
+                    versioned: true,
+                    ..Default::default()
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                pre_token_balances: vec![token_balance(3, &key(5), 3_000_000_000)],
+                post_token_balances: vec![
+                    token_balance(3, &key(5), 2_000_000_000),
+                    token_balance(4, &key(6), 151_200_000),
+                ],
+                loaded_writable_addresses: vec![key(3), key(4)],
+                loaded_readonly_addresses: vec![key(5), key(6), key(7)],
+                ..Default::default()
+            }),
+        };
+
+        let account_keys = TransactionAccounts::new(&trx);
+        let instruction = walk_instructions(&trx).next().unwrap();
+        assert_eq!(account_keys.get(instruction.program_id_index), Some(JUPITER_V6_PROGRAM_ID));
+        let accounts = account_keys.resolve(instruction.accounts).unwrap();
+        let balances = build_token_balance_index(&trx, &account_keys);
+
+        let mut parsed = parse_jupiter_instruction(instruction.data, &accounts, true);
+        let (executed_in, executed_out) = executed_amounts(&mut parsed, &balances);
+
+        assert_eq!(parsed.user_wallet, base58::encode(key(1)));
+        assert_eq!(parsed.source_token_account, base58::encode(key(3)));
+        assert_eq!(parsed.destination_token_account, base58::encode(key(4)));
+        // Input mint from the lookup-loaded source balance, output mint from a readonly lookup
+        assert_eq!(parsed.input_mint, base58::encode(key(5)));
+        assert_eq!(parsed.output_mint, base58::encode(key(6)));
+        assert_eq!(executed_in, Some(1_000_000_000));
+        assert_eq!(executed_out, Some(151_200_000));
+    }
+
+    #[test]
+    fn test_token_2022_transfer_fee_output() {
+        let data = create_shared_accounts_instruction(1_000_000, 2_000_000);
+        let accounts = create_test_accounts();
+        let mut parsed = parse_jupiter_instruction(&data, &accounts, true);
+
+        let mut balances = HashMap::new();
+        balances.insert(
+            accounts[3].clone(),
+            TokenBalanceChange {
+                program_id: TOKEN_PROGRAM_ID.to_string(),
+                ..user_balance(&accounts[7], &accounts[2], 5_000_000, 4_000_000)
+            },
+        );
+        // 1% transfer fee withheld from the 2_000_000 delivered to the user
+        balances.insert(
+            accounts[6].clone(),
+            TokenBalanceChange {
+                program_id: TOKEN_2022_PROGRAM_ID.to_string(),
+                ..user_balance(&accounts[8], &accounts[2], 0, 1_980_000)
+            },
+        );
+
+        let (executed_in, executed_out) = executed_amounts(&mut parsed, &balances);
+
+        assert_eq!(executed_in, Some(1_000_000));
+        assert_eq!(executed_out, Some(1_980_000));
+        assert_eq!(swap_token_program(&parsed, &balances), TOKEN_2022_PROGRAM_ID);
+
+        balances.remove(&accounts[6]);
+        assert_eq!(swap_token_program(&parsed, &balances), TOKEN_PROGRAM_ID);
+        assert_eq!(swap_token_program(&parsed, &HashMap::new()), "");
+    }
+
+    /// Balance change of a user-owned token account
+    fn user_balance(mint: &str, owner: &str, pre: u64, post: u64) -> TokenBalanceChange {
+        TokenBalanceChange {
+            mint: mint.to_string(),
+            owner: owner.to_string(),
+            pre,
+            post,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_v4_route_layout() {
+        // v4 route: Swap { Raydium }, in 3_000_000, quoted out 2_900_000
+        let mut data = V4_ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[2, 7]);
+        data.extend_from_slice(&3_000_000u64.to_le_bytes());
+        data.extend_from_slice(&2_900_000u64.to_le_bytes());
+        data.extend_from_slice(&50u16.to_le_bytes());
+        data.push(0);
+        // [0] token_program, [1] user_transfer_authority, [2] destination, [3+] AMM accounts
+        let accounts: Vec<String> = ["token", "wallet", "userOut", "pool", "userIn"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut balances = HashMap::new();
+        balances.insert("userIn".to_string(), user_balance("mintA", "wallet", 3_000_000, 0));
+        balances.insert("userOut".to_string(), user_balance("mintB", "wallet", 0, 2_950_000));
+
+        let mut parsed = parse_v4_instruction(&data, &accounts, &balances);
+        let (executed_in, executed_out) = executed_amounts(&mut parsed, &balances);
+
+        // Not the v6 shared accounts positions
+        assert_eq!(parsed.user_wallet, "wallet");
+        assert_eq!(parsed.amount_in, 3_000_000);
+        assert_eq!(parsed.amount_out, 2_900_000);
+        assert_eq!(parsed.input_mint, "mintA");
+        assert_eq!(parsed.output_mint, "mintB");
+        assert_eq!(executed_in, Some(3_000_000));
+        assert_eq!(executed_out, Some(2_950_000));
+    }
+
+    #[test]
+    fn test_parse_legacy_amm_swap_uses_signer() {
+        // v3 raydium_swap_v2(in_amount: Some(1_000_000), minimum_out_amount: 990_000, fee: 0)
+        let mut data = vec![69, 227, 98, 93, 237, 202, 223, 140, 1];
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&990_000u64.to_le_bytes());
+        data.push(0);
+        let accounts: Vec<String> =
+            ["raydium", "token", "amm", "ammAuthority", "poolIn", "poolOut", "userIn", "userOut", "wallet"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+        let mut balances = HashMap::new();
+        balances.insert("poolOut".to_string(), user_balance("mintB", "ammAuthority", 9_000_000, 8_000_000));
+        balances.insert("poolIn".to_string(), user_balance("mintA", "ammAuthority", 0, 1_000_000));
+        balances.insert("userIn".to_string(), user_balance("mintA", "wallet", 1_000_000, 0));
+        balances.insert("userOut".to_string(), user_balance("mintB", "wallet", 0, 1_000_000));
+        let signers = vec!["wallet".to_string()];
+
+        let mut parsed = parse_amm_swap_instruction(&data, &accounts, &signers, &balances);
+        executed_amounts(&mut parsed, &balances);
+
+        assert_eq!(parsed.user_wallet, "wallet");
+        assert_eq!(parsed.source_token_account, "userIn");
+        assert_eq!(parsed.destination_token_account, "userOut");
+        assert_eq!(parsed.input_mint, "mintA");
+        assert_eq!(parsed.output_mint, "mintB");
+        assert_eq!(parsed.amount_in, 1_000_000);
+        assert_eq!(parsed.amount_out, 990_000);
+
+        // Without a signing instruction account the user cannot be identified
+        let parsed = parse_amm_swap_instruction(&data, &accounts, &[], &balances);
+        assert!(parsed.user_wallet.is_empty());
+        assert_eq!(parsed.amount_in, 0);
+    }
+
+    #[test]
+    fn test_executed_amounts_fill_missing_mint_and_fallback() {
+        let mut parsed = ParsedSwap {
+            amount_in: 1_000,
+            amount_out: 2_000,
+            source_token_account: "source".to_string(),
+            destination_token_account: "destination".to_string(),
+            ..Default::default()
+        };
+
+        let mut balances = HashMap::new();
+        balances.insert(
+            "source".to_string(),
+            TokenBalanceChange {
+                mint: "mintA".to_string(),
+                owner: String::new(),
+                pre: 1_500,
+                post: 500,
+                ..Default::default()
+            },
+        );
+
+        let (executed_in, executed_out) = executed_amounts(&mut parsed, &balances);
+
+        assert_eq!(executed_in, Some(1_000));
+        // Destination has no balance entry: caller falls back to the quote
+        assert_eq!(executed_out, None);
+        assert_eq!(parsed.input_mint, "mintA");
+        assert!(parsed.output_mint.is_empty());
+    }
+
+    #[test]
+    fn test_parse_shared_accounts_route_with_payload_step() {
+        // A Whirlpool step carries a payload byte, which broke tail-offset guessing
+        let data = create_shared_accounts_instruction(42_000, 7_500);
+        let accounts = create_test_accounts();
+
+        let result = parse_jupiter_instruction(&data, &accounts, true);
+
+        assert_eq!(result.amount_in, 42_000);
+        assert_eq!(result.amount_out, 7_500);
+        assert_eq!(result.input_mint, "InputMintAddress123456789012345678901234AB");
+        assert_eq!(result.output_mint, "OutputMintAddress12345678901234567890ABCD");
+    }
+
+    #[test]
+    fn test_parse_route_instruction_malformed_args() {
+        // Truncated args for a known discriminator must not fall back to heuristics
+        let mut data = create_route_instruction(1_000_000_000, 150_000_000);
+        data.truncate(data.len() - 4);
+        let accounts = create_test_accounts();
+
+        let result = parse_jupiter_instruction(&data, &accounts, true);
 
         assert_eq!(result.amount_in, 0);
         assert_eq!(result.amount_out, 0);
     }
 
+    #[test]
+    fn test_parse_swap_event_hop() {
+        let whirlpool = base58::decode(ORCA_WHIRLPOOL_PROGRAM_ID).unwrap();
+        let mut data = EVENT_IX_TAG.to_vec();
+        data.extend_from_slice(&SWAP_EVENT_DISCRIMINATOR);
+        data.extend_from_slice(&whirlpool);
+        data.extend_from_slice(&[8u8; 32]);
+        data.extend_from_slice(&5_000u64.to_le_bytes());
+        data.extend_from_slice(&[9u8; 32]);
+        data.extend_from_slice(&4_900u64.to_le_bytes());
+
+        let swap_data = create_shared_accounts_instruction(5_000, 4_900);
+        let route_plan = decode_route(&swap_data).unwrap().route_plan;
+        let route = RouteContext {
+            item_index: 0,
+            instruction_index: 0,
+            stack_height: 1,
+            route_plan,
+        };
+
+        let hop = parse_swap_event(&data, 1, &route).unwrap();
+
+        assert_eq!(hop.amm, ORCA_WHIRLPOOL_PROGRAM_ID);
+        assert_eq!(hop.input_mint, base58::encode([8u8; 32]));
+        assert_eq!(hop.input_amount, 5_000);
+        assert_eq!(hop.output_mint, base58::encode([9u8; 32]));
+        assert_eq!(hop.output_amount, 4_900);
+        assert_eq!(hop.hop_index, 1);
+        assert_eq!(hop.percent, 50);
+        // The event's AMM program labels the hop over the Raydium route plan step
+        assert_eq!(hop.dex, "orca_whirlpool");
+
+        // Malformed event is skipped, not turned into a zero-amount hop
+        data.truncate(64);
+        assert!(parse_swap_event(&data, 2, &route).is_none());
+    }
+
+    #[test]
+    fn test_swap_events_stay_with_their_route() {
+        let key = |byte: u8| vec![byte; 32];
+        let swap_event = |amount: u64| {
+            let mut data = EVENT_IX_TAG.to_vec();
+            data.extend_from_slice(&SWAP_EVENT_DISCRIMINATOR);
+            data.extend_from_slice(&[8u8; 32]);
+            data.extend_from_slice(&key(5));
+            data.extend_from_slice(&amount.to_le_bytes());
+            data.extend_from_slice(&key(6));
+            data.extend_from_slice(&(amount / 2).to_le_bytes());
+            InnerInstructions {
+                index: 0,
+                instructions: vec![InnerInstruction {
+                    program_id_index: 1,
+                    accounts: vec![7],
+                    data,
+                    stack_height: Some(2),
+                }],
+            }
+        };
+        let route = |data: Vec<u8>| CompiledInstruction {
+            program_id_index: 1,
+            accounts: vec![2, 0, 3, 4, 1, 6, 1, 7, 1],
+            data,
+        };
+        // The second route's args don't decode, so it must not inherit the first route
+        let mut undecodable = create_route_instruction(2_000_000, 900_000);
+        undecodable.truncate(undecodable.len() - 4);
+        let mut second_events = swap_event(2_000_000);
+        second_events.index = 1;
+
+        // [0] wallet, [1] program, [2] token program, [3] source, [4] destination,
+        // [5] input mint, [6] output mint, [7] event authority
+        let trx = ConfirmedTransaction {
+            transaction: Some(Transaction {
+                signatures: vec![vec![9; 64]],
+                message: Some(Message {
+                    account_keys: vec![
+                        key(1),
+                        base58::decode(JUPITER_V6_PROGRAM_ID).unwrap(),
+                        key(2),
+                        key(3),
+                        key(4),
+                        key(5),
+                        key(6),
+                        key(7),
+                    ],
+                    instructions: vec![
+                        route(create_route_instruction(1_000_000, 450_000)),
+                        route(undecodable),
+                    ],
+                    ..Default::default()
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                inner_instructions: vec![swap_event(1_000_000), second_events],
+                ..Default::default()
+            }),
+        };
+
+        let mut items = Vec::new();
+        parse_transaction(&trx, 1, 1_700_000_000, false, &|_| None, &mut items);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].hops.len(), 1);
+        assert_eq!(items[0].hops[0].input_amount, 1_000_000);
+        assert!(items[1].hops.is_empty());
+        assert_eq!(items[1].amount_in, 0);
+    }
+
+    #[test]
+    fn test_output_transfer_fee_deducted_from_fallbacks() {
+        let key = |byte: u8| vec![byte; 32];
+        let swap_event = {
+            let mut data = EVENT_IX_TAG.to_vec();
+            data.extend_from_slice(&SWAP_EVENT_DISCRIMINATOR);
+            data.extend_from_slice(&[9u8; 32]);
+            data.extend_from_slice(&key(5));
+            data.extend_from_slice(&1_000_000u64.to_le_bytes());
+            data.extend_from_slice(&key(6));
+            data.extend_from_slice(&500_000u64.to_le_bytes());
+            InnerInstruction {
+                program_id_index: 1,
+                accounts: vec![7],
+                data,
+                stack_height: Some(2),
+            }
+        };
+        // TransferCheckedWithFee(amount: 500_000, decimals: 6, fee: 5_000) into the destination
+        let mut transfer = vec![26, 1];
+        transfer.extend_from_slice(&500_000u64.to_le_bytes());
+        transfer.push(6);
+        transfer.extend_from_slice(&5_000u64.to_le_bytes());
+        let transfer = InnerInstruction {
+            program_id_index: 8,
+            accounts: vec![7, 6, 4, 7],
+            data: transfer,
+            stack_height: Some(2),
+        };
+
+        // [0] wallet, [1] program, [2] token program, [3] source, [4] destination,
+        // [5] input mint, [6] output mint, [7] event authority, [8] Token-2022.
+        // No balance deltas: the output falls back to the SwapEvent, then the quote.
+        let trade_with = |inner: Vec<InnerInstruction>| {
+            let trx = ConfirmedTransaction {
+                transaction: Some(Transaction {
+                    signatures: vec![vec![9; 64]],
+                    message: Some(Message {
+                        account_keys: vec![
+                            key(1),
+                            base58::decode(JUPITER_V6_PROGRAM_ID).unwrap(),
+                            key(2),
+                            key(3),
+                            key(4),
+                            key(5),
+                            key(6),
+                            key(7),
+                            base58::decode(TOKEN_2022_PROGRAM_ID).unwrap(),
+                        ],
+                        instructions: vec![CompiledInstruction {
+                            program_id_index: 1,
+                            accounts: vec![2, 0, 3, 4, 1, 6, 1, 7, 1],
+                            data: create_route_instruction(1_000_000, 450_000),
+                        }],
+                        ..Default::default()
+                    }),
+                }),
+                meta: Some(TransactionStatusMeta {
+                    inner_instructions: vec![InnerInstructions { index: 0, instructions: inner }],
+                    ..Default::default()
+                }),
+            };
+            parse_single_trade(&trx)
+        };
+
+        let trade = trade_with(vec![swap_event.clone(), transfer.clone()]);
+        assert_eq!(trade.amount_out, 495_000);
+        assert_eq!(trade.output_transfer_fee, 5_000);
+
+        let trade = trade_with(vec![transfer]);
+        assert_eq!(trade.amount_out, 445_000);
+        assert_eq!(trade.quoted_amount_out, 450_000);
+        assert_eq!(trade.output_transfer_fee, 5_000);
+
+        let trade = trade_with(vec![swap_event]);
+        assert_eq!(trade.amount_out, 500_000);
+        assert_eq!(trade.output_transfer_fee, 0);
+    }
+
+    #[test]
+    fn test_legacy_route_legs_merge_into_one_swap() {
+        let key = |byte: u8| vec![byte; 32];
+        let token_balance = |account_index: u32, mint: u8, amount: u64| TokenBalance {
+            account_index,
+            mint: base58::encode(key(mint)),
+            ui_token_amount: Some(UiTokenAmount {
+                amount: amount.to_string(),
+                ..Default::default()
+            }),
+            owner: base58::encode(key(1)),
+            program_id: TOKEN_PROGRAM_ID.to_string(),
+        };
+        // raydium_swap(Some(1_000_000), 500_000, 0): A -> B
+        let mut raydium_swap = vec![177, 173, 42, 240, 184, 4, 124, 81, 1];
+        raydium_swap.extend_from_slice(&1_000_000u64.to_le_bytes());
+        raydium_swap.extend_from_slice(&500_000u64.to_le_bytes());
+        raydium_swap.push(0);
+        // whirlpool_swap(true, None, 2_000_000, 0): B -> C, spending the token ledger
+        let mut whirlpool_swap = vec![123, 229, 184, 63, 12, 0, 92, 145, 1, 0];
+        whirlpool_swap.extend_from_slice(&2_000_000u64.to_le_bytes());
+        whirlpool_swap.push(0);
+
+        // [0] wallet, [1] v3 program, [2] user A account, [3] user B account,
+        // [4] user C account, [5] Raydium pool, [6] Whirlpool
+        let trx = ConfirmedTransaction {
+            transaction: Some(Transaction {
+                signatures: vec![vec![9; 64]],
+                message: Some(Message {
+                    account_keys: vec![
+                        key(1),
+                        base58::decode(JUPITER_V3_PROGRAM_ID).unwrap(),
+                        key(2),
+                        key(3),
+                        key(4),
+                        key(5),
+                        key(6),
+                    ],
+                    instructions: vec![
+                        CompiledInstruction {
+                            program_id_index: 1,
+                            accounts: vec![5, 0, 2, 3],
+                            data: raydium_swap,
+                        },
+                        CompiledInstruction {
+                            program_id_index: 1,
+                            accounts: vec![6, 0, 3, 4],
+                            data: whirlpool_swap,
+                        },
+                    ],
+                    ..Default::default()
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                // The intermediate B account ends where it started
+                pre_token_balances: vec![
+                    token_balance(2, 10, 5_000_000),
+                    token_balance(3, 11, 0),
+                    token_balance(4, 12, 0),
+                ],
+                post_token_balances: vec![
+                    token_balance(2, 10, 4_000_000),
+                    token_balance(3, 11, 0),
+                    token_balance(4, 12, 2_100_000),
+                ],
+                ..Default::default()
+            }),
+        };
+
+        let mut items = Vec::new();
+        parse_transaction(&trx, 1, 1_700_000_000, false, &|_| None, &mut items);
+
+        assert_eq!(items.len(), 1);
+        let trade = &items[0];
+        assert_eq!(trade.user_wallet, base58::encode(key(1)));
+        assert_eq!(trade.input_mint, base58::encode(key(10)));
+        assert_eq!(trade.output_mint, base58::encode(key(12)));
+        assert_eq!(trade.amount_in, 1_000_000);
+        assert_eq!(trade.amount_out, 2_100_000);
+        // Quoted at the last leg's minimum output, not the intermediate token's
+        assert_eq!(trade.quoted_amount_out, 2_000_000);
+        assert_eq!(trade.parse_method, ParseMethod::BalanceDelta as i32);
+    }
+
+    const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+    /// Single-instruction legacy swap between the wallet's USDC and wSOL accounts
+    ///
+    /// Keys: [0] wallet (signer), [1] Jupiter program, [2] user USDC account,
+    /// [3] user wSOL account, [4] pool, [5] token program. Instruction data and
+    /// account orders follow the program's IDL; the amounts are illustrative.
+    fn legacy_swap_transaction(
+        program_id: &str,
+        accounts: Vec<u8>,
+        data: Vec<u8>,
+        usdc: (u64, u64),
+        wsol: (u64, u64),
+    ) -> ConfirmedTransaction {
+        let key = |byte: u8| vec![byte; 32];
+        let token_balance = |account_index: u32, mint: &str, decimals: u32, amount: u64| {
+            TokenBalance {
+                account_index,
+                mint: mint.to_string(),
+                ui_token_amount: Some(UiTokenAmount {
+                    amount: amount.to_string(),
+                    decimals,
+                    ..Default::default()
+                }),
+                owner: base58::encode(key(1)),
+                program_id: TOKEN_PROGRAM_ID.to_string(),
+            }
+        };
+        ConfirmedTransaction {
+            transaction: Some(Transaction {
+                signatures: vec![vec![9; 64]],
+                message: Some(Message {
+                    account_keys: vec![
+                        key(1),
+                        base58::decode(program_id).unwrap(),
+                        key(2),
+                        key(3),
+                        key(4),
+                        base58::decode(TOKEN_PROGRAM_ID).unwrap(),
+                    ],
+                    instructions: vec![CompiledInstruction { program_id_index: 1, accounts, data }],
+                    ..Default::default()
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                pre_token_balances: vec![
+                    token_balance(2, USDC_MINT, 6, usdc.0),
+                    token_balance(3, WSOL_MINT, 9, wsol.0),
+                ],
+                post_token_balances: vec![
+                    token_balance(2, USDC_MINT, 6, usdc.1),
+                    token_balance(3, WSOL_MINT, 9, wsol.1),
+                ],
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Parse a legacy swap transaction expected to hold exactly one trade
+    fn parse_single_trade(trx: &ConfirmedTransaction) -> TradingData {
+        let mut items = Vec::new();
+        parse_transaction(trx, 1, 1_700_000_000, false, &|_| None, &mut items);
+        assert_eq!(items.len(), 1);
+        items.remove(0)
+    }
+
+    #[test]
+    fn test_v2_raydium_swap_transaction() {
+        // raydium_swap(in_amount: Some(25_000_000), minimum_out_amount: 180_000_000, fee: 0)
+        let mut data = vec![177, 173, 42, 240, 184, 4, 124, 81, 1];
+        data.extend_from_slice(&25_000_000u64.to_le_bytes());
+        data.extend_from_slice(&180_000_000u64.to_le_bytes());
+        data.push(0);
+        // [swap_program, amm, user_transfer_authority, source, destination, token_program]
+        let trx = legacy_swap_transaction(
+            JUPITER_V2_PROGRAM_ID,
+            vec![4, 4, 0, 2, 3, 5],
+            data,
+            (100_000_000, 75_000_000),
+            (0, 181_234_567),
+        );
+
+        let trade = parse_single_trade(&trx);
+
+        assert_eq!(trade.user_wallet, base58::encode(vec![1; 32]));
+        assert_eq!(trade.input_mint, USDC_MINT);
+        assert_eq!(trade.output_mint, WSOL_MINT);
+        assert_eq!(trade.amount_in, 25_000_000);
+        assert_eq!(trade.amount_out, 181_234_567);
+        assert_eq!(trade.quoted_amount_out, 180_000_000);
+        assert_eq!(trade.input_decimals, Some(6));
+        assert_eq!(trade.output_decimals, Some(9));
+    }
+
+    #[test]
+    fn test_v3_serum_swap_transaction() {
+        // serum_swap(side: Ask, in_amount: Some(2_000_000_000),
+        //            minimum_out_amount: 270_000_000, platform_fee_bps: 0)
+        let mut data = vec![88, 183, 70, 249, 214, 118, 82, 210, 1, 1];
+        data.extend_from_slice(&2_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&270_000_000u64.to_le_bytes());
+        data.push(0);
+        // [market, authority, order_payer, coin_wallet, pc_wallet, token_program]
+        let trx = legacy_swap_transaction(
+            JUPITER_V3_PROGRAM_ID,
+            vec![4, 0, 3, 3, 2, 5],
+            data,
+            (0, 271_500_000),
+            (2_000_000_000, 0),
+        );
+
+        let trade = parse_single_trade(&trx);
+
+        assert_eq!(trade.user_wallet, base58::encode(vec![1; 32]));
+        assert_eq!(trade.input_mint, WSOL_MINT);
+        assert_eq!(trade.output_mint, USDC_MINT);
+        assert_eq!(trade.amount_in, 2_000_000_000);
+        assert_eq!(trade.amount_out, 271_500_000);
+        assert_eq!(trade.quoted_amount_out, 270_000_000);
+    }
+
+    #[test]
+    fn test_v4_route_transaction() {
+        // route(Swap { Whirlpool { a_to_b: true } }, 25_000_000, 181_000_000, 50, 0)
+        let mut data = V4_ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[2, 17, 1]);
+        data.extend_from_slice(&25_000_000u64.to_le_bytes());
+        data.extend_from_slice(&181_000_000u64.to_le_bytes());
+        data.extend_from_slice(&50u16.to_le_bytes());
+        data.push(0);
+        // [token_program, user_transfer_authority, destination, whirlpool, source]
+        let trx = legacy_swap_transaction(
+            JUPITER_V4_PROGRAM_ID,
+            vec![5, 0, 3, 4, 2],
+            data,
+            (100_000_000, 75_000_000),
+            (0, 181_234_567),
+        );
+
+        let trade = parse_single_trade(&trx);
+
+        assert_eq!(trade.user_wallet, base58::encode(vec![1; 32]));
+        assert_eq!(trade.input_mint, USDC_MINT);
+        assert_eq!(trade.output_mint, WSOL_MINT);
+        assert_eq!(trade.amount_in, 25_000_000);
+        assert_eq!(trade.amount_out, 181_234_567);
+        assert_eq!(trade.quoted_amount_out, 181_000_000);
+    }
+
     #[test]
     fn test_parse_jupiter_instruction_unknown_discriminator() {
         let mut data = vec![0xFFu8; 32]; // Unknown discriminator
@@ -443,13 +1710,148 @@ mod tests {
         data[16..24].copy_from_slice(&amount_out.to_le_bytes());
 
         let accounts = create_test_accounts();
-        let result = parse_jupiter_instruction(&data, &accounts);
+        let result = parse_jupiter_instruction(&data, &accounts, true);
 
         // Generic parser should find the amounts
         assert_eq!(result.amount_in, amount_in);
         assert_eq!(result.amount_out, amount_out);
     }
 
+    #[test]
+    fn test_parse_jupiter_instruction_strict() {
+        let mut data = vec![0xFFu8; 32];
+        data[8..16].copy_from_slice(&1_000_000_000u64.to_le_bytes());
+        data[16..24].copy_from_slice(&500_000_000u64.to_le_bytes());
+        let accounts = create_test_accounts();
+
+        assert_eq!(parse_jupiter_instruction(&data, &accounts, true).method, ParseMethod::Heuristic);
+
+        // Strict pipelines record no guessed amounts
+        let result = parse_jupiter_instruction(&data, &accounts, false);
+        assert_eq!(result.amount_in, 0);
+        assert_eq!(result.method, ParseMethod::Unspecified);
+
+        // Exact decodes are unaffected
+        let data = create_route_instruction(1_000_000_000, 150_000_000);
+        let result = parse_jupiter_instruction(&data, &accounts, false);
+        assert_eq!(result.amount_in, 1_000_000_000);
+        assert_eq!(result.method, ParseMethod::ExactArgs);
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        assert!(!parse_strict("").unwrap());
+        assert!(parse_strict("strict=true").unwrap());
+        assert!(!parse_strict(" strict = false ").unwrap());
+        assert!(parse_strict("strict=yes").is_err());
+        assert!(parse_strict("heuristics=false").is_err());
+    }
+
+    fn create_test_hop(input_mint: &str, input_amount: u64, output_mint: &str, output_amount: u64) -> SwapHop {
+        SwapHop {
+            input_mint: input_mint.to_string(),
+            input_amount,
+            output_mint: output_mint.to_string(),
+            output_amount,
+            ..Default::default()
+        }
+    }
+
+    fn create_test_swap(amount_in: u64, amount_out: u64, hops: Vec<SwapHop>) -> TradingData {
+        TradingData {
+            success: true,
+            input_mint: "mintA".to_string(),
+            output_mint: "mintC".to_string(),
+            amount_in,
+            amount_out,
+            input_decimals: Some(2),
+            output_decimals: Some(2),
+            hops,
+            parse_method: ParseMethod::ExactArgs as i32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_swap_event_amounts() {
+        // Split A->B->C and A->C routes
+        let mut trade = create_test_swap(
+            1_000,
+            900,
+            vec![
+                create_test_hop("mintA", 600, "mintB", 300),
+                create_test_hop("mintB", 300, "mintC", 560),
+                create_test_hop("mintA", 400, "mintC", 380),
+            ],
+        );
+
+        settle_swap(&mut trade, Some(1_000), None, 0);
+
+        assert_eq!(trade.amount_in, 1_000);
+        assert_eq!(trade.amount_out, 940);
+        assert_eq!(trade.amount_out_normalized, "9.4");
+        assert_eq!(trade.parse_method, ParseMethod::SwapEvent as i32);
+
+        // No hop delivers the output mint: the quote stays
+        trade.output_mint = "mintD".to_string();
+        trade.parse_method = ParseMethod::ExactArgs as i32;
+        apply_swap_event_amounts(&mut trade, true, true);
+
+        assert_eq!(trade.amount_in, 1_000);
+        assert_eq!(trade.amount_out, 940);
+        assert_eq!(trade.parse_method, ParseMethod::ExactArgs as i32);
+    }
+
+    #[test]
+    fn test_settle_transaction_shared_source_account() {
+        // Two routes spend from the same source ATA: its 3_000 delta covers both
+        let mut items = vec![
+            create_test_swap(1_000, 900, vec![create_test_hop("mintA", 1_000, "mintC", 950)]),
+            create_test_swap(2_000, 1_800, Vec::new()),
+        ];
+        let settlement = |item_index: usize, destination: &str, executed_out: u64| Settlement {
+            item_index,
+            instruction_index: item_index as u32,
+            source_token_account: "sharedSource".to_string(),
+            destination_token_account: destination.to_string(),
+            executed_in: Some(3_000),
+            executed_out: Some(executed_out),
+            output_transfer_fee: 0,
+        };
+
+        settle_transaction(&mut items, &[settlement(0, "destination0", 960), settlement(1, "destination1", 1_850)]);
+
+        // Input from the route's own SwapEvents, output from its own destination
+        assert_eq!(items[0].amount_in, 1_000);
+        assert_eq!(items[0].amount_out, 960);
+        assert_eq!(items[0].parse_method, ParseMethod::SwapEvent as i32);
+        // Without SwapEvents the input stays quoted
+        assert_eq!(items[1].amount_in, 2_000);
+        assert_eq!(items[1].amount_in_normalized, "20");
+        assert_eq!(items[1].amount_out, 1_850);
+        assert_eq!(items[1].parse_method, ParseMethod::ExactArgs as i32);
+    }
+
+    #[test]
+    fn test_settle_transaction_own_accounts() {
+        let mut items = vec![create_test_swap(1_000, 900, Vec::new())];
+        let settlements = [Settlement {
+            item_index: 0,
+            instruction_index: 0,
+            source_token_account: "source".to_string(),
+            destination_token_account: "destination".to_string(),
+            executed_in: Some(1_000),
+            executed_out: Some(912),
+            output_transfer_fee: 0,
+        }];
+
+        settle_transaction(&mut items, &settlements);
+
+        assert_eq!(items[0].amount_out, 912);
+        assert_eq!(items[0].amount_out_normalized, "9.12");
+        assert_eq!(items[0].parse_method, ParseMethod::BalanceDelta as i32);
+    }
+
     #[test]
     fn test_is_jupiter_program() {
         // Valid Jupiter programs
@@ -0,0 +1,719 @@
+//! Jupiter v6 instruction argument decoder
+//!
+//! Decodes the Borsh-encoded arguments of the v6 aggregator route instructions
+//! (`route`, `shared_accounts_route`, `exact_out_route`, ...) into typed structs,
+//! including the full `Vec<RoutePlanStep>` with its `Swap` enum variants, and
+//! the per-hop `SwapEvent` that v6 emits through a self-CPI.
+//!
+//! Decoding is strict: the buffer must match the instruction layout exactly,
+//! otherwise a [`DecodeError`] is returned instead of zeroed amounts.
+
+use std::fmt;
+
+use borsh::BorshDeserialize;
+
+use crate::constants::JUPITER_V6_PROGRAM_ID;
+
+// Jupiter v6 instruction discriminators (sha256("global:<name>")[..8])
+pub const ROUTE_DISCRIMINATOR: [u8; 8] = [229, 23, 203, 151, 122, 227, 173, 42];
+pub const SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+pub const EXACT_OUT_ROUTE_DISCRIMINATOR: [u8; 8] = [208, 51, 239, 151, 123, 43, 237, 92];
+pub const SHARED_ACCOUNTS_EXACT_OUT_ROUTE_DISCRIMINATOR: [u8; 8] = [176, 209, 105, 168, 154, 125, 69, 62];
+pub const ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR: [u8; 8] = [150, 86, 71, 116, 167, 93, 14, 104];
+pub const SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR: [u8; 8] =
+    [230, 121, 143, 80, 119, 159, 106, 170];
+
+// Anchor event self-CPI prefix (EVENT_IX_TAG, little-endian) and event discriminators
+// (sha256("event:<Name>")[..8])
+pub const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+pub const SWAP_EVENT_DISCRIMINATOR: [u8; 8] = [64, 198, 205, 232, 38, 8, 113, 226];
+
+/// Error returned when instruction data does not match a known Jupiter layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Data is shorter than the 8-byte discriminator
+    TooShort(usize),
+    /// Discriminator is not one of the decoded instructions
+    UnknownDiscriminator([u8; 8]),
+    /// Discriminator matched but the arguments failed to deserialize
+    InvalidArgs(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::TooShort(len) => write!(f, "instruction data too short: {} bytes", len),
+            DecodeError::UnknownDiscriminator(disc) => {
+                write!(f, "unknown discriminator: {}", hex::encode(disc))
+            }
+            DecodeError::InvalidArgs(reason) => write!(f, "invalid instruction args: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Order book side used by several `Swap` variants
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+/// Slice of remaining accounts passed to Token-2022 aware AMMs
+///
+/// `accounts_type` is kept as the raw enum index since only its size matters here.
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemainingAccountsSlice {
+    pub accounts_type: u8,
+    pub length: u8,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemainingAccountsInfo {
+    pub slices: Vec<RemainingAccountsSlice>,
+}
+
+/// AMM swap variant of a route plan step, in Jupiter v6 IDL order
+///
+/// The Borsh enum index is the position in this list, so variants must never be
+/// reordered. Unknown (newer) variants fail to decode rather than being guessed.
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Swap {
+    Saber,
+    SaberAddDecimalsDeposit,
+    SaberAddDecimalsWithdraw,
+    TokenSwap,
+    Sencha,
+    Step,
+    Cropper,
+    Raydium,
+    Crema { a_to_b: bool },
+    Lifinity,
+    Mercurial,
+    Cykura,
+    Serum { side: Side },
+    MarinadeDeposit,
+    MarinadeUnstake,
+    Aldrin { side: Side },
+    AldrinV2 { side: Side },
+    Whirlpool { a_to_b: bool },
+    Invariant { x_to_y: bool },
+    Meteora,
+    GooseFX,
+    DeltaFi { stable: bool },
+    Balansol,
+    MarcoPolo { x_to_y: bool },
+    Dradex { side: Side },
+    LifinityV2,
+    RaydiumClmm,
+    Openbook { side: Side },
+    Phoenix { side: Side },
+    Symmetry { from_token_id: u64, to_token_id: u64 },
+    TokenSwapV2,
+    HeliumTreasuryManagementRedeemV0,
+    StakeDexStakeWrappedSol,
+    StakeDexSwapViaStake { bridge_stake_seed: u32 },
+    GooseFXV2,
+    Perps,
+    PerpsAddLiquidity,
+    PerpsRemoveLiquidity,
+    MeteoraDlmm,
+    OpenBookV2 { side: Side },
+    RaydiumClmmV2,
+    StakeDexPrefundWithdrawStakeAndDepositStake { bridge_stake_seed: u32 },
+    Clone { pool_index: u8, quantity_is_input: bool, quantity_is_collateral: bool },
+    SanctumS {
+        src_lst_value_calc_accs: u8,
+        dst_lst_value_calc_accs: u8,
+        src_lst_index: u32,
+        dst_lst_index: u32,
+    },
+    SanctumSAddLiquidity { lst_value_calc_accs: u8, lst_index: u32 },
+    SanctumSRemoveLiquidity { lst_value_calc_accs: u8, lst_index: u32 },
+    RaydiumCP,
+    WhirlpoolSwapV2 { a_to_b: bool, remaining_accounts_info: Option<RemainingAccountsInfo> },
+    OneIntro,
+    PumpdotfunWrappedBuy,
+    PumpdotfunWrappedSell,
+    PerpsV2,
+    PerpsV2AddLiquidity,
+    PerpsV2RemoveLiquidity,
+    MoonshotWrappedBuy,
+    MoonshotWrappedSell,
+    StabbleStableSwap,
+    StabbleWeightedSwap,
+    Obric { x_to_y: bool },
+    FoxBuyFromEstimatedCost,
+    FoxClaimPartial { is_y: bool },
+    SolFi { is_quote_to_base: bool },
+    SolayerDelegateNoInit,
+    SolayerUndelegateNoInit,
+    TokenMill { side: Side },
+    DaosFunBuy,
+    DaosFunSell,
+    ZeroFi,
+    StakeDexWithdrawWrappedSol,
+    VirtualsBuy,
+    VirtualsSell,
+    Perena { in_index: u8, out_index: u8 },
+    PumpdotfunAmmBuy,
+    PumpdotfunAmmSell,
+    Gamma,
+    MeteoraDlmmSwapV2 { remaining_accounts_info: RemainingAccountsInfo },
+    Woofi,
+    MeteoraDammV2,
+    MeteoraDynamicBondingCurveSwap,
+    StabbleStableSwapV2,
+    StabbleWeightedSwapV2,
+    RaydiumLaunchlabBuy { share_fee_rate: u64 },
+    RaydiumLaunchlabSell { share_fee_rate: u64 },
+    BoopdotfunWrappedBuy,
+    BoopdotfunWrappedSell,
+    Plasma { side: Side },
+    GoonFi { is_bid: bool, blacklist_bump: u8 },
+    HumidiFi { swap_id: u64, is_base_to_quote: bool },
+}
+
+/// One step of a route plan: which AMM to use and how much of the input to send
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RoutePlanStep {
+    pub swap: Swap,
+    /// Share of the input token amount (0-100) routed through this step
+    pub percent: u8,
+    pub input_index: u8,
+    pub output_index: u8,
+}
+
+/// Arguments of `route`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteArgs {
+    pub route_plan: Vec<RoutePlanStep>,
+    pub in_amount: u64,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Arguments of `shared_accounts_route`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SharedAccountsRouteArgs {
+    pub id: u8,
+    pub route_plan: Vec<RoutePlanStep>,
+    pub in_amount: u64,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Arguments of `exact_out_route`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExactOutRouteArgs {
+    pub route_plan: Vec<RoutePlanStep>,
+    pub out_amount: u64,
+    pub quoted_in_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Arguments of `shared_accounts_exact_out_route`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SharedAccountsExactOutRouteArgs {
+    pub id: u8,
+    pub route_plan: Vec<RoutePlanStep>,
+    pub out_amount: u64,
+    pub quoted_in_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Arguments of `route_with_token_ledger` (input amount comes from the ledger account)
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteWithTokenLedgerArgs {
+    pub route_plan: Vec<RoutePlanStep>,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Arguments of `shared_accounts_route_with_token_ledger`
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SharedAccountsRouteWithTokenLedgerArgs {
+    pub id: u8,
+    pub route_plan: Vec<RoutePlanStep>,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Which v6 route instruction was decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteKind {
+    Route,
+    SharedAccountsRoute,
+    ExactOutRoute,
+    SharedAccountsExactOutRoute,
+    RouteWithTokenLedger,
+    SharedAccountsRouteWithTokenLedger,
+}
+
+impl RouteKind {
+    /// Stable name matching the IDL instruction name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteKind::Route => "route",
+            RouteKind::SharedAccountsRoute => "shared_accounts_route",
+            RouteKind::ExactOutRoute => "exact_out_route",
+            RouteKind::SharedAccountsExactOutRoute => "shared_accounts_exact_out_route",
+            RouteKind::RouteWithTokenLedger => "route_with_token_ledger",
+            RouteKind::SharedAccountsRouteWithTokenLedger => {
+                "shared_accounts_route_with_token_ledger"
+            }
+        }
+    }
+
+    /// ExactOut routes fix the output amount and quote a maximum input
+    pub fn is_exact_out(&self) -> bool {
+        matches!(self, RouteKind::ExactOutRoute | RouteKind::SharedAccountsExactOutRoute)
+    }
+}
+
+/// User-facing accounts of a route instruction, resolved per route kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteAccounts<'a> {
+    pub user_wallet: &'a str,
+    pub source_token_account: &'a str,
+    pub destination_token_account: &'a str,
+    /// Not passed to `route`/`route_with_token_ledger`; recover it from token balances
+    pub input_mint: Option<&'a str>,
+    pub output_mint: &'a str,
+}
+
+impl RouteKind {
+    /// Resolve the user-facing accounts from the instruction's account list
+    ///
+    /// Shared accounts layout:
+    /// [0] token_program, [1] program_authority, [2] user_transfer_authority,
+    /// [3] source_token_account, [4] program_source_token_account,
+    /// [5] program_destination_token_account, [6] destination_token_account,
+    /// [7] source_mint, [8] destination_mint
+    ///
+    /// Route / RouteWithTokenLedger layout:
+    /// [0] token_program, [1] user_transfer_authority, [2] user_source_token_account,
+    /// [3] user_destination_token_account, [4] destination_token_account (or the
+    /// program id when unset), [5] destination_mint
+    ///
+    /// ExactOutRoute layout:
+    /// [0] token_program, [1] user_transfer_authority, [2] user_source_token_account,
+    /// [3] user_destination_token_account, [4] destination_token_account (or the
+    /// program id when unset), [5] source_mint, [6] destination_mint
+    pub fn accounts<'a>(&self, accounts: &'a [String]) -> Option<RouteAccounts<'a>> {
+        match self {
+            RouteKind::SharedAccountsRoute
+            | RouteKind::SharedAccountsExactOutRoute
+            | RouteKind::SharedAccountsRouteWithTokenLedger => {
+                if accounts.len() < 9 {
+                    return None;
+                }
+                Some(RouteAccounts {
+                    user_wallet: &accounts[2],
+                    source_token_account: &accounts[3],
+                    destination_token_account: &accounts[6],
+                    input_mint: Some(&accounts[7]),
+                    output_mint: &accounts[8],
+                })
+            }
+            RouteKind::Route | RouteKind::RouteWithTokenLedger => {
+                if accounts.len() < 6 {
+                    return None;
+                }
+                Some(RouteAccounts {
+                    user_wallet: &accounts[1],
+                    source_token_account: &accounts[2],
+                    destination_token_account: optional_destination(accounts),
+                    input_mint: None,
+                    output_mint: &accounts[5],
+                })
+            }
+            RouteKind::ExactOutRoute => {
+                if accounts.len() < 7 {
+                    return None;
+                }
+                Some(RouteAccounts {
+                    user_wallet: &accounts[1],
+                    source_token_account: &accounts[2],
+                    destination_token_account: optional_destination(accounts),
+                    input_mint: Some(&accounts[5]),
+                    output_mint: &accounts[6],
+                })
+            }
+        }
+    }
+}
+
+/// Anchor passes the program id in place of an unset optional account
+#[inline]
+fn optional_destination(accounts: &[String]) -> &str {
+    if accounts[4] == JUPITER_V6_PROGRAM_ID {
+        &accounts[3]
+    } else {
+        &accounts[4]
+    }
+}
+
+/// Route instruction normalized across all six v6 route variants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedRoute {
+    pub kind: RouteKind,
+    /// Shared program authority id (shared accounts variants only)
+    pub id: Option<u8>,
+    pub route_plan: Vec<RoutePlanStep>,
+    /// Exact input for ExactIn routes, quoted maximum input for ExactOut routes,
+    /// zero for token ledger routes (the amount is read from the ledger account)
+    pub in_amount: u64,
+    /// Quoted output for ExactIn routes, exact output for ExactOut routes
+    pub out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Per-hop `SwapEvent` emitted by v6 through a self-CPI on the event authority
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SwapEvent {
+    pub amm: [u8; 32],
+    pub input_mint: [u8; 32],
+    pub input_amount: u64,
+    pub output_mint: [u8; 32],
+    pub output_amount: u64,
+}
+
+/// Check whether instruction data is an Anchor event self-CPI (any event type)
+#[inline]
+pub fn is_event_cpi(data: &[u8]) -> bool {
+    data.len() >= 16 && data[0..8] == EVENT_IX_TAG
+}
+
+/// Decode a `SwapEvent` self-CPI: EVENT_IX_TAG(8) + event discriminator(8) + event
+///
+/// Returns `Ok(None)` for other event types (e.g. `FeeEvent`).
+pub fn decode_swap_event(data: &[u8]) -> Result<Option<SwapEvent>, DecodeError> {
+    if !is_event_cpi(data) {
+        return Err(DecodeError::TooShort(data.len()));
+    }
+    if data[8..16] != SWAP_EVENT_DISCRIMINATOR {
+        return Ok(None);
+    }
+    deserialize(&data[16..]).map(Some)
+}
+
+/// Decode a Jupiter v6 route instruction from raw instruction data
+pub fn decode_route(data: &[u8]) -> Result<DecodedRoute, DecodeError> {
+    if data.len() < 8 {
+        return Err(DecodeError::TooShort(data.len()));
+    }
+
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&data[0..8]);
+    let args = &data[8..];
+
+    match discriminator {
+        ROUTE_DISCRIMINATOR => {
+            let a: RouteArgs = deserialize(args)?;
+            Ok(DecodedRoute {
+                kind: RouteKind::Route,
+                id: None,
+                route_plan: a.route_plan,
+                in_amount: a.in_amount,
+                out_amount: a.quoted_out_amount,
+                slippage_bps: a.slippage_bps,
+                platform_fee_bps: a.platform_fee_bps,
+            })
+        }
+        SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR => {
+            let a: SharedAccountsRouteArgs = deserialize(args)?;
+            Ok(DecodedRoute {
+                kind: RouteKind::SharedAccountsRoute,
+                id: Some(a.id),
+                route_plan: a.route_plan,
+                in_amount: a.in_amount,
+                out_amount: a.quoted_out_amount,
+                slippage_bps: a.slippage_bps,
+                platform_fee_bps: a.platform_fee_bps,
+            })
+        }
+        EXACT_OUT_ROUTE_DISCRIMINATOR => {
+            let a: ExactOutRouteArgs = deserialize(args)?;
+            Ok(DecodedRoute {
+                kind: RouteKind::ExactOutRoute,
+                id: None,
+                route_plan: a.route_plan,
+                in_amount: a.quoted_in_amount,
+                out_amount: a.out_amount,
+                slippage_bps: a.slippage_bps,
+                platform_fee_bps: a.platform_fee_bps,
+            })
+        }
+        SHARED_ACCOUNTS_EXACT_OUT_ROUTE_DISCRIMINATOR => {
+            let a: SharedAccountsExactOutRouteArgs = deserialize(args)?;
+            Ok(DecodedRoute {
+                kind: RouteKind::SharedAccountsExactOutRoute,
+                id: Some(a.id),
+                route_plan: a.route_plan,
+                in_amount: a.quoted_in_amount,
+                out_amount: a.out_amount,
+                slippage_bps: a.slippage_bps,
+                platform_fee_bps: a.platform_fee_bps,
+            })
+        }
+        ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR => {
+            let a: RouteWithTokenLedgerArgs = deserialize(args)?;
+            Ok(DecodedRoute {
+                kind: RouteKind::RouteWithTokenLedger,
+                id: None,
+                route_plan: a.route_plan,
+                in_amount: 0,
+                out_amount: a.quoted_out_amount,
+                slippage_bps: a.slippage_bps,
+                platform_fee_bps: a.platform_fee_bps,
+            })
+        }
+        SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR => {
+            let a: SharedAccountsRouteWithTokenLedgerArgs = deserialize(args)?;
+            Ok(DecodedRoute {
+                kind: RouteKind::SharedAccountsRouteWithTokenLedger,
+                id: Some(a.id),
+                route_plan: a.route_plan,
+                in_amount: 0,
+                out_amount: a.quoted_out_amount,
+                slippage_bps: a.slippage_bps,
+                platform_fee_bps: a.platform_fee_bps,
+            })
+        }
+        _ => Err(DecodeError::UnknownDiscriminator(discriminator)),
+    }
+}
+
+/// Deserialize Borsh args, requiring the whole buffer to be consumed
+#[inline]
+pub(crate) fn deserialize<T: BorshDeserialize>(args: &[u8]) -> Result<T, DecodeError> {
+    T::try_from_slice(args).map_err(|err| DecodeError::InvalidArgs(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode route args tail: in_amount, quoted_out_amount, slippage_bps, platform_fee_bps
+    fn push_amounts(data: &mut Vec<u8>, first: u64, second: u64, slippage_bps: u16, fee_bps: u8) {
+        data.extend_from_slice(&first.to_le_bytes());
+        data.extend_from_slice(&second.to_le_bytes());
+        data.extend_from_slice(&slippage_bps.to_le_bytes());
+        data.push(fee_bps);
+    }
+
+    #[test]
+    fn test_decode_route_single_step() {
+        let mut data = ROUTE_DISCRIMINATOR.to_vec();
+        // route_plan: len=1, Swap::Raydium (7), percent=100, input_index=0, output_index=1
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[7, 100, 0, 1]);
+        push_amounts(&mut data, 1_000_000, 2_000_000, 50, 0);
+
+        let route = decode_route(&data).unwrap();
+
+        assert_eq!(route.kind, RouteKind::Route);
+        assert_eq!(route.id, None);
+        assert_eq!(route.in_amount, 1_000_000);
+        assert_eq!(route.out_amount, 2_000_000);
+        assert_eq!(route.slippage_bps, 50);
+        assert_eq!(route.route_plan.len(), 1);
+        assert_eq!(route.route_plan[0].swap, Swap::Raydium);
+        assert_eq!(route.route_plan[0].percent, 100);
+    }
+
+    #[test]
+    fn test_decode_shared_accounts_route_with_payload_variants() {
+        let mut data = SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR.to_vec();
+        data.push(3); // id
+        data.extend_from_slice(&2u32.to_le_bytes());
+        // Swap::Whirlpool { a_to_b: true } (17), percent=60
+        data.extend_from_slice(&[17, 1, 60, 0, 1]);
+        // Swap::Phoenix { side: Ask } (28), percent=40
+        data.extend_from_slice(&[28, 1, 40, 0, 1]);
+        push_amounts(&mut data, 5_000_000_000, 123_456_789, 100, 20);
+
+        let route = decode_route(&data).unwrap();
+
+        assert_eq!(route.kind, RouteKind::SharedAccountsRoute);
+        assert_eq!(route.id, Some(3));
+        assert_eq!(route.in_amount, 5_000_000_000);
+        assert_eq!(route.out_amount, 123_456_789);
+        assert_eq!(route.platform_fee_bps, 20);
+        assert_eq!(route.route_plan[0].swap, Swap::Whirlpool { a_to_b: true });
+        assert_eq!(route.route_plan[1].swap, Swap::Phoenix { side: Side::Ask });
+        assert_eq!(route.route_plan[1].percent, 40);
+    }
+
+    #[test]
+    fn test_decode_exact_out_route() {
+        let mut data = EXACT_OUT_ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        // Swap::MeteoraDlmm (38)
+        data.extend_from_slice(&[38, 100, 0, 1]);
+        // out_amount, quoted_in_amount
+        push_amounts(&mut data, 750_000, 1_000_000, 50, 0);
+
+        let route = decode_route(&data).unwrap();
+
+        assert!(route.kind.is_exact_out());
+        assert_eq!(route.out_amount, 750_000);
+        assert_eq!(route.in_amount, 1_000_000);
+    }
+
+    #[test]
+    fn test_decode_route_with_token_ledger() {
+        let mut data = ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[46, 100, 0, 1]); // Swap::RaydiumCP
+        data.extend_from_slice(&42_000u64.to_le_bytes());
+        data.extend_from_slice(&30u16.to_le_bytes());
+        data.push(0);
+
+        let route = decode_route(&data).unwrap();
+
+        assert_eq!(route.kind, RouteKind::RouteWithTokenLedger);
+        assert_eq!(route.in_amount, 0);
+        assert_eq!(route.out_amount, 42_000);
+    }
+
+    #[test]
+    fn test_decode_whirlpool_v2_remaining_accounts() {
+        let mut data = ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        // Swap::WhirlpoolSwapV2 (47) { a_to_b: false, Some(slices: [ {0, 2} ]) }
+        data.extend_from_slice(&[47, 0, 1]);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[0, 2]);
+        data.extend_from_slice(&[100, 0, 1]);
+        push_amounts(&mut data, 9_000, 8_000, 10, 0);
+
+        let route = decode_route(&data).unwrap();
+
+        assert_eq!(route.in_amount, 9_000);
+        match &route.route_plan[0].swap {
+            Swap::WhirlpoolSwapV2 { a_to_b, remaining_accounts_info } => {
+                assert!(!a_to_b);
+                assert_eq!(remaining_accounts_info.as_ref().unwrap().slices[0].length, 2);
+            }
+            other => panic!("unexpected swap variant {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_route_accounts_per_kind() {
+        let accounts: Vec<String> = (0..10).map(|i| format!("account{}", i)).collect();
+
+        let shared = RouteKind::SharedAccountsRoute.accounts(&accounts).unwrap();
+        assert_eq!(shared.user_wallet, "account2");
+        assert_eq!(shared.source_token_account, "account3");
+        assert_eq!(shared.destination_token_account, "account6");
+        assert_eq!(shared.input_mint, Some("account7"));
+        assert_eq!(shared.output_mint, "account8");
+
+        let route = RouteKind::Route.accounts(&accounts).unwrap();
+        assert_eq!(route.user_wallet, "account1");
+        assert_eq!(route.source_token_account, "account2");
+        assert_eq!(route.destination_token_account, "account4");
+        assert_eq!(route.input_mint, None);
+        assert_eq!(route.output_mint, "account5");
+
+        let exact_out = RouteKind::ExactOutRoute.accounts(&accounts).unwrap();
+        assert_eq!(exact_out.input_mint, Some("account5"));
+        assert_eq!(exact_out.output_mint, "account6");
+
+        assert!(RouteKind::SharedAccountsRoute.accounts(&accounts[..8]).is_none());
+    }
+
+    #[test]
+    fn test_route_accounts_unset_destination() {
+        let mut accounts: Vec<String> = (0..9).map(|i| format!("account{}", i)).collect();
+        accounts[4] = JUPITER_V6_PROGRAM_ID.to_string();
+
+        let route = RouteKind::Route.accounts(&accounts).unwrap();
+        assert_eq!(route.destination_token_account, "account3");
+    }
+
+    fn create_swap_event_data(input_amount: u64, output_amount: u64) -> Vec<u8> {
+        let mut data = EVENT_IX_TAG.to_vec();
+        data.extend_from_slice(&SWAP_EVENT_DISCRIMINATOR);
+        data.extend_from_slice(&[1u8; 32]); // amm
+        data.extend_from_slice(&[2u8; 32]); // input_mint
+        data.extend_from_slice(&input_amount.to_le_bytes());
+        data.extend_from_slice(&[3u8; 32]); // output_mint
+        data.extend_from_slice(&output_amount.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_decode_swap_event() {
+        let data = create_swap_event_data(1_000_000, 24_500);
+        assert!(is_event_cpi(&data));
+
+        let event = decode_swap_event(&data).unwrap().unwrap();
+
+        assert_eq!(event.amm, [1u8; 32]);
+        assert_eq!(event.input_mint, [2u8; 32]);
+        assert_eq!(event.input_amount, 1_000_000);
+        assert_eq!(event.output_mint, [3u8; 32]);
+        assert_eq!(event.output_amount, 24_500);
+    }
+
+    #[test]
+    fn test_decode_swap_event_other_and_invalid() {
+        // FeeEvent: a self-CPI event, but not a swap hop
+        let mut fee_event = EVENT_IX_TAG.to_vec();
+        fee_event.extend_from_slice(&[73, 79, 78, 127, 184, 213, 13, 220]);
+        fee_event.extend_from_slice(&[0u8; 72]);
+        assert!(is_event_cpi(&fee_event));
+        assert_eq!(decode_swap_event(&fee_event), Ok(None));
+
+        // Truncated SwapEvent
+        let mut data = create_swap_event_data(1, 2);
+        data.truncate(100);
+        assert!(matches!(decode_swap_event(&data), Err(DecodeError::InvalidArgs(_))));
+
+        // Route instruction data is not an event
+        assert!(!is_event_cpi(&ROUTE_DISCRIMINATOR));
+        assert!(decode_swap_event(&ROUTE_DISCRIMINATOR).is_err());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode_route(&[1, 2, 3]), Err(DecodeError::TooShort(3)));
+        assert_eq!(
+            decode_route(&[0xFF; 16]),
+            Err(DecodeError::UnknownDiscriminator([0xFF; 8]))
+        );
+
+        // Truncated args must not decode to zeros
+        let mut data = ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[7, 100, 0, 1]);
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        assert!(matches!(decode_route(&data), Err(DecodeError::InvalidArgs(_))));
+
+        // Trailing bytes are rejected too
+        let mut data = ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&0u32.to_le_bytes());
+        push_amounts(&mut data, 1, 2, 3, 4);
+        data.push(0);
+        assert!(matches!(decode_route(&data), Err(DecodeError::InvalidArgs(_))));
+
+        // Unknown swap variant
+        let mut data = ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[250, 100, 0, 1]);
+        push_amounts(&mut data, 1, 2, 3, 4);
+        assert!(matches!(decode_route(&data), Err(DecodeError::InvalidArgs(_))));
+    }
+}
@@ -1,17 +1,41 @@
 pub mod constants;
+pub mod amm_registry;
+pub mod jupiter_v6;
+pub mod jupiter_legacy;
+pub mod jupiter_limit_order;
+pub mod jupiter_dca;
+pub mod token_balances;
+pub mod transaction_error;
+pub mod transaction_view;
 pub mod pb;
 pub mod spl_account_store;
+pub mod mint_decimals;
+pub mod token_metadata;
 pub mod jupiter_trading_store;
+pub mod parse_diagnostics;
+pub mod price_outliers;
+pub mod oracle_prices;
 pub mod token_price_store;
 pub mod jupiter_instructions;
 pub mod jupiter_analytics;
+pub mod swap_events;
+pub mod limit_order_events;
+pub mod dca_events;
 pub mod db_out;
 pub mod stores;
 
-pub use spl_account_store::map_spl_initialized_account;
+pub use spl_account_store::{map_spl_initialized_account, store_account_owners};
+pub use mint_decimals::{map_mint_decimals, store_mint_decimals};
+pub use token_metadata::{map_token_metadata, store_metadata_mints};
 pub use jupiter_trading_store::map_jupiter_trading_data;
-pub use token_price_store::map_token_prices;
+pub use parse_diagnostics::map_parse_diagnostics;
+pub use price_outliers::{map_validated_trading_data, store_pair_price_reference};
+pub use oracle_prices::{map_oracle_prices, store_oracle_prices};
+pub use token_price_store::{map_token_prices, store_token_last_price, store_token_volume_usd};
 pub use jupiter_instructions::map_jupiter_instructions;
 pub use jupiter_analytics::map_jupiter_analytics;
+pub use swap_events::map_swap_events;
+pub use limit_order_events::map_limit_order_events;
+pub use dca_events::{map_dca_events, map_dca_positions, store_dca_positions};
 pub use db_out::db_out;
-pub use stores::{store_swap_volumes, store_unique_traders, store_token_stats};
\ No newline at end of file
+pub use stores::{store_swap_volumes, store_dex_volume_usd, store_unique_traders, store_token_stats};
\ No newline at end of file
@@ -0,0 +1,288 @@
+//! Limit order events
+//!
+//! Decodes Jupiter Limit Order program instructions into
+//! `jupiter.events.v1.LimitOrderEvent` records: one per order creation, fill,
+//! cancellation or expiry. Filled and refunded amounts come from the token
+//! balance deltas of the order reserve and the maker's token account.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::constants::{is_jupiter_limit_orders, quote_rank};
+use crate::jupiter_limit_order::{decode_limit_order, LimitOrderInstruction, OrderAccounts};
+use crate::jupiter_v6::DecodeError;
+use crate::pb::jupiter::events::v1::{
+    LimitOrderEvent, LimitOrderEvents, LimitOrderStatus, LimitOrderType,
+};
+use crate::token_balances::{build_token_balance_index, TokenBalanceChange};
+use crate::transaction_view::TransactionAccounts;
+use substreams::errors::Error;
+use substreams::log;
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
+
+// Prices are quote units per base unit, scaled like candle prices
+const PRICE_SCALE: u128 = 1_000_000;
+
+#[substreams::handlers::map]
+pub fn map_limit_order_events(block: Block) -> Result<LimitOrderEvents, Error> {
+    let mut events = Vec::new();
+
+    let timestamp = block
+        .block_time
+        .as_ref()
+        .map(|ts| ts.timestamp.max(0) as u64)
+        .unwrap_or_default();
+
+    for trx in block.transactions() {
+        let mut balances: Option<HashMap<String, TokenBalanceChange>> = None;
+
+        for instruction in trx.walk_instructions() {
+            if !is_jupiter_limit_orders(&instruction.program_id().to_string()) {
+                continue;
+            }
+
+            let decoded = match decode_limit_order(instruction.data()) {
+                Ok(decoded) => decoded,
+                Err(DecodeError::UnknownDiscriminator(_)) | Err(DecodeError::TooShort(_)) => continue,
+                Err(err) => {
+                    log::info!("Failed to decode Jupiter limit order instruction: {}", err);
+                    continue;
+                }
+            };
+
+            let accounts: Vec<String> = instruction
+                .accounts()
+                .iter()
+                .map(|address| address.to_string())
+                .collect();
+
+            // Only build the balance index for transactions that touch an order
+            let balances = balances.get_or_insert_with(|| {
+                build_token_balance_index(trx, &TransactionAccounts::new(trx))
+            });
+
+            if let Some(mut event) = to_limit_order_event(&decoded, &accounts, balances) {
+                event.transaction_signature = trx.id();
+                event.slot = block.slot;
+                event.timestamp = timestamp;
+                events.push(event);
+            }
+        }
+    }
+
+    Ok(LimitOrderEvents {
+        events,
+        block_number: block.slot,
+        block_hash: block.blockhash,
+        timestamp,
+    })
+}
+
+/// Build a LimitOrderEvent from a decoded instruction (extracted for testability)
+///
+/// Returns `None` for instructions that do not change an order (`pre_flash_fill_order`,
+/// whose fill is reported by the following `flash_fill_order`, and `withdraw_fee`).
+/// A fill leaves the order Pending unless it empties the reserve.
+fn to_limit_order_event(
+    decoded: &LimitOrderInstruction,
+    accounts: &[String],
+    balances: &HashMap<String, TokenBalanceChange>,
+) -> Option<LimitOrderEvent> {
+    let order_accounts = decoded.accounts(accounts)?;
+    let reserve = balances.get(order_accounts.reserve);
+    let maker_token = balances.get(order_accounts.maker_token_account);
+    let fill_status = if reserve.is_some_and(|change| change.post == 0) {
+        LimitOrderStatus::Filled
+    } else {
+        LimitOrderStatus::Pending
+    };
+
+    let (input_amount, output_amount, status) = match decoded {
+        LimitOrderInstruction::InitializeOrder(args) => {
+            (args.making_amount, args.taking_amount, LimitOrderStatus::Pending)
+        }
+        LimitOrderInstruction::FillOrder(args) => (
+            reserve.map(TokenBalanceChange::decrease).unwrap_or(args.making_amount),
+            maker_token.map(TokenBalanceChange::increase).unwrap_or(args.max_taking_amount),
+            fill_status,
+        ),
+        LimitOrderInstruction::FlashFillOrder(args) => (
+            reserve.map(TokenBalanceChange::decrease).unwrap_or_default(),
+            maker_token.map(TokenBalanceChange::increase).unwrap_or(args.max_taking_amount),
+            fill_status,
+        ),
+        // Cancels refund the unfilled reserve to the maker
+        LimitOrderInstruction::CancelOrder => (
+            reserve.map(TokenBalanceChange::decrease).unwrap_or_default(),
+            0,
+            LimitOrderStatus::Cancelled,
+        ),
+        LimitOrderInstruction::CancelExpiredOrder => (
+            reserve.map(TokenBalanceChange::decrease).unwrap_or_default(),
+            0,
+            LimitOrderStatus::Expired,
+        ),
+        LimitOrderInstruction::PreFlashFillOrder(_) | LimitOrderInstruction::WithdrawFee => {
+            return None
+        }
+    };
+
+    let (input_mint, output_mint) = order_mints(decoded, &order_accounts, reserve, maker_token);
+    let order_type = order_type(&input_mint, &output_mint);
+
+    Some(LimitOrderEvent {
+        user: order_accounts.maker.to_string(),
+        order_id: order_accounts.order.to_string(),
+        price: order_price(order_type, input_amount, output_amount),
+        input_mint,
+        output_mint,
+        input_amount,
+        output_amount,
+        order_type: order_type as i32,
+        status: status as i32,
+        ..Default::default()
+    })
+}
+
+/// Input/output mints from the instruction accounts, falling back to token balances
+///
+/// The reserve always holds the input mint. The maker token account holds the
+/// output mint for fills and the input mint for cancels.
+fn order_mints(
+    decoded: &LimitOrderInstruction,
+    order_accounts: &OrderAccounts,
+    reserve: Option<&TokenBalanceChange>,
+    maker_token: Option<&TokenBalanceChange>,
+) -> (String, String) {
+    let balance_mint = |change: Option<&TokenBalanceChange>| {
+        change.map(|change| change.mint.clone()).unwrap_or_default()
+    };
+
+    let input_mint = match order_accounts.input_mint {
+        Some(mint) => mint.to_string(),
+        None => balance_mint(reserve),
+    };
+    let output_mint = match (order_accounts.output_mint, decoded) {
+        (Some(mint), _) => mint.to_string(),
+        (None, LimitOrderInstruction::FillOrder(_) | LimitOrderInstruction::FlashFillOrder(_)) => {
+            balance_mint(maker_token)
+        }
+        (None, _) => String::new(),
+    };
+
+    (input_mint, output_mint)
+}
+
+/// Classify an order by which side is the quote token (stablecoin, then SOL)
+fn order_type(input_mint: &str, output_mint: &str) -> LimitOrderType {
+    match quote_rank(output_mint).cmp(&quote_rank(input_mint)) {
+        Ordering::Greater => LimitOrderType::Sell,
+        Ordering::Less => LimitOrderType::Buy,
+        Ordering::Equal => LimitOrderType::Unknown,
+    }
+}
+
+/// Price of the base token in quote units, scaled by 1e6
+///
+/// Orders without a quote side are priced as output per input.
+fn order_price(order_type: LimitOrderType, input_amount: u64, output_amount: u64) -> u64 {
+    let (quote, base) = match order_type {
+        LimitOrderType::Buy => (input_amount, output_amount),
+        LimitOrderType::Sell | LimitOrderType::Unknown => (output_amount, input_amount),
+    };
+
+    if base == 0 {
+        return 0;
+    }
+    (quote as u128 * PRICE_SCALE / base as u128).min(u64::MAX as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{USDC_MINT, WSOL_MINT};
+    use crate::jupiter_limit_order::{FillOrderArgs, InitializeOrderArgs};
+
+    fn create_test_accounts(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("account{}", i)).collect()
+    }
+
+    fn balance_change(mint: &str, pre: u64, post: u64) -> TokenBalanceChange {
+        TokenBalanceChange {
+            mint: mint.to_string(),
+            owner: "owner".to_string(),
+            pre,
+            post,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_initialize_order_event() {
+        let mut accounts = create_test_accounts(12);
+        accounts[7] = WSOL_MINT.to_string();
+        accounts[8] = USDC_MINT.to_string();
+        let decoded = LimitOrderInstruction::InitializeOrder(InitializeOrderArgs {
+            making_amount: 2_000_000_000,
+            taking_amount: 300_000_000,
+            expired_at: None,
+        });
+
+        let event = to_limit_order_event(&decoded, &accounts, &HashMap::new()).unwrap();
+
+        assert_eq!(event.user, "account1");
+        assert_eq!(event.order_id, "account2");
+        assert_eq!(event.input_amount, 2_000_000_000);
+        assert_eq!(event.output_amount, 300_000_000);
+        assert_eq!(event.order_type, LimitOrderType::Sell as i32);
+        assert_eq!(event.status, LimitOrderStatus::Pending as i32);
+        // 300 USDC (6 dp) for 2 SOL (9 dp), in raw units scaled by 1e6
+        assert_eq!(event.price, 150_000);
+    }
+
+    #[test]
+    fn test_fill_order_uses_balance_deltas() {
+        let accounts = create_test_accounts(12);
+        let mut balances = HashMap::new();
+        balances.insert("account1".to_string(), balance_change(USDC_MINT, 500_000, 200_000));
+        balances.insert("account5".to_string(), balance_change(WSOL_MINT, 0, 2_000_000));
+        let decoded = LimitOrderInstruction::FillOrder(FillOrderArgs {
+            making_amount: 999,
+            max_taking_amount: 999,
+        });
+
+        let event = to_limit_order_event(&decoded, &accounts, &balances).unwrap();
+
+        assert_eq!(event.input_mint, USDC_MINT);
+        assert_eq!(event.output_mint, WSOL_MINT);
+        assert_eq!(event.input_amount, 300_000);
+        assert_eq!(event.output_amount, 2_000_000);
+        assert_eq!(event.order_type, LimitOrderType::Buy as i32);
+        // Part of the reserve is left: the order stays open
+        assert_eq!(event.status, LimitOrderStatus::Pending as i32);
+
+        balances.insert("account1".to_string(), balance_change(USDC_MINT, 200_000, 0));
+        let event = to_limit_order_event(&decoded, &accounts, &balances).unwrap();
+
+        assert_eq!(event.input_amount, 200_000);
+        assert_eq!(event.status, LimitOrderStatus::Filled as i32);
+    }
+
+    #[test]
+    fn test_cancel_and_non_order_instructions() {
+        let accounts = create_test_accounts(8);
+        let mut balances = HashMap::new();
+        balances.insert("account1".to_string(), balance_change("mintA", 750, 0));
+
+        let event =
+            to_limit_order_event(&LimitOrderInstruction::CancelExpiredOrder, &accounts, &balances)
+                .unwrap();
+        assert_eq!(event.input_mint, "mintA");
+        assert_eq!(event.input_amount, 750);
+        assert_eq!(event.price, 0);
+        assert_eq!(event.status, LimitOrderStatus::Expired as i32);
+
+        assert!(to_limit_order_event(&LimitOrderInstruction::WithdrawFee, &accounts, &balances)
+            .is_none());
+    }
+}
@@ -0,0 +1,171 @@
+//! Mint decimals
+//!
+//! Records the decimals of every mint seen in a block, so raw base unit amounts
+//! can be normalized to whole tokens. Decimals come from the InitializeMint and
+//! InitializeMint2 instructions of the Token and Token-2022 programs, and from the
+//! `ui_token_amount` of transaction token balances, which also covers mints
+//! created before the initial block.
+
+use std::collections::HashSet;
+
+use crate::constants::is_token_program;
+use crate::pb::sf::jupiter::v1::{MintDecimals, MintDecimalsList};
+use substreams::errors::Error;
+use substreams::store::{StoreNew, StoreSetIfNotExists, StoreSetIfNotExistsInt64};
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
+
+// SPL Token instruction tags (first data byte)
+const INITIALIZE_MINT: u8 = 0;
+const INITIALIZE_MINT_2: u8 = 20;
+
+// [tag, decimals, mint_authority, COption<freeze_authority>] with the freeze
+// authority None (1 byte) or Some (33 bytes)
+const INITIALIZE_MINT_LEN: [usize; 2] = [35, 67];
+
+#[substreams::handlers::map]
+pub fn map_mint_decimals(block: Block) -> Result<MintDecimalsList, Error> {
+    let mut items = Vec::new();
+    let mut seen = HashSet::new();
+
+    for trx in block.transactions() {
+        for instruction in trx.walk_instructions() {
+            if !is_token_program(&instruction.program_id().to_string()) {
+                continue;
+            }
+            // InitializeMint: [0] mint, [1] rent; InitializeMint2: [0] mint
+            if let Some(decimals) = decode_initialize_mint(instruction.data()) {
+                if let Some(mint) = instruction.accounts().first() {
+                    push_mint_decimals(&mut items, &mut seen, mint.to_string(), decimals);
+                }
+            }
+        }
+
+        if let Some(meta) = trx.meta.as_ref() {
+            for balance in meta.pre_token_balances.iter().chain(&meta.post_token_balances) {
+                if let Some(amount) = balance.ui_token_amount.as_ref() {
+                    push_mint_decimals(&mut items, &mut seen, balance.mint.clone(), amount.decimals);
+                }
+            }
+        }
+    }
+
+    Ok(MintDecimalsList { items })
+}
+
+/// Store handler for the decimals of every mint
+///
+/// Key format: `mint:{address}`
+/// Value: decimals (stored only if not exists, a mint's decimals never change)
+#[substreams::handlers::store]
+pub fn store_mint_decimals(decimals: MintDecimalsList, store: StoreSetIfNotExistsInt64) {
+    for (ordinal, item) in decimals.items.iter().enumerate() {
+        store.set_if_not_exists(ordinal as u64, decimals_key(&item.mint), &(item.decimals as i64));
+    }
+}
+
+/// Key of a mint's decimals in `store_mint_decimals`
+#[inline]
+pub fn decimals_key(mint: &str) -> String {
+    format!("mint:{}", mint)
+}
+
+/// Format a raw base unit amount in whole tokens, e.g. 1_500_000 with 6 decimals is "1.5"
+///
+/// Exact: the amount is shifted as a digit string rather than divided as a float.
+pub fn format_units(amount: u64, decimals: u32) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Decode the decimals of an InitializeMint/InitializeMint2 instruction
+///
+/// Returns `None` for every other Token Program instruction.
+fn decode_initialize_mint(data: &[u8]) -> Option<u32> {
+    match data.first()? {
+        &INITIALIZE_MINT | &INITIALIZE_MINT_2 if INITIALIZE_MINT_LEN.contains(&data.len()) => {
+            Some(data[1] as u32)
+        }
+        _ => None,
+    }
+}
+
+/// Record a mint's decimals once per block
+#[inline]
+fn push_mint_decimals(
+    items: &mut Vec<MintDecimals>,
+    seen: &mut HashSet<String>,
+    mint: String,
+    decimals: u32,
+) {
+    if !mint.is_empty() && seen.insert(mint.clone()) {
+        items.push(MintDecimals { mint, decimals });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn initialize_mint(tag: u8, decimals: u8, freeze_authority: bool) -> Vec<u8> {
+        let mut data = vec![tag, decimals];
+        data.extend_from_slice(&[7; 32]);
+        if freeze_authority {
+            data.push(1);
+            data.extend_from_slice(&[8; 32]);
+        } else {
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn test_decode_initialize_mint() {
+        assert_eq!(decode_initialize_mint(&initialize_mint(0, 6, false)), Some(6));
+        assert_eq!(decode_initialize_mint(&initialize_mint(0, 9, true)), Some(9));
+        assert_eq!(decode_initialize_mint(&initialize_mint(20, 2, true)), Some(2));
+
+        // InitializeAccount (1) and a truncated mint authority
+        assert_eq!(decode_initialize_mint(&initialize_mint(1, 6, false)), None);
+        assert_eq!(decode_initialize_mint(&initialize_mint(0, 6, false)[..20]), None);
+        assert_eq!(decode_initialize_mint(&[]), None);
+    }
+
+    #[test]
+    fn test_format_units() {
+        assert_eq!(format_units(1_500_000, 6), "1.5");
+        assert_eq!(format_units(1_000_000_000, 9), "1");
+        assert_eq!(format_units(42, 6), "0.000042");
+        assert_eq!(format_units(0, 9), "0");
+        assert_eq!(format_units(12_345, 0), "12345");
+        assert_eq!(format_units(u64::MAX, 18), "18.446744073709551615");
+    }
+
+    #[test]
+    fn test_push_mint_decimals_once_per_mint() {
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+
+        push_mint_decimals(&mut items, &mut seen, "mintA".to_string(), 6);
+        push_mint_decimals(&mut items, &mut seen, "mintA".to_string(), 6);
+        push_mint_decimals(&mut items, &mut seen, "mintB".to_string(), 9);
+        push_mint_decimals(&mut items, &mut seen, String::new(), 9);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].mint, "mintB");
+        assert_eq!(items[1].decimals, 9);
+        assert_eq!(decimals_key("mintA"), "mint:mintA");
+    }
+}
@@ -0,0 +1,339 @@
+//! Oracle reference prices
+//!
+//! Decodes the Pyth price updates posted in a block, so Jupiter execution prices
+//! can be measured against an independent reference.
+//!
+//! Pyth prices reach Solana through the Pyth Solana Receiver: `post_update` and
+//! `post_update_atomic` verify a Wormhole-signed merkle update and write its
+//! `PriceFeedMessage` to a price update account. Push oracle feeds
+//! (`update_price_feed`) CPI into `post_update`, so walking inner instructions
+//! covers both.
+//!
+//! Feeds are identified by their 32-byte id. The module params map feed ids to
+//! mints, e.g. `ef0d8b…b56d=So11111111111111111111111111111111111111112&…`;
+//! prices of unmapped feeds are emitted with an empty mint.
+
+// The handler macro reads the `params` string from a raw pointer in the generated export
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use std::collections::{BTreeMap, HashMap};
+
+use borsh::BorshDeserialize;
+
+use crate::constants::PYTH_RECEIVER_PROGRAM_ID;
+use crate::jupiter_v6::{deserialize, DecodeError};
+use crate::pb::sf::jupiter::v1::{OraclePrice, OraclePriceList, TradingData};
+use substreams::errors::Error;
+use substreams::log;
+use substreams::store::{StoreNew, StoreSet, StoreSetProto};
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
+
+// Pyth Solana Receiver instruction discriminators (sha256("global:<name>")[..8])
+pub const POST_UPDATE_DISCRIMINATOR: [u8; 8] = [133, 95, 207, 175, 11, 79, 118, 44];
+pub const POST_UPDATE_ATOMIC_DISCRIMINATOR: [u8; 8] = [49, 172, 84, 192, 175, 180, 52, 234];
+
+// post_update: [4] price update account; post_update_atomic: [4] price update account
+const PRICE_UPDATE_ACCOUNT_INDEX: usize = 4;
+
+/// Pythnet message type of a `PriceFeedMessage` (first message byte)
+const PRICE_FEED_MESSAGE: u8 = 0;
+/// type(1) + feed_id(32) + price(8) + conf(8) + exponent(4) + publish_time(8)
+/// + prev_publish_time(8) + ema_price(8) + ema_conf(8)
+const PRICE_FEED_MESSAGE_LEN: usize = 85;
+
+/// Pythnet `MerklePriceUpdate`: the serialized message and its merkle proof
+#[derive(BorshDeserialize)]
+struct MerklePriceUpdate {
+    message: Vec<u8>,
+    _proof: Vec<[u8; 20]>,
+}
+
+#[derive(BorshDeserialize)]
+struct PostUpdateParams {
+    merkle_price_update: MerklePriceUpdate,
+    _treasury_id: u8,
+}
+
+#[derive(BorshDeserialize)]
+struct PostUpdateAtomicParams {
+    _vaa: Vec<u8>,
+    merkle_price_update: MerklePriceUpdate,
+    _treasury_id: u8,
+}
+
+/// A decoded Pythnet `PriceFeedMessage`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub ema_price: i64,
+}
+
+impl PriceFeedMessage {
+    fn to_oracle_price(&self) -> OraclePrice {
+        let scale = 10f64.powi(self.exponent);
+        OraclePrice {
+            feed_id: hex::encode(self.feed_id),
+            price: self.price as f64 * scale,
+            confidence: self.conf as f64 * scale,
+            price_raw: self.price,
+            exponent: self.exponent,
+            publish_time: self.publish_time,
+            ema_price: self.ema_price as f64 * scale,
+            ..Default::default()
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_oracle_prices(params: String, block: Block) -> Result<OraclePriceList, Error> {
+    let feed_mints = parse_feed_mints(&params)?;
+    let mut items = Vec::new();
+
+    for trx in block.transactions() {
+        for instruction in trx.walk_instructions() {
+            let program_id = instruction.program_id().to_string();
+            if program_id != PYTH_RECEIVER_PROGRAM_ID {
+                continue;
+            }
+
+            let message = match decode_price_update(instruction.data()) {
+                Ok(Some(message)) => message,
+                Ok(None) => continue,
+                Err(err) => {
+                    log::info!("Failed to decode Pyth price update: {}", err);
+                    continue;
+                }
+            };
+
+            let mut price = message.to_oracle_price();
+            price.mint = feed_mints.get(&price.feed_id).cloned().unwrap_or_default();
+            price.price_account = instruction
+                .accounts()
+                .get(PRICE_UPDATE_ACCOUNT_INDEX)
+                .map(|address| address.to_string())
+                .unwrap_or_default();
+            price.program_id = program_id;
+            price.transaction_id = trx.id();
+            price.slot = block.slot;
+            items.push(price);
+        }
+    }
+
+    Ok(OraclePriceList { items })
+}
+
+/// Store handler for the latest oracle price of every mapped mint
+///
+/// Key format: `mint:{address}`
+/// Value: OraclePrice with the newest publish time in the block
+#[substreams::handlers::store]
+pub fn store_oracle_prices(prices: OraclePriceList, store: StoreSetProto<OraclePrice>) {
+    let mut latest: BTreeMap<&str, &OraclePrice> = BTreeMap::new();
+    for price in prices.items.iter().filter(|price| !price.mint.is_empty()) {
+        let entry = latest.entry(&price.mint).or_insert(price);
+        if price.publish_time > entry.publish_time {
+            *entry = price;
+        }
+    }
+
+    for (ordinal, (mint, price)) in latest.into_iter().enumerate() {
+        store.set(ordinal as u64, oracle_price_key(mint), price);
+    }
+}
+
+/// Key of a mint's latest price in `store_oracle_prices`
+#[inline]
+pub fn oracle_price_key(mint: &str) -> String {
+    format!("mint:{}", mint)
+}
+
+/// Parse the `feed_id=mint` pairs of the module params, separated by `&`
+///
+/// Feed ids are hex, with or without a `0x` prefix, and are returned lowercase.
+pub fn parse_feed_mints(params: &str) -> Result<HashMap<String, String>, Error> {
+    let mut feed_mints = HashMap::new();
+
+    for pair in params.split('&').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (feed_id, mint) = pair
+            .split_once('=')
+            .ok_or_else(|| Error::msg(format!("invalid feed mapping {:?}, expected feed_id=mint", pair)))?;
+        let feed_id = feed_id.trim().trim_start_matches("0x").to_lowercase();
+        if feed_id.len() != 64 || hex::decode(&feed_id).is_err() {
+            return Err(Error::msg(format!("invalid Pyth feed id {:?}", feed_id)));
+        }
+
+        feed_mints.insert(feed_id, mint.trim().to_string());
+    }
+
+    Ok(feed_mints)
+}
+
+/// Decode the `PriceFeedMessage` of a Pyth Solana Receiver instruction
+///
+/// Returns `Ok(None)` for other instructions and for other message types.
+pub fn decode_price_update(data: &[u8]) -> Result<Option<PriceFeedMessage>, DecodeError> {
+    if data.len() < 8 {
+        return Err(DecodeError::TooShort(data.len()));
+    }
+    let (discriminator, args) = data.split_at(8);
+
+    let update = if discriminator == POST_UPDATE_DISCRIMINATOR {
+        deserialize::<PostUpdateParams>(args)?.merkle_price_update
+    } else if discriminator == POST_UPDATE_ATOMIC_DISCRIMINATOR {
+        deserialize::<PostUpdateAtomicParams>(args)?.merkle_price_update
+    } else {
+        return Ok(None);
+    };
+
+    decode_price_feed_message(&update.message)
+}
+
+/// Decode a Pythnet `PriceFeedMessage` (big-endian, unlike the Borsh envelope)
+fn decode_price_feed_message(message: &[u8]) -> Result<Option<PriceFeedMessage>, DecodeError> {
+    if message.first() != Some(&PRICE_FEED_MESSAGE) {
+        return Ok(None);
+    }
+    if message.len() < PRICE_FEED_MESSAGE_LEN {
+        return Err(DecodeError::InvalidArgs(format!(
+            "price feed message too short: {} bytes",
+            message.len()
+        )));
+    }
+
+    let field = |offset: usize| -> [u8; 8] { message[offset..offset + 8].try_into().unwrap() };
+    Ok(Some(PriceFeedMessage {
+        feed_id: message[1..33].try_into().unwrap(),
+        price: i64::from_be_bytes(field(33)),
+        conf: u64::from_be_bytes(field(41)),
+        exponent: i32::from_be_bytes(message[49..53].try_into().unwrap()),
+        publish_time: i64::from_be_bytes(field(53)),
+        ema_price: i64::from_be_bytes(field(69)),
+    }))
+}
+
+/// Execution price of a trade against oracle prices, in basis points
+///
+/// Compares the USD value received with the USD value paid, both at oracle prices:
+/// positive when the trade beat the oracle, negative when it paid slippage, fees or
+/// price impact. `None` unless both amounts are normalized.
+pub fn execution_vs_oracle_bps(trade: &TradingData, input_price: f64, output_price: f64) -> Option<f64> {
+    let amount_in = trade.amount_in_normalized.parse::<f64>().ok()?;
+    let amount_out = trade.amount_out_normalized.parse::<f64>().ok()?;
+    let paid_usd = amount_in * input_price;
+    if paid_usd <= 0.0 || output_price <= 0.0 {
+        return None;
+    }
+
+    Some((amount_out * output_price / paid_usd - 1.0) * 10_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL_USD_FEED: &str = "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
+
+    fn create_test_message(price: i64, exponent: i32) -> Vec<u8> {
+        let mut message = vec![PRICE_FEED_MESSAGE];
+        message.extend_from_slice(&hex::decode(SOL_USD_FEED).unwrap());
+        message.extend_from_slice(&price.to_be_bytes());
+        message.extend_from_slice(&7_000_000u64.to_be_bytes());
+        message.extend_from_slice(&exponent.to_be_bytes());
+        message.extend_from_slice(&1_700_000_000i64.to_be_bytes());
+        message.extend_from_slice(&1_699_999_999i64.to_be_bytes());
+        message.extend_from_slice(&(price - 100_000_000).to_be_bytes());
+        message.extend_from_slice(&8_000_000u64.to_be_bytes());
+        message
+    }
+
+    /// Borsh `MerklePriceUpdate` followed by the treasury id
+    fn create_test_update(discriminator: [u8; 8], vaa: Option<&[u8]>, message: &[u8]) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        if let Some(vaa) = vaa {
+            data.extend_from_slice(&(vaa.len() as u32).to_le_bytes());
+            data.extend_from_slice(vaa);
+        }
+        data.extend_from_slice(&(message.len() as u32).to_le_bytes());
+        data.extend_from_slice(message);
+        // Two proof nodes
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[9; 40]);
+        data.push(0);
+        data
+    }
+
+    #[test]
+    fn test_decode_post_update() {
+        let message = create_test_message(15_012_345_678, -8);
+        let data = create_test_update(POST_UPDATE_DISCRIMINATOR, None, &message);
+
+        let decoded = decode_price_update(&data).unwrap().unwrap();
+
+        assert_eq!(hex::encode(decoded.feed_id), SOL_USD_FEED);
+        assert_eq!(decoded.price, 15_012_345_678);
+        assert_eq!(decoded.conf, 7_000_000);
+        assert_eq!(decoded.exponent, -8);
+        assert_eq!(decoded.publish_time, 1_700_000_000);
+        assert_eq!(decoded.ema_price, 14_912_345_678);
+
+        let price = decoded.to_oracle_price();
+        assert_eq!(price.feed_id, SOL_USD_FEED);
+        assert!((price.price - 150.12345678).abs() < 1e-9);
+        assert!((price.confidence - 0.07).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_decode_post_update_atomic() {
+        let message = create_test_message(100_000_000, -8);
+        let data = create_test_update(POST_UPDATE_ATOMIC_DISCRIMINATOR, Some(&[1; 100]), &message);
+
+        let decoded = decode_price_update(&data).unwrap().unwrap();
+        assert_eq!(decoded.price, 100_000_000);
+    }
+
+    #[test]
+    fn test_decode_ignores_other_instructions_and_messages() {
+        // TWAP message (type 1)
+        let mut message = create_test_message(100, -2);
+        message[0] = 1;
+        let data = create_test_update(POST_UPDATE_DISCRIMINATOR, None, &message);
+        assert_eq!(decode_price_update(&data).unwrap(), None);
+
+        assert_eq!(decode_price_update(&[0; 12]).unwrap(), None);
+        assert!(decode_price_update(&[0; 4]).is_err());
+
+        let truncated = create_test_update(POST_UPDATE_DISCRIMINATOR, None, &create_test_message(1, 0)[..60]);
+        assert!(decode_price_update(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_parse_feed_mints() {
+        let params = format!("0x{}=So11111111111111111111111111111111111111112", SOL_USD_FEED.to_uppercase());
+        let feed_mints = parse_feed_mints(&params).unwrap();
+
+        assert_eq!(feed_mints[SOL_USD_FEED], "So11111111111111111111111111111111111111112");
+        assert!(parse_feed_mints("").unwrap().is_empty());
+        assert!(parse_feed_mints("abc=mint").is_err());
+        assert!(parse_feed_mints(SOL_USD_FEED).is_err());
+    }
+
+    #[test]
+    fn test_execution_vs_oracle_bps() {
+        // 150 USDC for 0.99 SOL with SOL at $150: 1% worse than the oracle
+        let trade = TradingData {
+            amount_in_normalized: "150".to_string(),
+            amount_out_normalized: "0.99".to_string(),
+            ..Default::default()
+        };
+
+        let bps = execution_vs_oracle_bps(&trade, 1.0, 150.0).unwrap();
+        assert!((bps + 100.0).abs() < 1e-6);
+
+        assert_eq!(execution_vs_oracle_bps(&TradingData::default(), 1.0, 150.0), None);
+        assert_eq!(execution_vs_oracle_bps(&trade, 0.0, 150.0), None);
+    }
+}
@@ -0,0 +1,160 @@
+//! Parse diagnostics
+//!
+//! Reports every Jupiter program instruction that no decoder recognised, with its
+//! discriminator, data length and account count, and counts per block how many
+//! instructions of each program discriminator were decoded. Used to find the
+//! instruction types the decoders are missing.
+//!
+//! An instruction is recognised when its program's decoder accepts it: v6 routes
+//! and event self-CPIs, v4 routes, v2/v3 AMM swaps, and limit order and DCA
+//! instructions. A known discriminator with malformed arguments is unrecognised.
+
+use std::collections::BTreeMap;
+
+use crate::constants::get_jupiter_version;
+use crate::jupiter_dca::decode_dca;
+use crate::jupiter_legacy::{decode_amm_swap, decode_v4_route};
+use crate::jupiter_limit_order::decode_limit_order;
+use crate::jupiter_v6::{decode_route, is_event_cpi};
+use crate::pb::sf::jupiter::v1::{DiscriminatorCoverage, ParseDiagnostics, UnparsedInstruction};
+use crate::transaction_view::{walk_instructions, TransactionAccounts};
+use substreams::errors::Error;
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
+
+const DISCRIMINATOR_LEN: usize = 8;
+
+#[substreams::handlers::map]
+pub fn map_parse_diagnostics(block: Block) -> Result<ParseDiagnostics, Error> {
+    let mut unparsed = Vec::new();
+    let mut coverage: BTreeMap<(String, String), DiscriminatorCoverage> = BTreeMap::new();
+
+    for trx in block.transactions.iter().filter(|trx| trx.meta.is_some()) {
+        let account_keys = TransactionAccounts::new(trx);
+        let success = trx.is_successful();
+
+        for instruction in walk_instructions(trx) {
+            let Some(program_id) = account_keys.get(instruction.program_id_index) else {
+                continue;
+            };
+            let Some(version) = get_jupiter_version(program_id) else {
+                continue;
+            };
+
+            let discriminator = discriminator_hex(instruction.data);
+            let decoded = decoded_instruction(version, instruction.data);
+            count_coverage(&mut coverage, program_id, &discriminator, decoded);
+
+            if decoded.is_none() {
+                unparsed.push(UnparsedInstruction {
+                    program_id: program_id.to_string(),
+                    discriminator,
+                    data_length: instruction.data.len() as u32,
+                    account_count: instruction.accounts.len() as u32,
+                    transaction_id: trx.id(),
+                    instruction_index: instruction.instruction_index,
+                    inner_index: instruction.inner_index,
+                    success,
+                });
+            }
+        }
+    }
+
+    Ok(ParseDiagnostics {
+        unparsed,
+        coverage: coverage.into_values().collect(),
+        slot: block.slot,
+    })
+}
+
+/// Name of the instruction the program's decoder recognised, `None` when it didn't
+fn decoded_instruction(version: &str, data: &[u8]) -> Option<&'static str> {
+    match version {
+        // SwapEvent, FeeEvent, ... all share the event self-CPI tag
+        "v6" if is_event_cpi(data) => Some("event_cpi"),
+        "v6" => decode_route(data).ok().map(|route| route.kind.as_str()),
+        "v4" => decode_v4_route(data).ok().map(|_| "route"),
+        "v3" | "v2" => decode_amm_swap(data).ok().map(|swap| swap.instruction),
+        "limit_orders" => decode_limit_order(data).ok().map(|instruction| instruction.as_str()),
+        "dca" => decode_dca(data).ok().map(|instruction| instruction.as_str()),
+        _ => None,
+    }
+}
+
+/// Count an instruction towards its program discriminator's coverage
+fn count_coverage(
+    coverage: &mut BTreeMap<(String, String), DiscriminatorCoverage>,
+    program_id: &str,
+    discriminator: &str,
+    decoded: Option<&str>,
+) {
+    let entry = coverage
+        .entry((program_id.to_string(), discriminator.to_string()))
+        .or_insert_with(|| DiscriminatorCoverage {
+            program_id: program_id.to_string(),
+            discriminator: discriminator.to_string(),
+            ..Default::default()
+        });
+
+    entry.instruction_count += 1;
+    if let Some(name) = decoded {
+        entry.decoded_count += 1;
+        if entry.instruction.is_empty() {
+            entry.instruction = name.to_string();
+        }
+    }
+}
+
+/// Hex of the instruction's discriminator, or of all its data when shorter
+#[inline]
+fn discriminator_hex(data: &[u8]) -> String {
+    hex::encode(&data[..data.len().min(DISCRIMINATOR_LEN)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jupiter_dca::CLOSE_DCA_DISCRIMINATOR;
+    use crate::jupiter_limit_order::CANCEL_ORDER_DISCRIMINATOR;
+    use crate::jupiter_v6::{EVENT_IX_TAG, ROUTE_DISCRIMINATOR, SWAP_EVENT_DISCRIMINATOR};
+
+    #[test]
+    fn test_decoded_instruction() {
+        let mut event = EVENT_IX_TAG.to_vec();
+        event.extend_from_slice(&SWAP_EVENT_DISCRIMINATOR);
+        assert_eq!(decoded_instruction("v6", &event), Some("event_cpi"));
+        assert_eq!(decoded_instruction("limit_orders", &CANCEL_ORDER_DISCRIMINATOR), Some("cancel_order"));
+        assert_eq!(decoded_instruction("dca", &CLOSE_DCA_DISCRIMINATOR), Some("close_dca"));
+
+        // Known route discriminator with truncated args, and an unknown discriminator
+        let mut route = ROUTE_DISCRIMINATOR.to_vec();
+        route.push(1);
+        assert_eq!(decoded_instruction("v6", &route), None);
+        assert_eq!(decoded_instruction("v6", &[0xFF; 24]), None);
+        assert_eq!(decoded_instruction("v4", &[]), None);
+    }
+
+    #[test]
+    fn test_count_coverage() {
+        let mut coverage = BTreeMap::new();
+
+        count_coverage(&mut coverage, "JUP6", "e517cb977ae3ad2a", Some("route"));
+        count_coverage(&mut coverage, "JUP6", "e517cb977ae3ad2a", None);
+        count_coverage(&mut coverage, "JUP6", "ffffffffffffffff", None);
+
+        let coverage: Vec<_> = coverage.into_values().collect();
+        assert_eq!(coverage.len(), 2);
+        assert_eq!(coverage[0].instruction, "route");
+        assert_eq!(coverage[0].instruction_count, 2);
+        assert_eq!(coverage[0].decoded_count, 1);
+        assert!(coverage[1].instruction.is_empty());
+        assert_eq!(coverage[1].decoded_count, 0);
+    }
+
+    #[test]
+    fn test_discriminator_hex() {
+        assert_eq!(discriminator_hex(&ROUTE_DISCRIMINATOR), "e517cb977ae3ad2a");
+        assert_eq!(discriminator_hex(&[1, 2, 3, 4, 5, 6, 7, 8, 9]), "0102030405060708");
+        assert_eq!(discriminator_hex(&[0xAB]), "ab");
+        assert_eq!(discriminator_hex(&[]), "");
+    }
+}
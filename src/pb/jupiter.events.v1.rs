@@ -92,12 +92,16 @@ pub struct SwapEvent {
     pub status: i32,
     #[prost(string, tag="15")]
     pub error_message: ::prost::alloc::string::String,
+    /// {signature}:{instruction_index}\[:{inner_index}\], as in TradingData
+    #[prost(string, tag="16")]
+    pub swap_id: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SwapRoute {
     #[prost(string, tag="1")]
     pub dex: ::prost::alloc::string::String,
+    /// AMM program ID that filled the hop
     #[prost(string, tag="2")]
     pub program_id: ::prost::alloc::string::String,
     #[prost(string, tag="3")]
@@ -112,6 +116,12 @@ pub struct SwapRoute {
     pub price_impact_pips: u64,
     #[prost(string, repeated, tag="8")]
     pub accounts: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Position within the route, in execution order
+    #[prost(uint32, tag="9")]
+    pub hop_index: u32,
+    /// Share of the route input sent through this hop
+    #[prost(uint32, tag="10")]
+    pub percent: u32,
 }
 /// Limit Order events
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -182,10 +192,12 @@ pub struct DcaEvent {
     pub input_mint: ::prost::alloc::string::String,
     #[prost(string, tag="5")]
     pub output_mint: ::prost::alloc::string::String,
+    /// Plan parameters; fills read them from store_dca_positions (0 when unknown)
     #[prost(uint64, tag="6")]
     pub amount_per_interval: u64,
     #[prost(uint64, tag="7")]
     pub interval_seconds: u64,
+    /// First cycle for opens, next cycle for active fills, 0 when unknown
     #[prost(uint64, tag="8")]
     pub next_execution: u64,
     #[prost(enumeration="DcaStatus", tag="9")]
@@ -196,6 +208,15 @@ pub struct DcaEvent {
     pub timestamp: u64,
     #[prost(string, tag="12")]
     pub error_message: ::prost::alloc::string::String,
+    /// DCA program instruction (open_dca, fulfill_flash_fill, ...)
+    #[prost(string, tag="13")]
+    pub instruction: ::prost::alloc::string::String,
+    /// Input deposited, filled or withdrawn by the instruction
+    #[prost(uint64, tag="14")]
+    pub in_amount: u64,
+    /// Output received by a fill or withdrawn by the user
+    #[prost(uint64, tag="15")]
+    pub out_amount: u64,
 }
 /// Aggregation events (routing decisions, arbitrage opportunities)
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -56,6 +56,8 @@ pub struct TradingData {
     #[prost(uint64, tag="6")]
     pub block_time: u64,
     /// Parsed swap fields
+    /// amount_in/amount_out are the executed (settled) amounts from token balance
+    /// deltas, falling back to the quoted amounts when no balance change is found
     #[prost(uint64, tag="7")]
     pub amount_in: u64,
     #[prost(uint64, tag="8")]
@@ -66,6 +68,89 @@ pub struct TradingData {
     pub output_mint: ::prost::alloc::string::String,
     #[prost(string, tag="11")]
     pub user_wallet: ::prost::alloc::string::String,
+    /// Quoted amounts from instruction arguments (ExactOut: amount_in is the maximum)
+    #[prost(uint64, tag="12")]
+    pub quoted_amount_in: u64,
+    #[prost(uint64, tag="13")]
+    pub quoted_amount_out: u64,
+    /// Per-hop amounts from v6 SwapEvent self-CPIs, in execution order
+    #[prost(message, repeated, tag="14")]
+    pub hops: ::prost::alloc::vec::Vec<SwapHop>,
+    /// Transaction status; failed swaps keep their quoted amounts but are excluded from volume
+    #[prost(bool, tag="15")]
+    pub success: bool,
+    /// Program error name (e.g. SlippageToleranceExceeded) or runtime error
+    #[prost(string, tag="16")]
+    pub error_code: ::prost::alloc::string::String,
+    /// Decoded from the transaction error and logs
+    #[prost(string, tag="17")]
+    pub error_message: ::prost::alloc::string::String,
+    /// Token program of the user's token accounts (Token-2022 if either side uses it)
+    #[prost(string, tag="18")]
+    pub token_program: ::prost::alloc::string::String,
+    /// Mint decimals from the transaction's token balances or store_mint_decimals
+    #[prost(uint32, optional, tag="19")]
+    pub input_decimals: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag="20")]
+    pub output_decimals: ::core::option::Option<u32>,
+    /// amount_in/amount_out in whole tokens (e.g. "1.5"), empty when decimals are unknown
+    #[prost(string, tag="21")]
+    pub amount_in_normalized: ::prost::alloc::string::String,
+    #[prost(string, tag="22")]
+    pub amount_out_normalized: ::prost::alloc::string::String,
+    /// Set by map_validated_trading_data; outliers are kept out of candles and prices
+    #[prost(enumeration="OutlierReason", tag="23")]
+    pub outlier_reason: i32,
+    /// Instruction path within the transaction: top-level index, then position among
+    /// its inner instructions (unset for top-level instructions)
+    #[prost(uint32, tag="24")]
+    pub instruction_index: u32,
+    #[prost(uint32, optional, tag="25")]
+    pub inner_index: ::core::option::Option<u32>,
+    /// Unique, stable ID: {signature}:{instruction_index}\[:{inner_index}\]
+    #[prost(string, tag="26")]
+    pub swap_id: ::prost::alloc::string::String,
+    /// Invocation context: CPIs come from integrators (bots, other aggregators)
+    #[prost(bool, tag="27")]
+    pub is_cpi: bool,
+    /// Program that invoked this instruction, empty when top-level
+    #[prost(string, tag="28")]
+    pub caller_program: ::prost::alloc::string::String,
+    /// 1 for top-level, 0 when unknown (older blocks)
+    #[prost(uint32, tag="29")]
+    pub stack_height: u32,
+    /// Where amount_in/amount_out came from, most trusted first
+    #[prost(enumeration="ParseMethod", tag="30")]
+    pub parse_method: i32,
+    /// Token-2022 fee withheld from the output on its way to the user (named by a
+    /// TransferCheckedWithFee), already excluded from amount_out
+    #[prost(uint64, tag="31")]
+    pub output_transfer_fee: u64,
+}
+/// A single AMM hop of a Jupiter route, decoded from a v6 SwapEvent
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SwapHop {
+    /// AMM program ID that filled the hop
+    #[prost(string, tag="1")]
+    pub amm: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub input_mint: ::prost::alloc::string::String,
+    #[prost(uint64, tag="3")]
+    pub input_amount: u64,
+    #[prost(string, tag="4")]
+    pub output_mint: ::prost::alloc::string::String,
+    #[prost(uint64, tag="5")]
+    pub output_amount: u64,
+    /// Position within the route, in execution order
+    #[prost(uint32, tag="6")]
+    pub hop_index: u32,
+    /// Share of the route input sent through this hop (route plan step)
+    #[prost(uint32, tag="7")]
+    pub percent: u32,
+    /// DEX label from the AMM registry (e.g. "orca_whirlpool")
+    #[prost(string, tag="9")]
+    pub dex: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -80,6 +165,90 @@ pub struct TokenPrice {
     pub price_change_24h: f64,
     #[prost(uint64, tag="5")]
     pub slot: u64,
+    /// USD notional of the trades the price is derived from (volume weight)
+    #[prost(double, tag="6")]
+    pub volume_usd: f64,
+    #[prost(uint32, tag="7")]
+    pub trade_count: u32,
+    #[prost(enumeration="PriceSource", tag="8")]
+    pub source: i32,
+    /// Stablecoin or hub token carrying most of the volume
+    #[prost(string, tag="9")]
+    pub quote_mint: ::prost::alloc::string::String,
+    /// Bridged through a hub price carried from a block older than the staleness window
+    #[prost(bool, tag="10")]
+    pub stale: bool,
+    /// volume_usd below the minimum liquidity
+    #[prost(bool, tag="11")]
+    pub thin_liquidity: bool,
+    /// Block time of the trades the price is derived from
+    #[prost(uint64, tag="12")]
+    pub timestamp: u64,
+}
+/// Reference price from a Pyth price update (Pythnet PriceFeedMessage)
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OraclePrice {
+    /// Hex Pyth price feed id
+    #[prost(string, tag="1")]
+    pub feed_id: ::prost::alloc::string::String,
+    /// Mint mapped to the feed in the module params, empty if unmapped
+    #[prost(string, tag="2")]
+    pub mint: ::prost::alloc::string::String,
+    /// price_raw * 10^exponent
+    #[prost(double, tag="3")]
+    pub price: f64,
+    /// Confidence interval, same scale as price
+    #[prost(double, tag="4")]
+    pub confidence: f64,
+    #[prost(int64, tag="5")]
+    pub price_raw: i64,
+    #[prost(int32, tag="6")]
+    pub exponent: i32,
+    #[prost(int64, tag="7")]
+    pub publish_time: i64,
+    #[prost(double, tag="8")]
+    pub ema_price: f64,
+    /// Price update account written
+    #[prost(string, tag="9")]
+    pub price_account: ::prost::alloc::string::String,
+    #[prost(string, tag="10")]
+    pub program_id: ::prost::alloc::string::String,
+    #[prost(string, tag="11")]
+    pub transaction_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="12")]
+    pub slot: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OraclePriceList {
+    #[prost(message, repeated, tag="1")]
+    pub items: ::prost::alloc::vec::Vec<OraclePrice>,
+}
+/// A mint's USD price at a point in time, kept in store_token_last_price
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenPriceState {
+    #[prost(double, tag="1")]
+    pub price_usd: f64,
+    #[prost(uint64, tag="2")]
+    pub slot: u64,
+    #[prost(uint64, tag="3")]
+    pub timestamp: u64,
+    /// Never used as a hub in later blocks
+    #[prost(bool, tag="4")]
+    pub thin_liquidity: bool,
+}
+/// A pair's raw price sampled from one block, kept in store_pair_price_reference
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PairPriceSample {
+    /// Median amount_out/amount_in of the block's accepted trades
+    #[prost(double, tag="1")]
+    pub price: f64,
+    /// Block time, identifies the sample period the slot holds
+    #[prost(uint64, tag="2")]
+    pub timestamp: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -96,10 +265,14 @@ pub struct EnrichedAccount {
 pub struct AccountOwnerRecord {
     #[prost(bytes="vec", tag="1")]
     pub account: ::prost::alloc::vec::Vec<u8>,
+    /// Empty for owner changes and closes
     #[prost(bytes="vec", tag="2")]
     pub mint: ::prost::alloc::vec::Vec<u8>,
+    /// New owner for owner changes, empty for closes
     #[prost(bytes="vec", tag="3")]
     pub owner: ::prost::alloc::vec::Vec<u8>,
+    #[prost(enumeration="AccountOwnerChange", tag="4")]
+    pub change: i32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -107,6 +280,81 @@ pub struct AccountOwnerRecords {
     #[prost(message, repeated, tag="1")]
     pub records: ::prost::alloc::vec::Vec<AccountOwnerRecord>,
 }
+/// Decimals of a mint, from InitializeMint or a token balance
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MintDecimals {
+    #[prost(string, tag="1")]
+    pub mint: ::prost::alloc::string::String,
+    #[prost(uint32, tag="2")]
+    pub decimals: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MintDecimalsList {
+    #[prost(message, repeated, tag="1")]
+    pub items: ::prost::alloc::vec::Vec<MintDecimals>,
+}
+/// Token metadata set by a Metaplex Token Metadata or Token-2022 metadata instruction
+/// Unset fields were not changed by the instruction
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenMetadata {
+    /// Empty when the instruction names only the metadata account
+    #[prost(string, tag="1")]
+    pub mint: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub metadata_account: ::prost::alloc::string::String,
+    #[prost(string, optional, tag="3")]
+    pub name: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag="4")]
+    pub symbol: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag="5")]
+    pub uri: ::core::option::Option<::prost::alloc::string::String>,
+    /// Empty when the authority was removed
+    #[prost(string, optional, tag="6")]
+    pub update_authority: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag="7")]
+    pub program_id: ::prost::alloc::string::String,
+    /// e.g. create_metadata_account_v3, update_field
+    #[prost(string, tag="8")]
+    pub instruction: ::prost::alloc::string::String,
+    #[prost(string, tag="9")]
+    pub transaction_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="10")]
+    pub slot: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenMetadataList {
+    #[prost(message, repeated, tag="1")]
+    pub items: ::prost::alloc::vec::Vec<TokenMetadata>,
+}
+/// Plan parameters of a DCA position opened or closed in a block, kept in
+/// store_dca_positions while the position is open
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DcaPosition {
+    #[prost(string, tag="1")]
+    pub dca_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="2")]
+    pub in_amount_per_cycle: u64,
+    /// Seconds between cycles
+    #[prost(uint64, tag="3")]
+    pub cycle_frequency: u64,
+    /// Set by close_dca, which removes the position from the store
+    #[prost(bool, tag="4")]
+    pub closed: bool,
+    /// Number of the instruction among the block's decoded DCA instructions
+    #[prost(uint64, tag="5")]
+    pub ordinal: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DcaPositionList {
+    #[prost(message, repeated, tag="1")]
+    pub items: ::prost::alloc::vec::Vec<DcaPosition>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TradingDataList {
@@ -147,6 +395,14 @@ pub struct JupiterInstruction {
     pub input_mint: ::prost::alloc::string::String,
     #[prost(string, tag="10")]
     pub output_mint: ::prost::alloc::string::String,
+    /// Instruction path, matching TradingData
+    #[prost(uint32, tag="11")]
+    pub instruction_index: u32,
+    #[prost(uint32, optional, tag="12")]
+    pub inner_index: ::core::option::Option<u32>,
+    /// Same ID as TradingData.swap_id for the instruction
+    #[prost(string, tag="13")]
+    pub swap_id: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -184,4 +440,193 @@ pub struct JupiterAnalytics {
     #[prost(uint64, tag="6")]
     pub total_swaps: u64,
 }
+/// A Jupiter program instruction that no decoder recognised
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnparsedInstruction {
+    #[prost(string, tag="1")]
+    pub program_id: ::prost::alloc::string::String,
+    /// First 8 data bytes in hex (fewer when the data is shorter)
+    #[prost(string, tag="2")]
+    pub discriminator: ::prost::alloc::string::String,
+    #[prost(uint32, tag="3")]
+    pub data_length: u32,
+    #[prost(uint32, tag="4")]
+    pub account_count: u32,
+    #[prost(string, tag="5")]
+    pub transaction_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag="6")]
+    pub instruction_index: u32,
+    #[prost(uint32, optional, tag="7")]
+    pub inner_index: ::core::option::Option<u32>,
+    /// Whether the transaction succeeded
+    #[prost(bool, tag="8")]
+    pub success: bool,
+}
+/// Decoder coverage of one program's discriminator within a block
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscriminatorCoverage {
+    #[prost(string, tag="1")]
+    pub program_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub discriminator: ::prost::alloc::string::String,
+    /// Decoded instruction name, empty when never recognised
+    #[prost(string, tag="3")]
+    pub instruction: ::prost::alloc::string::String,
+    #[prost(uint32, tag="4")]
+    pub instruction_count: u32,
+    #[prost(uint32, tag="5")]
+    pub decoded_count: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParseDiagnostics {
+    #[prost(message, repeated, tag="1")]
+    pub unparsed: ::prost::alloc::vec::Vec<UnparsedInstruction>,
+    #[prost(message, repeated, tag="2")]
+    pub coverage: ::prost::alloc::vec::Vec<DiscriminatorCoverage>,
+    #[prost(uint64, tag="3")]
+    pub slot: u64,
+}
+/// How a token account's ownership changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum AccountOwnerChange {
+    /// InitializeAccount/2/3
+    Initialized = 0,
+    /// SetAuthority(AccountOwner)
+    OwnerSet = 1,
+    /// CloseAccount
+    Closed = 2,
+}
+impl AccountOwnerChange {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            AccountOwnerChange::Initialized => "ACCOUNT_OWNER_CHANGE_INITIALIZED",
+            AccountOwnerChange::OwnerSet => "ACCOUNT_OWNER_CHANGE_OWNER_SET",
+            AccountOwnerChange::Closed => "ACCOUNT_OWNER_CHANGE_CLOSED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ACCOUNT_OWNER_CHANGE_INITIALIZED" => Some(Self::Initialized),
+            "ACCOUNT_OWNER_CHANGE_OWNER_SET" => Some(Self::OwnerSet),
+            "ACCOUNT_OWNER_CHANGE_CLOSED" => Some(Self::Closed),
+            _ => None,
+        }
+    }
+}
+/// How a token's USD price was derived
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum PriceSource {
+    /// Swaps against USDC/USDT
+    Direct = 0,
+    /// Swaps against a priced hub token (e.g. SOL)
+    Bridged = 1,
+    /// USDC/USDT themselves, fixed at 1.0
+    Anchor = 2,
+}
+impl PriceSource {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            PriceSource::Direct => "PRICE_SOURCE_DIRECT",
+            PriceSource::Bridged => "PRICE_SOURCE_BRIDGED",
+            PriceSource::Anchor => "PRICE_SOURCE_ANCHOR",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PRICE_SOURCE_DIRECT" => Some(Self::Direct),
+            "PRICE_SOURCE_BRIDGED" => Some(Self::Bridged),
+            "PRICE_SOURCE_ANCHOR" => Some(Self::Anchor),
+            _ => None,
+        }
+    }
+}
+/// Source of a trade's recorded amounts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ParseMethod {
+    /// No amounts parsed (e.g. limit order and DCA instructions)
+    Unspecified = 0,
+    /// Both sides settled from the user's token balance deltas
+    BalanceDelta = 1,
+    /// Sides without a balance delta summed from the route's SwapEvents
+    SwapEvent = 2,
+    /// Quoted amounts from exactly decoded instruction arguments
+    ExactArgs = 3,
+    /// Amounts guessed from a byte scan of an unknown instruction
+    Heuristic = 4,
+}
+impl ParseMethod {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ParseMethod::Unspecified => "PARSE_METHOD_UNSPECIFIED",
+            ParseMethod::BalanceDelta => "PARSE_METHOD_BALANCE_DELTA",
+            ParseMethod::SwapEvent => "PARSE_METHOD_SWAP_EVENT",
+            ParseMethod::ExactArgs => "PARSE_METHOD_EXACT_ARGS",
+            ParseMethod::Heuristic => "PARSE_METHOD_HEURISTIC",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PARSE_METHOD_UNSPECIFIED" => Some(Self::Unspecified),
+            "PARSE_METHOD_BALANCE_DELTA" => Some(Self::BalanceDelta),
+            "PARSE_METHOD_SWAP_EVENT" => Some(Self::SwapEvent),
+            "PARSE_METHOD_EXACT_ARGS" => Some(Self::ExactArgs),
+            "PARSE_METHOD_HEURISTIC" => Some(Self::Heuristic),
+            _ => None,
+        }
+    }
+}
+/// Why a trade's price was rejected as an outlier
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OutlierReason {
+    /// Accepted (or not price checked)
+    None = 0,
+    /// An amount below the minimum base units
+    Dust = 1,
+    /// Too far from the pair's rolling median price
+    PriceDeviation = 2,
+}
+impl OutlierReason {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            OutlierReason::None => "OUTLIER_REASON_NONE",
+            OutlierReason::Dust => "OUTLIER_REASON_DUST",
+            OutlierReason::PriceDeviation => "OUTLIER_REASON_PRICE_DEVIATION",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "OUTLIER_REASON_NONE" => Some(Self::None),
+            "OUTLIER_REASON_DUST" => Some(Self::Dust),
+            "OUTLIER_REASON_PRICE_DEVIATION" => Some(Self::PriceDeviation),
+            _ => None,
+        }
+    }
+}
 // @@protoc_insertion_point(module)
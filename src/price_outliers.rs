@@ -0,0 +1,407 @@
+//! Price outlier rejection
+//!
+//! Tags trades whose price can't be trusted before they reach candles and the
+//! token price stores, where a single bad price is permanent (`max`/`min`
+//! upserts can't be undone). Tagged trades keep flowing to `jupiter_swaps`.
+//!
+//! A successful trade is an outlier when:
+//! - either amount is below `min_base_units` (dust, where rounding dominates the price)
+//! - its price (raw `amount_out / amount_in`) deviates more than `max_deviation`
+//!   from the pair's reference price, in either direction
+//!
+//! The reference is the median of the pair's price samples over the previous
+//! `window_hours` hours, from `store_pair_price_reference`. Pairs without history
+//! fall back to the median of the block's trades when there are enough of them.
+//!
+//! The store keeps one sample per pair and `SAMPLE_SECONDS` period: the median of
+//! the latest block's non-dust trades in that period, without the trades that
+//! deviate from it. Samples sit in a ring of slots covering the window, so a pair
+//! never holds more than a window of them; readers check each sample's timestamp.
+//!
+//! Configured with the module params, e.g. `max_deviation=0.5&min_base_units=1000&window_hours=6`.
+//! A zero `max_deviation` or `min_base_units` disables that check.
+
+// The handler macro reads the `params` string from a raw pointer in the generated exports
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use std::collections::BTreeMap;
+
+use crate::pb::sf::jupiter::v1::{OutlierReason, PairPriceSample, TradingData, TradingDataList};
+use substreams::errors::Error;
+use substreams::store::{StoreGet, StoreGetProto, StoreNew, StoreSet, StoreSetProto};
+
+const SECONDS_PER_HOUR: u64 = 3_600;
+/// Length of the period each stored price sample stands for
+const SAMPLE_SECONDS: u64 = 300;
+/// Trades of a pair needed in a block before their median is used as a reference
+const MIN_BLOCK_TRADES: usize = 3;
+
+/// Outlier rejection settings, parsed from the module params
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlierConfig {
+    /// Largest accepted price move from the reference, as a fraction (0.5 = 50%)
+    pub max_deviation: f64,
+    /// Smallest accepted raw amount on either side of a trade
+    pub min_base_units: u64,
+    /// Hours of pair prices in the rolling median
+    pub window_hours: u64,
+}
+
+impl Default for OutlierConfig {
+    fn default() -> Self {
+        Self {
+            max_deviation: 0.5,
+            min_base_units: 1_000,
+            window_hours: 6,
+        }
+    }
+}
+
+impl OutlierConfig {
+    /// Parse `key=value` pairs separated by `&`; missing keys keep their default
+    pub fn parse(params: &str) -> Result<Self, Error> {
+        let mut config = Self::default();
+
+        for pair in params.split('&').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| Error::msg(format!("invalid outlier param {:?}, expected key=value", pair)))?;
+            let invalid = || Error::msg(format!("invalid value {:?} for outlier param {}", value, key));
+
+            match key.trim() {
+                "max_deviation" => config.max_deviation = value.trim().parse().map_err(|_| invalid())?,
+                "min_base_units" => config.min_base_units = value.trim().parse().map_err(|_| invalid())?,
+                "window_hours" => config.window_hours = value.trim().parse().map_err(|_| invalid())?,
+                other => return Err(Error::msg(format!("unknown outlier param {:?}", other))),
+            }
+        }
+
+        if !config.max_deviation.is_finite() || config.max_deviation < 0.0 {
+            return Err(Error::msg("outlier param max_deviation must be a non-negative number"));
+        }
+        if config.window_hours == 0 {
+            return Err(Error::msg("outlier param window_hours must be at least 1"));
+        }
+
+        Ok(config)
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_validated_trading_data(
+    params: String,
+    trading_data: TradingDataList,
+    samples: StoreGetProto<PairPriceSample>,
+) -> Result<TradingDataList, Error> {
+    let config = OutlierConfig::parse(&params)?;
+    let mut trading_data = trading_data;
+
+    let block_prices = pair_prices(&trading_data.items, &config);
+    let mut references: BTreeMap<String, Option<f64>> = BTreeMap::new();
+    for trade in trading_data.items.iter_mut() {
+        let Some(price) = trade_price(trade) else {
+            continue;
+        };
+        let block_time = trade.block_time;
+
+        let reference = *references
+            .entry(pair_key(&trade.input_mint, &trade.output_mint))
+            .or_insert_with_key(|pair| {
+                let mut history = window_prices(pair, block_time, &config, |key| samples.get_last(key));
+                median(&mut history).or_else(|| {
+                    block_prices
+                        .get(pair)
+                        .filter(|prices| prices.len() >= MIN_BLOCK_TRADES)
+                        .and_then(|prices| median(&mut prices.clone()))
+                })
+            });
+
+        trade.outlier_reason = classify(trade, price, reference, &config) as i32;
+    }
+
+    Ok(trading_data)
+}
+
+/// Store handler for each pair's price samples
+///
+/// Key format: `pair:{input_mint}:{output_mint}:sample:{slot}`
+/// Value: PairPriceSample of the latest block in the slot's sample period
+#[substreams::handlers::store]
+pub fn store_pair_price_reference(
+    params: String,
+    trading_data: TradingDataList,
+    store: StoreSetProto<PairPriceSample>,
+) {
+    // Stores can't return errors: fail the module instead of sampling with other settings
+    let config = OutlierConfig::parse(&params)
+        .unwrap_or_else(|err| panic!("invalid store_pair_price_reference params: {}", err));
+    let Some(block_time) = trading_data.items.first().map(|trade| trade.block_time) else {
+        return;
+    };
+
+    for (ordinal, (pair, prices)) in pair_prices(&trading_data.items, &config).into_iter().enumerate() {
+        if let Some(price) = sample_price(prices, &config) {
+            let sample = PairPriceSample {
+                price,
+                timestamp: block_time,
+            };
+            store.set(ordinal as u64, sample_key(&pair, block_time, &config), &sample);
+        }
+    }
+}
+
+/// Median of a pair's block prices, recomputed without the prices that deviate
+/// from it once there are enough trades to tell them apart
+fn sample_price(mut prices: Vec<f64>, config: &OutlierConfig) -> Option<f64> {
+    let block_median = median(&mut prices)?;
+    if prices.len() < MIN_BLOCK_TRADES {
+        return Some(block_median);
+    }
+
+    let mut accepted: Vec<f64> = prices
+        .into_iter()
+        .filter(|price| !deviates(*price, block_median, config.max_deviation))
+        .collect();
+    median(&mut accepted)
+}
+
+/// Prices sampled for a pair in the `window_hours` before `block_time`
+fn window_prices(
+    pair: &str,
+    block_time: u64,
+    config: &OutlierConfig,
+    sample_at: impl Fn(&str) -> Option<PairPriceSample>,
+) -> Vec<f64> {
+    let since = block_time.saturating_sub(config.window_hours * SECONDS_PER_HOUR);
+
+    (0..ring_slots(config))
+        .filter_map(|slot| sample_at(&slot_key(pair, slot)))
+        .filter(|sample| sample.timestamp >= since && sample.timestamp < block_time)
+        .map(|sample| sample.price)
+        .collect()
+}
+
+/// Key of a directed pair, e.g. `{input_mint}:{output_mint}`
+#[inline]
+fn pair_key(input_mint: &str, output_mint: &str) -> String {
+    format!("{}:{}", input_mint, output_mint)
+}
+
+/// Sample slots per pair: the window's sample periods plus the current one
+#[inline]
+fn ring_slots(config: &OutlierConfig) -> u64 {
+    config.window_hours * SECONDS_PER_HOUR / SAMPLE_SECONDS + 1
+}
+
+/// Key of the slot holding a pair's sample for the period containing `timestamp`
+#[inline]
+pub fn sample_key(pair: &str, timestamp: u64, config: &OutlierConfig) -> String {
+    slot_key(pair, timestamp / SAMPLE_SECONDS % ring_slots(config))
+}
+
+#[inline]
+fn slot_key(pair: &str, slot: u64) -> String {
+    format!("pair:{}:sample:{}", pair, slot)
+}
+
+/// Raw output per input amount of a successful trade
+fn trade_price(trade: &TradingData) -> Option<f64> {
+    if !trade.success || trade.amount_in == 0 || trade.amount_out == 0 {
+        return None;
+    }
+    Some(trade.amount_out as f64 / trade.amount_in as f64)
+}
+
+/// Prices of the block's non-dust trades, grouped by pair
+fn pair_prices(trades: &[TradingData], config: &OutlierConfig) -> BTreeMap<String, Vec<f64>> {
+    let mut prices: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for trade in trades {
+        if is_dust(trade, config) {
+            continue;
+        }
+        if let Some(price) = trade_price(trade) {
+            prices.entry(pair_key(&trade.input_mint, &trade.output_mint)).or_default().push(price);
+        }
+    }
+    prices
+}
+
+#[inline]
+fn is_dust(trade: &TradingData, config: &OutlierConfig) -> bool {
+    trade.amount_in < config.min_base_units || trade.amount_out < config.min_base_units
+}
+
+/// Classify a trade's price against the pair's reference price
+fn classify(trade: &TradingData, price: f64, reference: Option<f64>, config: &OutlierConfig) -> OutlierReason {
+    if is_dust(trade, config) {
+        return OutlierReason::Dust;
+    }
+
+    match reference {
+        Some(reference) if deviates(price, reference, config.max_deviation) => {
+            OutlierReason::PriceDeviation
+        }
+        _ => OutlierReason::None,
+    }
+}
+
+/// Whether `price` moved more than `max_deviation` from `reference`; a zero
+/// `max_deviation` accepts every price
+#[inline]
+fn deviates(price: f64, reference: f64, max_deviation: f64) -> bool {
+    if max_deviation <= 0.0 || reference <= 0.0 {
+        return false;
+    }
+    // Symmetric: +50% and -33% are the same move of the inverse pair
+    let bound = 1.0 + max_deviation;
+    price > reference * bound || price < reference / bound
+}
+
+/// Median of `values`, reordering them in place
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[middle - 1] + values[middle]) / 2.0)
+    } else {
+        Some(values[middle])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_trade(amount_in: u64, amount_out: u64) -> TradingData {
+        TradingData {
+            block_time: 1_700_000_000,
+            amount_in,
+            amount_out,
+            input_mint: "mintA".to_string(),
+            output_mint: "mintB".to_string(),
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(OutlierConfig::parse("").unwrap(), OutlierConfig::default());
+
+        let config = OutlierConfig::parse("max_deviation=0.25 & min_base_units=0").unwrap();
+        assert_eq!(config.max_deviation, 0.25);
+        assert_eq!(config.min_base_units, 0);
+        assert_eq!(config.window_hours, 6);
+
+        assert!(OutlierConfig::parse("max_deviation").is_err());
+        assert!(OutlierConfig::parse("max_deviation=-1").is_err());
+        assert!(OutlierConfig::parse("window_hours=0").is_err());
+        assert!(OutlierConfig::parse("window=3").is_err());
+    }
+
+    #[test]
+    fn test_classify() {
+        let config = OutlierConfig::default();
+        let classify_trade = |amount_in, amount_out, reference| {
+            let trade = create_test_trade(amount_in, amount_out);
+            classify(&trade, trade_price(&trade).unwrap(), reference, &config)
+        };
+
+        assert_eq!(classify_trade(1_000_000, 2_000_000, Some(2.0)), OutlierReason::None);
+        assert_eq!(classify_trade(1_000_000, 2_900_000, Some(2.0)), OutlierReason::None);
+        assert_eq!(classify_trade(1_000_000, 3_100_000, Some(2.0)), OutlierReason::PriceDeviation);
+        assert_eq!(classify_trade(1_000_000, 1_300_000, Some(2.0)), OutlierReason::PriceDeviation);
+        // No reference: only the dust check applies
+        assert_eq!(classify_trade(1_000_000, 100_000_000, None), OutlierReason::None);
+        assert_eq!(classify_trade(999, 2_000, Some(2.0)), OutlierReason::Dust);
+    }
+
+    #[test]
+    fn test_classify_disabled_checks() {
+        let config = OutlierConfig {
+            max_deviation: 0.0,
+            min_base_units: 0,
+            ..Default::default()
+        };
+        let trade = create_test_trade(1, 1_000);
+
+        assert_eq!(classify(&trade, 1_000.0, Some(2.0), &config), OutlierReason::None);
+    }
+
+    #[test]
+    fn test_pair_prices_skip_dust_and_failed() {
+        let mut failed = create_test_trade(1_000_000, 2_000_000);
+        failed.success = false;
+        let trades = vec![
+            create_test_trade(1_000_000, 2_000_000),
+            create_test_trade(10, 2_000_000),
+            failed,
+        ];
+
+        let prices = pair_prices(&trades, &OutlierConfig::default());
+
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices["mintA:mintB"], vec![2.0]);
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [3.0, 1.0, 200.0]), Some(3.0));
+        assert_eq!(median(&mut [4.0, 1.0, 2.0, 3.0]), Some(2.5));
+    }
+
+    #[test]
+    fn test_sample_price_skips_deviating_trades() {
+        let config = OutlierConfig::default();
+
+        assert_eq!(sample_price(vec![2.0, 2.1, 50.0, 1.9, 2.2], &config), Some(2.05));
+        // Too few trades to tell which one is off
+        assert_eq!(sample_price(vec![2.0, 50.0], &config), Some(26.0));
+        assert_eq!(sample_price(Vec::new(), &config), None);
+    }
+
+    #[test]
+    fn test_window_prices() {
+        let config = OutlierConfig {
+            window_hours: 1,
+            ..Default::default()
+        };
+        let now = 1_700_000_000;
+        let samples: std::collections::HashMap<String, PairPriceSample> = [
+            (now - 400, 2.0),
+            (now - 1_800, 2.2),
+            // Older than the window, in a slot not reused since
+            (now - 2 * SECONDS_PER_HOUR + 30, 9.0),
+            // Written by this block
+            (now, 5.0),
+        ]
+        .into_iter()
+        .map(|(timestamp, price)| {
+            let key = sample_key("mintA:mintB", timestamp, &config);
+            (key, PairPriceSample { price, timestamp })
+        })
+        .collect();
+
+        let mut prices = window_prices("mintA:mintB", now, &config, |key| samples.get(key).cloned());
+        prices.sort_by(f64::total_cmp);
+
+        assert_eq!(prices, vec![2.0, 2.2]);
+    }
+
+    #[test]
+    fn test_sample_keys() {
+        let config = OutlierConfig::default();
+
+        assert_eq!(ring_slots(&config), 73);
+        assert_eq!(sample_key("mintA:mintB", 0, &config), "pair:mintA:mintB:sample:0");
+        assert_eq!(sample_key("mintA:mintB", 299, &config), "pair:mintA:mintB:sample:0");
+        assert_eq!(sample_key("mintA:mintB", 300, &config), "pair:mintA:mintB:sample:1");
+        // The slot comes back after the window and the current period
+        assert_eq!(sample_key("mintA:mintB", 73 * SAMPLE_SECONDS, &config), "pair:mintA:mintB:sample:0");
+    }
+}
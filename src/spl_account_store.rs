@@ -1,30 +1,284 @@
-use crate::constants::TOKEN_PROGRAM_ID;
-use crate::pb::sf::jupiter::v1::{AccountOwnerRecord, AccountOwnerRecords};
+//! SPL token account ownership
+//!
+//! Records the account → mint → owner triple of every token account initialized
+//! in a block, for both the Token and Token-2022 programs (which share the
+//! instruction layout). Only the InitializeAccount instructions carry that triple;
+//! variants 2 and 3 pass the owner in instruction data instead of the account list.
+//! Associated token accounts are recorded from the ATA program's Create and
+//! CreateIdempotent instructions, whose accounts name the wallet and mint directly.
+//! Owner changes (`SetAuthority(AccountOwner)`) and closes are recorded too, so
+//! `store_account_owners` can keep ownership current across blocks.
+
+use crate::constants::{is_token_program, ASSOCIATED_TOKEN_PROGRAM_ID};
+use crate::pb::sf::jupiter::v1::{AccountOwnerChange, AccountOwnerRecord, AccountOwnerRecords};
 use substreams::errors::Error;
+use substreams::store::{StoreDelete, StoreNew, StoreSet, StoreSetString};
+use substreams_solana::base58;
 use substreams_solana::pb::sf::solana::r#type::v1::Block;
 
+// SPL Token instruction tags (first data byte)
+const INITIALIZE_ACCOUNT: u8 = 1;
+const SET_AUTHORITY: u8 = 6;
+const CLOSE_ACCOUNT: u8 = 9;
+const INITIALIZE_ACCOUNT_2: u8 = 16;
+const INITIALIZE_ACCOUNT_3: u8 = 18;
+
+// SetAuthority authority type for the token account owner
+const AUTHORITY_TYPE_ACCOUNT_OWNER: u8 = 2;
+
+// Associated Token Account instruction tags (Create may also have empty data)
+const ATA_CREATE: u8 = 0;
+const ATA_CREATE_IDEMPOTENT: u8 = 1;
+
 #[substreams::handlers::map]
 pub fn map_spl_initialized_account(block: Block) -> Result<AccountOwnerRecords, Error> {
     let mut records = Vec::new();
 
     for trx in block.transactions() {
         for instruction in trx.walk_instructions() {
-            if instruction.program_id().to_string() != TOKEN_PROGRAM_ID {
+            let program_id = instruction.program_id().to_string();
+            let decode = if is_token_program(&program_id) {
+                decode_owner_change
+            } else if program_id == ASSOCIATED_TOKEN_PROGRAM_ID {
+                decode_associated_account
+            } else {
                 continue;
-            }
+            };
 
             let accounts = instruction.accounts();
-            if accounts.len() < 3 {
-                continue;
-            }
+            let accounts: Vec<&[u8]> = accounts.iter().map(|address| address.as_ref()).collect();
 
-            records.push(AccountOwnerRecord {
-                account: accounts[0].as_ref().to_vec(),
-                mint: accounts[1].as_ref().to_vec(),
-                owner: accounts[2].as_ref().to_vec(),
-            });
+            if let Some(record) = decode(instruction.data(), &accounts) {
+                records.push(record);
+            }
         }
     }
 
     Ok(AccountOwnerRecords { records })
-}
\ No newline at end of file
+}
+
+/// Store handler for the current owner and mint of every token account
+///
+/// Key format: `account:{address}:owner` and `account:{address}:mint`
+/// Value: base58 owner / mint. Closed accounts are deleted, so a later
+/// re-initialization of the same address starts clean.
+#[substreams::handlers::store]
+pub fn store_account_owners(records: AccountOwnerRecords, store: StoreSetString) {
+    for (ordinal, record) in records.records.iter().enumerate() {
+        let ordinal = ordinal as u64;
+        let account = base58::encode(&record.account);
+
+        match record.change() {
+            AccountOwnerChange::Initialized => {
+                store.set(ordinal, owner_key(&account), &base58::encode(&record.owner));
+                store.set(ordinal, mint_key(&account), &base58::encode(&record.mint));
+            }
+            AccountOwnerChange::OwnerSet => {
+                store.set(ordinal, owner_key(&account), &base58::encode(&record.owner));
+            }
+            AccountOwnerChange::Closed => {
+                store.delete_prefix(ordinal as i64, &account_prefix(&account));
+            }
+        }
+    }
+}
+
+/// Key of a token account's owner in `store_account_owners`
+#[inline]
+pub fn owner_key(account: &str) -> String {
+    format!("{}owner", account_prefix(account))
+}
+
+/// Key of a token account's mint in `store_account_owners`
+#[inline]
+pub fn mint_key(account: &str) -> String {
+    format!("{}mint", account_prefix(account))
+}
+
+/// The trailing separator keeps one address from prefixing another
+#[inline]
+fn account_prefix(account: &str) -> String {
+    format!("account:{}:", account)
+}
+
+/// Decode a Token Program instruction that changes a token account's ownership
+///
+/// InitializeAccount:  data `[1]`, accounts [0] account, [1] mint, [2] owner, [3] rent
+/// InitializeAccount2: data `[16, owner]`, accounts [0] account, [1] mint, [2] rent
+/// InitializeAccount3: data `[18, owner]`, accounts [0] account, [1] mint
+/// SetAuthority:       data `[6, authority_type, COption<owner>]`, accounts [0] account, [1] current authority
+/// CloseAccount:       data `[9]`, accounts [0] account, [1] destination, [2] owner
+///
+/// SetAuthority only counts for the AccountOwner authority type. Returns `None`
+/// for every other Token Program instruction.
+fn decode_owner_change(data: &[u8], accounts: &[&[u8]]) -> Option<AccountOwnerRecord> {
+    let (tag, args) = data.split_first()?;
+    let account = accounts.first()?.to_vec();
+
+    let (mint, owner, change) = match *tag {
+        INITIALIZE_ACCOUNT if args.is_empty() => {
+            (*accounts.get(1)?, *accounts.get(2)?, AccountOwnerChange::Initialized)
+        }
+        INITIALIZE_ACCOUNT_2 | INITIALIZE_ACCOUNT_3 if args.len() == 32 => {
+            (*accounts.get(1)?, args, AccountOwnerChange::Initialized)
+        }
+        // A new owner is always Some: [authority_type, 1, owner]
+        SET_AUTHORITY if args.len() == 34 && args[..2] == [AUTHORITY_TYPE_ACCOUNT_OWNER, 1] => {
+            (&[][..], &args[2..], AccountOwnerChange::OwnerSet)
+        }
+        CLOSE_ACCOUNT if args.is_empty() => (&[][..], &[][..], AccountOwnerChange::Closed),
+        _ => return None,
+    };
+
+    Some(AccountOwnerRecord {
+        account,
+        mint: mint.to_vec(),
+        owner: owner.to_vec(),
+        change: change as i32,
+    })
+}
+
+/// Decode an Associated Token Account Create/CreateIdempotent instruction
+///
+/// Accounts: [0] funding account, [1] associated token account, [2] wallet,
+/// [3] mint, [4] system program, [5] token program
+///
+/// The ATA program initializes the account through a Token Program CPI, so a
+/// Create usually yields a second, identical record from that InitializeAccount3.
+fn decode_associated_account(data: &[u8], accounts: &[&[u8]]) -> Option<AccountOwnerRecord> {
+    match data.first() {
+        None | Some(&ATA_CREATE) | Some(&ATA_CREATE_IDEMPOTENT) if data.len() <= 1 => {}
+        _ => return None,
+    }
+    if accounts.len() < 4 {
+        return None;
+    }
+
+    Some(AccountOwnerRecord {
+        account: accounts[1].to_vec(),
+        mint: accounts[3].to_vec(),
+        owner: accounts[2].to_vec(),
+        change: AccountOwnerChange::Initialized as i32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: [u8; 32] = [1; 32];
+    const MINT: [u8; 32] = [2; 32];
+    const OWNER: [u8; 32] = [3; 32];
+    const RENT: [u8; 32] = [4; 32];
+
+    fn expected_record() -> AccountOwnerRecord {
+        AccountOwnerRecord {
+            account: ACCOUNT.to_vec(),
+            mint: MINT.to_vec(),
+            owner: OWNER.to_vec(),
+            change: AccountOwnerChange::Initialized as i32,
+        }
+    }
+
+    fn with_owner(tag: u8) -> Vec<u8> {
+        let mut data = vec![tag];
+        data.extend_from_slice(&OWNER);
+        data
+    }
+
+    #[test]
+    fn test_decode_owner_change() {
+        let accounts: [&[u8]; 4] = [&ACCOUNT, &MINT, &OWNER, &RENT];
+        assert_eq!(decode_owner_change(&[1], &accounts), Some(expected_record()));
+
+        // Owner must be in the account list
+        assert_eq!(decode_owner_change(&[1], &accounts[..2]), None);
+    }
+
+    #[test]
+    fn test_decode_initialize_account_2_and_3() {
+        // Variant 2 keeps the rent sysvar in [2]; the owner comes from data
+        let accounts: [&[u8]; 3] = [&ACCOUNT, &MINT, &RENT];
+        assert_eq!(
+            decode_owner_change(&with_owner(16), &accounts),
+            Some(expected_record())
+        );
+
+        let accounts: [&[u8]; 2] = [&ACCOUNT, &MINT];
+        assert_eq!(
+            decode_owner_change(&with_owner(18), &accounts),
+            Some(expected_record())
+        );
+
+        // Truncated owner
+        assert_eq!(decode_owner_change(&with_owner(18)[..20], &accounts), None);
+    }
+
+    #[test]
+    fn test_decode_set_authority_and_close() {
+        let mut set_owner = vec![6, 2, 1];
+        set_owner.extend_from_slice(&OWNER);
+        let accounts: [&[u8]; 2] = [&ACCOUNT, &RENT];
+
+        let record = decode_owner_change(&set_owner, &accounts).unwrap();
+        assert_eq!(record.change(), AccountOwnerChange::OwnerSet);
+        assert_eq!(record.account, ACCOUNT.to_vec());
+        assert_eq!(record.owner, OWNER.to_vec());
+        assert!(record.mint.is_empty());
+
+        // CloseAccount authority (3) and mint authorities don't move the owner
+        set_owner[1] = 3;
+        assert_eq!(decode_owner_change(&set_owner, &accounts), None);
+        assert_eq!(decode_owner_change(&[6, 2, 0], &accounts), None);
+
+        let accounts: [&[u8]; 3] = [&ACCOUNT, &RENT, &OWNER];
+        let record = decode_owner_change(&[9], &accounts).unwrap();
+        assert_eq!(record.change(), AccountOwnerChange::Closed);
+        assert_eq!(record.account, ACCOUNT.to_vec());
+        assert!(record.owner.is_empty());
+    }
+
+    #[test]
+    fn test_decode_associated_account() {
+        let funder: [u8; 32] = [5; 32];
+        let accounts: [&[u8]; 6] = [&funder, &ACCOUNT, &OWNER, &MINT, &RENT, &RENT];
+
+        // Create (legacy empty data or tag 0) and CreateIdempotent
+        for data in [&[][..], &[0], &[1]] {
+            assert_eq!(decode_associated_account(data, &accounts), Some(expected_record()));
+        }
+
+        // RecoverNested and malformed data are not creations
+        assert_eq!(decode_associated_account(&[2], &accounts), None);
+        assert_eq!(decode_associated_account(&[1, 0], &accounts), None);
+        assert_eq!(decode_associated_account(&[1], &accounts[..3]), None);
+    }
+
+    #[test]
+    fn test_store_keys() {
+        assert_eq!(owner_key("abc"), "account:abc:owner");
+        assert_eq!(mint_key("abc"), "account:abc:mint");
+        // Deleting one account's keys must not match a longer address
+        assert!(!owner_key("abcd").starts_with(&account_prefix("abc")));
+    }
+
+    #[test]
+    fn test_rejects_other_token_instructions() {
+        let accounts: [&[u8]; 4] = [&ACCOUNT, &MINT, &OWNER, &RENT];
+
+        // Transfer (3) and TransferChecked (12) have 3+ accounts
+        let mut transfer = vec![3];
+        transfer.extend_from_slice(&1_000u64.to_le_bytes());
+        assert_eq!(decode_owner_change(&transfer, &accounts), None);
+
+        let mut transfer_checked = vec![12];
+        transfer_checked.extend_from_slice(&1_000u64.to_le_bytes());
+        transfer_checked.push(6);
+        assert_eq!(decode_owner_change(&transfer_checked, &accounts), None);
+
+        assert_eq!(decode_owner_change(&[9, 0], &accounts), None);
+        assert_eq!(decode_owner_change(&[], &accounts), None);
+        // InitializeAccount never carries arguments
+        assert_eq!(decode_owner_change(&[1, 0], &accounts), None);
+    }
+}
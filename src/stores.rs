@@ -2,13 +2,18 @@
 //!
 //! Provides persistent state tracking across blocks for:
 //! - Cumulative swap volumes by token pair
+//! - USD volume routed through each DEX
 //! - Unique trader (wallet) tracking
 //! - Daily/hourly aggregations
 //! - Token statistics
 
-use crate::pb::sf::jupiter::v1::TradingDataList;
+use crate::pb::sf::jupiter::v1::{TokenPriceState, TradingDataList};
+use crate::token_price_store::stored_usd_value;
 use substreams::scalar::BigInt;
-use substreams::store::{StoreAdd, StoreAddBigInt, StoreNew, StoreSetIfNotExists, StoreSetIfNotExistsString};
+use substreams::store::{
+    StoreAdd, StoreAddBigInt, StoreAddFloat64, StoreGet, StoreGetInt64, StoreGetProto, StoreNew,
+    StoreSetIfNotExists, StoreSetIfNotExistsString,
+};
 
 /// Store handler for tracking cumulative swap volumes by token pair
 ///
@@ -17,8 +22,8 @@ use substreams::store::{StoreAdd, StoreAddBigInt, StoreNew, StoreSetIfNotExists,
 #[substreams::handlers::store]
 pub fn store_swap_volumes(trading_data: TradingDataList, store: StoreAddBigInt) {
     for trade in &trading_data.items {
-        // Skip non-swap instructions
-        if trade.amount_in == 0 {
+        // Skip non-swap instructions and reverted swaps
+        if trade.amount_in == 0 || !trade.success {
             continue;
         }
 
@@ -59,6 +64,42 @@ pub fn store_swap_volumes(trading_data: TradingDataList, store: StoreAddBigInt)
 
         let program_count_key = format!("program:{}:count", trade.program_id);
         store.add(0, &program_count_key, &BigInt::from(1u64));
+
+        // Store routed hops by DEX (USD volume in store_dex_volume_usd)
+        for hop in &trade.hops {
+            let dex_count_key = format!("dex:{}:hop_count", hop.dex);
+            store.add(0, &dex_count_key, &BigInt::from(1u64));
+        }
+    }
+}
+
+/// Store handler for the USD volume routed through each DEX
+///
+/// Key format: `dex:{dex}:volume_usd`
+/// Value: Cumulative USD value of the DEX's hops, each valued at its input mint's
+/// last price, else its output mint's (hops without a fresh price add nothing)
+#[substreams::handlers::store]
+pub fn store_dex_volume_usd(
+    trading_data: TradingDataList,
+    mint_decimals: StoreGetInt64,
+    last_prices: StoreGetProto<TokenPriceState>,
+    store: StoreAddFloat64,
+) {
+    for trade in &trading_data.items {
+        if trade.amount_in == 0 || !trade.success {
+            continue;
+        }
+
+        let value_usd = |mint: &str, amount: u64| {
+            stored_usd_value(mint, amount, trade.block_time, &mint_decimals, &last_prices)
+        };
+        for hop in &trade.hops {
+            if let Some(volume_usd) = value_usd(&hop.input_mint, hop.input_amount)
+                .or_else(|| value_usd(&hop.output_mint, hop.output_amount))
+            {
+                store.add(0, format!("dex:{}:volume_usd", hop.dex), volume_usd);
+            }
+        }
     }
 }
 
@@ -69,8 +110,8 @@ pub fn store_swap_volumes(trading_data: TradingDataList, store: StoreAddBigInt)
 #[substreams::handlers::store]
 pub fn store_unique_traders(trading_data: TradingDataList, store: StoreSetIfNotExistsString) {
     for trade in &trading_data.items {
-        // Skip if no wallet info
-        if trade.user_wallet.is_empty() {
+        // Skip if no wallet info or the swap reverted
+        if trade.user_wallet.is_empty() || !trade.success {
             continue;
         }
 
@@ -98,7 +139,7 @@ pub fn store_unique_traders(trading_data: TradingDataList, store: StoreSetIfNotE
 #[substreams::handlers::store]
 pub fn store_token_stats(trading_data: TradingDataList, store: StoreAddBigInt) {
     for trade in &trading_data.items {
-        if trade.amount_in == 0 {
+        if trade.amount_in == 0 || !trade.success {
             continue;
         }
 
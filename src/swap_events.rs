@@ -0,0 +1,160 @@
+//! Swap events with route hops
+//!
+//! Converts parsed Jupiter swaps into `jupiter.events.v1.SwapEvent` records,
+//! each carrying its ordered route hops as `SwapRoute` entries.
+
+use crate::constants::{get_jupiter_version, is_jupiter_swap_program};
+use crate::pb::jupiter::events::v1::{SwapEvent, SwapEvents, SwapRoute, SwapStatus};
+use crate::pb::sf::jupiter::v1::{SwapHop, TradingData, TradingDataList};
+use substreams::errors::Error;
+use substreams::pb::substreams::Clock;
+
+/// Emit one SwapEvent per parsed Jupiter swap, with its hops as SwapRoutes
+#[substreams::handlers::map]
+pub fn map_swap_events(clock: Clock, trading_data: TradingDataList) -> Result<SwapEvents, Error> {
+    Ok(SwapEvents {
+        events: build_swap_events(&trading_data),
+        block_number: clock.number,
+        block_hash: clock.id,
+        timestamp: clock.timestamp.map(|ts| ts.seconds.max(0) as u64).unwrap_or_default(),
+    })
+}
+
+/// Core conversion logic (extracted for testability)
+fn build_swap_events(trading_data: &TradingDataList) -> Vec<SwapEvent> {
+    trading_data
+        .items
+        .iter()
+        .filter(|trade| trade.amount_in > 0 && is_jupiter_swap_program(&trade.program_id))
+        .map(to_swap_event)
+        .collect()
+}
+
+/// Build a SwapEvent from a parsed swap
+fn to_swap_event(trade: &TradingData) -> SwapEvent {
+    SwapEvent {
+        transaction_signature: trade.transaction_id.clone(),
+        user: trade.user_wallet.clone(),
+        input_mint: trade.input_mint.clone(),
+        output_mint: trade.output_mint.clone(),
+        input_amount: trade.amount_in,
+        output_amount: trade.amount_out,
+        routes: trade.hops.iter().map(to_swap_route).collect(),
+        program_id: trade.program_id.clone(),
+        slot: trade.slot,
+        timestamp: trade.block_time,
+        version: get_jupiter_version(&trade.program_id).unwrap_or_default().to_string(),
+        status: swap_status(trade) as i32,
+        error_message: trade.error_message.clone(),
+        swap_id: trade.swap_id.clone(),
+        ..Default::default()
+    }
+}
+
+/// Success, or Failed for swaps whose transaction reverted
+#[inline]
+fn swap_status(trade: &TradingData) -> SwapStatus {
+    if trade.success {
+        SwapStatus::Success
+    } else {
+        SwapStatus::Failed
+    }
+}
+
+/// Build a SwapRoute from a decoded route hop
+fn to_swap_route(hop: &SwapHop) -> SwapRoute {
+    SwapRoute {
+        program_id: hop.amm.clone(),
+        input_mint: hop.input_mint.clone(),
+        output_mint: hop.output_mint.clone(),
+        input_amount: hop.input_amount,
+        output_amount: hop.output_amount,
+        hop_index: hop.hop_index,
+        percent: hop.percent,
+        dex: hop.dex.clone(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{JUPITER_DCA_PROGRAM_ID, JUPITER_V6_PROGRAM_ID};
+
+    fn create_test_hop(hop_index: u32, percent: u32) -> SwapHop {
+        SwapHop {
+            amm: "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(),
+            input_mint: "mintA".to_string(),
+            input_amount: 1_000,
+            output_mint: "mintB".to_string(),
+            output_amount: 990,
+            hop_index,
+            percent,
+            dex: "orca_whirlpool".to_string(),
+        }
+    }
+
+    fn create_test_trade(program_id: &str, amount_in: u64) -> TradingData {
+        TradingData {
+            program_id: program_id.to_string(),
+            transaction_id: "test_tx_123".to_string(),
+            slot: 1000,
+            block_time: 1700000000,
+            amount_in,
+            amount_out: 1_980,
+            input_mint: "mintA".to_string(),
+            output_mint: "mintB".to_string(),
+            user_wallet: "wallet".to_string(),
+            hops: vec![create_test_hop(0, 50), create_test_hop(1, 50)],
+            swap_id: "test_tx_123:2".to_string(),
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_swap_event_carries_ordered_routes() {
+        let event = to_swap_event(&create_test_trade(JUPITER_V6_PROGRAM_ID, 2_000));
+
+        assert_eq!(event.version, "v6");
+        assert_eq!(event.swap_id, "test_tx_123:2");
+        assert_eq!(event.status, SwapStatus::Success as i32);
+        assert_eq!(event.input_amount, 2_000);
+        assert_eq!(event.routes.len(), 2);
+        assert_eq!(event.routes[1].hop_index, 1);
+        assert_eq!(event.routes[1].percent, 50);
+        assert_eq!(event.routes[1].dex, "orca_whirlpool");
+        assert_eq!(event.routes[0].program_id, "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+    }
+
+    #[test]
+    fn test_failed_swap_event_carries_error() {
+        let mut trade = create_test_trade(JUPITER_V6_PROGRAM_ID, 2_000);
+        trade.success = false;
+        trade.error_code = "SlippageToleranceExceeded".to_string();
+        trade.error_message = "Slippage tolerance exceeded".to_string();
+
+        let event = to_swap_event(&trade);
+
+        assert_eq!(event.status, SwapStatus::Failed as i32);
+        assert_eq!(event.error_message, "Slippage tolerance exceeded");
+    }
+
+    #[test]
+    fn test_build_swap_events_skips_non_swaps() {
+        let trading_data = TradingDataList {
+            items: vec![
+                create_test_trade(JUPITER_V6_PROGRAM_ID, 2_000),
+                create_test_trade(JUPITER_V6_PROGRAM_ID, 0),
+                create_test_trade(JUPITER_DCA_PROGRAM_ID, 2_000),
+            ],
+            total_volume: 4_000,
+            swap_count: 2,
+        };
+
+        let events = build_swap_events(&trading_data);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].program_id, JUPITER_V6_PROGRAM_ID);
+    }
+}
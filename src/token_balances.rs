@@ -0,0 +1,303 @@
+//! Transaction meta token balance helpers
+//!
+//! Indexes the pre/post SPL token balances of a transaction by token account
+//! address, so settled swap amounts can be derived from balance deltas instead
+//! of the quoted amounts carried in instruction arguments. Also decodes the
+//! Token-2022 transfer fees withheld on the way into an account, for amounts
+//! that can't come from a balance delta.
+
+use std::collections::HashMap;
+
+use crate::transaction_view::TransactionAccounts;
+use substreams_solana::pb::sf::solana::r#type::v1::{ConfirmedTransaction, TokenBalance};
+
+// Token-2022 TransferFeeExtension instruction tag and its TransferCheckedWithFee sub-tag
+const TRANSFER_FEE_EXTENSION: u8 = 26;
+const TRANSFER_CHECKED_WITH_FEE: u8 = 1;
+
+// [26, 1, amount (u64), decimals (u8), fee (u64)]
+const TRANSFER_CHECKED_WITH_FEE_LEN: usize = 19;
+
+/// Pre/post balance of a single token account within one transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenBalanceChange {
+    pub mint: String,
+    pub owner: String,
+    /// Token program owning the account (Token or Token-2022), empty if not recorded
+    pub program_id: String,
+    /// Mint decimals, `None` if the balance carried no UI amount
+    pub decimals: Option<u32>,
+    pub pre: u64,
+    pub post: u64,
+}
+
+impl TokenBalanceChange {
+    /// Amount that left the account (zero if the balance grew)
+    #[inline]
+    pub fn decrease(&self) -> u64 {
+        self.pre.saturating_sub(self.post)
+    }
+
+    /// Amount that arrived in the account (zero if the balance shrank)
+    #[inline]
+    pub fn increase(&self) -> u64 {
+        self.post.saturating_sub(self.pre)
+    }
+}
+
+/// Build an index of token balance changes keyed by token account address
+///
+/// Accounts created in the transaction have no pre balance and accounts closed
+/// in it have no post balance; both are treated as zero. Balances are what the
+/// account actually holds, so Token-2022 transfer fees withheld on the way in are
+/// already excluded from an increase. Balance account indices cover lookup table
+/// addresses like instruction accounts, so they resolve through `accounts`.
+pub fn build_token_balance_index(
+    trx: &ConfirmedTransaction,
+    accounts: &TransactionAccounts,
+) -> HashMap<String, TokenBalanceChange> {
+    let meta = match trx.meta.as_ref() {
+        Some(meta) => meta,
+        None => return HashMap::new(),
+    };
+
+    let mut index: HashMap<String, TokenBalanceChange> =
+        HashMap::with_capacity(meta.post_token_balances.len());
+
+    for balance in &meta.pre_token_balances {
+        if let Some(entry) = balance_entry(&mut index, accounts, balance) {
+            entry.pre = parse_amount(balance);
+        }
+    }
+
+    for balance in &meta.post_token_balances {
+        if let Some(entry) = balance_entry(&mut index, accounts, balance) {
+            entry.post = parse_amount(balance);
+        }
+    }
+
+    index
+}
+
+/// Find the token accounts of `owner` that an instruction spent from and received into
+///
+/// Scans the instruction's accounts in order and returns the first owned account
+/// whose balance decreased (source) and the first whose balance increased
+/// (destination). Used when an instruction layout has no fixed user account slots.
+pub fn find_owner_token_accounts<'a>(
+    index: &HashMap<String, TokenBalanceChange>,
+    owner: &str,
+    accounts: &'a [String],
+) -> (Option<&'a str>, Option<&'a str>) {
+    let mut source = None;
+    let mut destination = None;
+
+    for account in accounts {
+        let change = match index.get(account) {
+            Some(change) if change.owner == owner => change,
+            _ => continue,
+        };
+        if source.is_none() && change.decrease() > 0 {
+            source = Some(account.as_str());
+        } else if destination.is_none() && change.increase() > 0 {
+            destination = Some(account.as_str());
+        }
+    }
+
+    (source, destination)
+}
+
+/// Decimals of `mint` from any token account of that mint in the index
+pub fn find_mint_decimals(index: &HashMap<String, TokenBalanceChange>, mint: &str) -> Option<u32> {
+    if mint.is_empty() {
+        return None;
+    }
+    index
+        .values()
+        .filter(|change| change.mint == mint)
+        .find_map(|change| change.decimals)
+}
+
+/// Fee of a Token-2022 `TransferCheckedWithFee` instruction
+///
+/// Accounts: [0] source, [1] mint, [2] destination, [3] authority. The fee is
+/// withheld in the destination, which receives the amount minus the fee. Transfers
+/// made with plain `TransferChecked` are charged from the mint's `TransferFeeConfig`
+/// without naming the fee, so they aren't seen here.
+pub fn decode_transfer_checked_fee(data: &[u8]) -> Option<u64> {
+    if data.len() != TRANSFER_CHECKED_WITH_FEE_LEN
+        || data[0] != TRANSFER_FEE_EXTENSION
+        || data[1] != TRANSFER_CHECKED_WITH_FEE
+    {
+        return None;
+    }
+    let mut fee = [0u8; 8];
+    fee.copy_from_slice(&data[11..19]);
+    Some(u64::from_le_bytes(fee))
+}
+
+/// Get or create the index entry for a token balance's account
+fn balance_entry<'a>(
+    index: &'a mut HashMap<String, TokenBalanceChange>,
+    resolved: &TransactionAccounts,
+    balance: &TokenBalance,
+) -> Option<&'a mut TokenBalanceChange> {
+    let address = resolved.get(balance.account_index)?;
+    let entry = index.entry(address.to_string()).or_default();
+    if entry.mint.is_empty() {
+        entry.mint = balance.mint.clone();
+        entry.owner = balance.owner.clone();
+        entry.program_id = balance.program_id.clone();
+    }
+    if let Some(amount) = balance.ui_token_amount.as_ref() {
+        entry.decimals = Some(amount.decimals);
+    }
+    Some(entry)
+}
+
+/// Raw (base unit) amount of a token balance
+#[inline]
+fn parse_amount(balance: &TokenBalance) -> u64 {
+    balance
+        .ui_token_amount
+        .as_ref()
+        .and_then(|amount| amount.amount.parse::<u64>().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substreams_solana::base58;
+    use substreams_solana::pb::sf::solana::r#type::v1::{
+        Message, Transaction, TransactionStatusMeta, UiTokenAmount,
+    };
+
+    fn token_balance(account_index: u32, mint: &str, amount: u64) -> TokenBalance {
+        TokenBalance {
+            account_index,
+            mint: mint.to_string(),
+            ui_token_amount: Some(UiTokenAmount {
+                amount: amount.to_string(),
+                ..Default::default()
+            }),
+            owner: "owner".to_string(),
+            program_id: String::new(),
+        }
+    }
+
+    fn create_test_transaction(
+        pre: Vec<TokenBalance>,
+        post: Vec<TokenBalance>,
+    ) -> ConfirmedTransaction {
+        ConfirmedTransaction {
+            transaction: Some(Transaction {
+                signatures: vec![vec![1; 64]],
+                message: Some(Message {
+                    account_keys: vec![vec![1; 32], vec![2; 32]],
+                    ..Default::default()
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                pre_token_balances: pre,
+                post_token_balances: post,
+                loaded_writable_addresses: vec![vec![3; 32]],
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_decode_transfer_checked_fee() {
+        // TransferCheckedWithFee(amount: 2_000_000, decimals: 6, fee: 20_000)
+        let mut data = vec![26, 1];
+        data.extend_from_slice(&2_000_000u64.to_le_bytes());
+        data.push(6);
+        data.extend_from_slice(&20_000u64.to_le_bytes());
+        assert_eq!(decode_transfer_checked_fee(&data), Some(20_000));
+
+        // TransferChecked (tag 12) names no fee
+        let mut data = vec![12];
+        data.extend_from_slice(&2_000_000u64.to_le_bytes());
+        data.push(6);
+        assert_eq!(decode_transfer_checked_fee(&data), None);
+        assert_eq!(decode_transfer_checked_fee(&[26, 1, 0]), None);
+    }
+
+    #[test]
+    fn test_balance_deltas() {
+        let trx = create_test_transaction(
+            vec![token_balance(0, "mintA", 5_000), token_balance(1, "mintB", 100)],
+            vec![token_balance(0, "mintA", 1_000), token_balance(1, "mintB", 900)],
+        );
+
+        let index = build_token_balance_index(&trx, &TransactionAccounts::new(&trx));
+
+        let source = &index[&base58::encode([1u8; 32])];
+        assert_eq!(source.mint, "mintA");
+        assert_eq!(source.decrease(), 4_000);
+        assert_eq!(source.increase(), 0);
+
+        let destination = &index[&base58::encode([2u8; 32])];
+        assert_eq!(destination.increase(), 800);
+    }
+
+    #[test]
+    fn test_created_and_closed_accounts() {
+        // Account 0 closed during the tx, account 2 (lookup table) created in it
+        let trx = create_test_transaction(
+            vec![token_balance(0, "mintA", 2_500)],
+            vec![token_balance(2, "mintB", 700)],
+        );
+
+        let index = build_token_balance_index(&trx, &TransactionAccounts::new(&trx));
+
+        assert_eq!(index[&base58::encode([1u8; 32])].decrease(), 2_500);
+        assert_eq!(index[&base58::encode([3u8; 32])].increase(), 700);
+    }
+
+    #[test]
+    fn test_find_owner_token_accounts() {
+        let mut index = HashMap::new();
+        let change = |owner: &str, pre: u64, post: u64| TokenBalanceChange {
+            mint: "mint".to_string(),
+            owner: owner.to_string(),
+            pre,
+            post,
+            ..Default::default()
+        };
+        index.insert("poolVault".to_string(), change("pool", 100, 50));
+        index.insert("userIn".to_string(), change("user", 80, 30));
+        index.insert("userOut".to_string(), change("user", 0, 40));
+        let accounts: Vec<String> = ["poolVault", "userOut", "userIn", "unknown"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let (source, destination) = find_owner_token_accounts(&index, "user", &accounts);
+
+        assert_eq!(source, Some("userIn"));
+        assert_eq!(destination, Some("userOut"));
+        assert_eq!(find_owner_token_accounts(&index, "nobody", &accounts), (None, None));
+    }
+
+    #[test]
+    fn test_find_mint_decimals() {
+        let mut usdc = token_balance(1, "mintB", 900);
+        usdc.ui_token_amount.as_mut().unwrap().decimals = 6;
+        let trx = create_test_transaction(vec![], vec![token_balance(0, "mintA", 1), usdc]);
+
+        let index = build_token_balance_index(&trx, &TransactionAccounts::new(&trx));
+
+        assert_eq!(find_mint_decimals(&index, "mintB"), Some(6));
+        assert_eq!(find_mint_decimals(&index, "mintA"), Some(0));
+        assert_eq!(find_mint_decimals(&index, "mintC"), None);
+        assert_eq!(find_mint_decimals(&index, ""), None);
+    }
+
+    #[test]
+    fn test_out_of_range_index_ignored() {
+        let trx = create_test_transaction(vec![token_balance(9, "mintA", 1)], vec![]);
+        assert!(build_token_balance_index(&trx, &TransactionAccounts::new(&trx)).is_empty());
+    }
+}
@@ -0,0 +1,630 @@
+//! Token metadata
+//!
+//! Decodes the instructions that set a token's name, symbol, uri and update
+//! authority, so tables keyed by mint can be joined to readable names:
+//!
+//! - Metaplex Token Metadata: the legacy `CreateMetadataAccount` (v1-v3) and
+//!   `UpdateMetadataAccount` (v1-v2) instructions, plus the newer `Create` and `Update`.
+//! - Token-2022 metadata extension (`spl-token-metadata-interface`): `Initialize`,
+//!   `UpdateField` and `UpdateAuthority`.
+//!
+//! Update instructions often name only the metadata account, so `store_metadata_mints`
+//! remembers the mint of every metadata account seen at creation.
+
+use borsh::BorshDeserialize;
+
+use crate::constants::{TOKEN_2022_PROGRAM_ID, TOKEN_METADATA_PROGRAM_ID};
+use crate::jupiter_v6::{deserialize, DecodeError};
+use crate::pb::sf::jupiter::v1::{TokenMetadata, TokenMetadataList};
+use substreams::errors::Error;
+use substreams::log;
+use substreams::store::{StoreNew, StoreSet, StoreSetString};
+use substreams_solana::base58;
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
+
+// Metaplex Token Metadata instruction tags (first data byte)
+const CREATE_METADATA_ACCOUNT: u8 = 0;
+const UPDATE_METADATA_ACCOUNT: u8 = 1;
+const UPDATE_METADATA_ACCOUNT_V2: u8 = 15;
+const CREATE_METADATA_ACCOUNT_V2: u8 = 16;
+const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+const CREATE: u8 = 42;
+const UPDATE: u8 = 50;
+
+// `Update` argument variants that set the update authority and/or data
+const UPDATE_V1: u8 = 0;
+const UPDATE_AS_UPDATE_AUTHORITY_V2: u8 = 1;
+const UPDATE_AS_AUTHORITY_ITEM_DELEGATE_V2: u8 = 2;
+const UPDATE_AS_DATA_DELEGATE_V2: u8 = 4;
+const UPDATE_AS_DATA_ITEM_DELEGATE_V2: u8 = 6;
+
+// Token metadata interface discriminators
+// (sha256("spl_token_metadata_interface:<name>")[..8])
+pub const INITIALIZE_DISCRIMINATOR: [u8; 8] = [210, 225, 30, 162, 88, 184, 77, 141];
+pub const UPDATE_FIELD_DISCRIMINATOR: [u8; 8] = [221, 233, 49, 45, 181, 202, 220, 200];
+pub const UPDATE_AUTHORITY_DISCRIMINATOR: [u8; 8] = [215, 228, 166, 228, 84, 100, 86, 123];
+
+#[derive(BorshDeserialize)]
+struct Creator {
+    _address: [u8; 32],
+    _verified: bool,
+    _share: u8,
+}
+
+/// Metaplex `Data`
+#[derive(BorshDeserialize)]
+struct Data {
+    name: String,
+    symbol: String,
+    uri: String,
+    _seller_fee_basis_points: u16,
+    _creators: Option<Vec<Creator>>,
+}
+
+#[derive(BorshDeserialize)]
+struct Collection {
+    _verified: bool,
+    _key: [u8; 32],
+}
+
+#[derive(BorshDeserialize)]
+enum UseMethod {
+    Burn,
+    Multiple,
+    Single,
+}
+
+#[derive(BorshDeserialize)]
+struct Uses {
+    _use_method: UseMethod,
+    _remaining: u64,
+    _total: u64,
+}
+
+/// Metaplex `DataV2`: `Data` plus collection and uses
+#[derive(BorshDeserialize)]
+struct DataV2 {
+    name: String,
+    symbol: String,
+    uri: String,
+    _seller_fee_basis_points: u16,
+    _creators: Option<Vec<Creator>>,
+    _collection: Option<Collection>,
+    _uses: Option<Uses>,
+}
+
+#[derive(BorshDeserialize)]
+enum CollectionDetails {
+    V1 { _size: u64 },
+    V2 { _padding: [u8; 8] },
+}
+
+#[derive(BorshDeserialize)]
+struct CreateMetadataAccountArgs {
+    data: Data,
+    _is_mutable: bool,
+}
+
+#[derive(BorshDeserialize)]
+struct CreateMetadataAccountArgsV2 {
+    data: DataV2,
+    _is_mutable: bool,
+}
+
+#[derive(BorshDeserialize)]
+struct CreateMetadataAccountArgsV3 {
+    data: DataV2,
+    _is_mutable: bool,
+    _collection_details: Option<CollectionDetails>,
+}
+
+#[derive(BorshDeserialize)]
+struct UpdateMetadataAccountArgs {
+    data: Option<Data>,
+    update_authority: Option<[u8; 32]>,
+    _primary_sale_happened: Option<bool>,
+}
+
+#[derive(BorshDeserialize)]
+struct UpdateMetadataAccountArgsV2 {
+    data: Option<DataV2>,
+    update_authority: Option<[u8; 32]>,
+    _primary_sale_happened: Option<bool>,
+    _is_mutable: Option<bool>,
+}
+
+/// Leading fields of `Create`'s `AssetData`
+#[derive(BorshDeserialize)]
+struct AssetDataPrefix {
+    name: String,
+    symbol: String,
+    uri: String,
+}
+
+/// Token metadata interface `Initialize`
+#[derive(BorshDeserialize)]
+struct InitializeArgs {
+    name: String,
+    symbol: String,
+    uri: String,
+}
+
+/// Token metadata interface field; additional keys are not tracked
+#[derive(BorshDeserialize)]
+enum Field {
+    Name,
+    Symbol,
+    Uri,
+    Key { _key: String },
+}
+
+#[derive(BorshDeserialize)]
+struct UpdateFieldArgs {
+    field: Field,
+    value: String,
+}
+
+/// Where an instruction takes the new update authority from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorityChange {
+    Unchanged,
+    /// Instruction account at this index
+    Account(usize),
+    /// Instruction data; `None` removes the authority
+    Key(Option<[u8; 32]>),
+}
+
+/// Metadata fields set by one instruction, `None` when left unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataChange {
+    /// Instruction name, e.g. `create_metadata_account_v3`
+    pub instruction: &'static str,
+    pub metadata_index: usize,
+    /// `None` when the instruction names only the metadata account
+    pub mint_index: Option<usize>,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub uri: Option<String>,
+    pub update_authority: AuthorityChange,
+}
+
+impl MetadataChange {
+    fn new(instruction: &'static str, metadata_index: usize, mint_index: Option<usize>) -> Self {
+        Self {
+            instruction,
+            metadata_index,
+            mint_index,
+            name: None,
+            symbol: None,
+            uri: None,
+            update_authority: AuthorityChange::Unchanged,
+        }
+    }
+
+    fn with_fields(mut self, name: String, symbol: String, uri: String) -> Self {
+        self.name = Some(clean(name));
+        self.symbol = Some(clean(symbol));
+        self.uri = Some(clean(uri));
+        self
+    }
+
+    fn with_authority(mut self, update_authority: AuthorityChange) -> Self {
+        self.update_authority = update_authority;
+        self
+    }
+
+    /// Resolve account indices into a `TokenMetadata` record
+    ///
+    /// Returns `None` if the instruction passes fewer accounts than its layout needs.
+    pub fn to_token_metadata(&self, accounts: &[String]) -> Option<TokenMetadata> {
+        let mint = match self.mint_index {
+            Some(index) => accounts.get(index)?.clone(),
+            None => String::new(),
+        };
+        let update_authority = match self.update_authority {
+            AuthorityChange::Unchanged => None,
+            AuthorityChange::Account(index) => Some(accounts.get(index)?.clone()),
+            AuthorityChange::Key(key) => Some(key.map(base58::encode).unwrap_or_default()),
+        };
+
+        Some(TokenMetadata {
+            mint,
+            metadata_account: accounts.get(self.metadata_index)?.clone(),
+            name: self.name.clone(),
+            symbol: self.symbol.clone(),
+            uri: self.uri.clone(),
+            update_authority,
+            instruction: self.instruction.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_token_metadata(block: Block) -> Result<TokenMetadataList, Error> {
+    let mut items = Vec::new();
+
+    for trx in block.transactions() {
+        for instruction in trx.walk_instructions() {
+            let program_id = instruction.program_id().to_string();
+            let decoded = if program_id == TOKEN_METADATA_PROGRAM_ID {
+                decode_metaplex(instruction.data())
+            } else if program_id == TOKEN_2022_PROGRAM_ID {
+                decode_token_2022_metadata(instruction.data())
+            } else {
+                continue;
+            };
+
+            let change = match decoded {
+                Ok(Some(change)) => change,
+                Ok(None) => continue,
+                Err(err) => {
+                    log::info!("Failed to decode token metadata instruction: {}", err);
+                    continue;
+                }
+            };
+
+            let accounts: Vec<String> = instruction
+                .accounts()
+                .iter()
+                .map(|address| address.to_string())
+                .collect();
+
+            if let Some(mut metadata) = change.to_token_metadata(&accounts) {
+                metadata.program_id = program_id;
+                metadata.transaction_id = trx.id();
+                metadata.slot = block.slot;
+                items.push(metadata);
+            }
+        }
+    }
+
+    Ok(TokenMetadataList { items })
+}
+
+/// Store handler for the mint of every metadata account
+///
+/// Key format: `metadata:{account}`
+/// Value: base58 mint
+#[substreams::handlers::store]
+pub fn store_metadata_mints(metadata: TokenMetadataList, store: StoreSetString) {
+    for (ordinal, item) in metadata.items.iter().enumerate() {
+        if !item.mint.is_empty() {
+            store.set(ordinal as u64, metadata_mint_key(&item.metadata_account), &item.mint);
+        }
+    }
+}
+
+/// Key of a metadata account's mint in `store_metadata_mints`
+#[inline]
+pub fn metadata_mint_key(metadata_account: &str) -> String {
+    format!("metadata:{}", metadata_account)
+}
+
+/// Decode a Metaplex Token Metadata instruction that sets metadata fields
+///
+/// CreateMetadataAccount v1-v3: accounts [0] metadata, [1] mint, [2] mint authority,
+///                              [3] payer, [4] update authority
+/// UpdateMetadataAccount v1-v2: accounts [0] metadata, [1] update authority
+/// Create:                      accounts [0] metadata, [1] master edition, [2] mint,
+///                              [3] authority, [4] payer, [5] update authority
+/// Update:                      accounts [0] authority, [1] delegate record, [2] token,
+///                              [3] mint, [4] metadata
+///
+/// The legacy instructions are decoded strictly. `Create` and `Update` only read
+/// their leading fields; the rest (rule sets, authorization payloads) is not needed.
+/// Returns `Ok(None)` for every other instruction.
+pub fn decode_metaplex(data: &[u8]) -> Result<Option<MetadataChange>, DecodeError> {
+    let (tag, args) = match data.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+
+    let change = match *tag {
+        CREATE_METADATA_ACCOUNT => {
+            let Data { name, symbol, uri, .. } = deserialize::<CreateMetadataAccountArgs>(args)?.data;
+            MetadataChange::new("create_metadata_account", 0, Some(1))
+                .with_fields(name, symbol, uri)
+                .with_authority(AuthorityChange::Account(4))
+        }
+        CREATE_METADATA_ACCOUNT_V2 => {
+            let DataV2 { name, symbol, uri, .. } = deserialize::<CreateMetadataAccountArgsV2>(args)?.data;
+            MetadataChange::new("create_metadata_account_v2", 0, Some(1))
+                .with_fields(name, symbol, uri)
+                .with_authority(AuthorityChange::Account(4))
+        }
+        CREATE_METADATA_ACCOUNT_V3 => {
+            let DataV2 { name, symbol, uri, .. } = deserialize::<CreateMetadataAccountArgsV3>(args)?.data;
+            MetadataChange::new("create_metadata_account_v3", 0, Some(1))
+                .with_fields(name, symbol, uri)
+                .with_authority(AuthorityChange::Account(4))
+        }
+        UPDATE_METADATA_ACCOUNT => {
+            let args = deserialize::<UpdateMetadataAccountArgs>(args)?;
+            let data = args.data.map(|data| (data.name, data.symbol, data.uri));
+            legacy_update("update_metadata_account", data, args.update_authority)
+        }
+        UPDATE_METADATA_ACCOUNT_V2 => {
+            let args = deserialize::<UpdateMetadataAccountArgsV2>(args)?;
+            let data = args.data.map(|data| (data.name, data.symbol, data.uri));
+            legacy_update("update_metadata_account_v2", data, args.update_authority)
+        }
+        CREATE => {
+            // CreateArgs::V1 is the only variant
+            let asset_data = match args.split_first() {
+                Some((0, asset_data)) => deserialize_prefix::<AssetDataPrefix>(asset_data)?,
+                _ => return Err(DecodeError::InvalidArgs("unknown Create variant".to_string())),
+            };
+            MetadataChange::new("create", 0, Some(2))
+                .with_fields(asset_data.name, asset_data.symbol, asset_data.uri)
+                .with_authority(AuthorityChange::Account(5))
+        }
+        UPDATE => return decode_update(args),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(change))
+}
+
+/// Decode the `Update` variants that change the update authority or data
+///
+/// Collection, uses, rule set and programmable config updates return `Ok(None)`.
+fn decode_update(args: &[u8]) -> Result<Option<MetadataChange>, DecodeError> {
+    let (variant, fields) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(DecodeError::TooShort(1)),
+    };
+
+    let (update_authority, data) = match *variant {
+        UPDATE_V1 | UPDATE_AS_UPDATE_AUTHORITY_V2 => {
+            deserialize_prefix::<(Option<[u8; 32]>, Option<Data>)>(fields)?
+        }
+        UPDATE_AS_AUTHORITY_ITEM_DELEGATE_V2 => (deserialize_prefix(fields)?, None),
+        UPDATE_AS_DATA_DELEGATE_V2 | UPDATE_AS_DATA_ITEM_DELEGATE_V2 => (None, deserialize_prefix(fields)?),
+        _ => return Ok(None),
+    };
+    if update_authority.is_none() && data.is_none() {
+        return Ok(None);
+    }
+
+    let mut change = MetadataChange::new("update", 4, Some(3));
+    if let Some(data) = data {
+        change = change.with_fields(data.name, data.symbol, data.uri);
+    }
+    if let Some(update_authority) = update_authority {
+        change = change.with_authority(AuthorityChange::Key(Some(update_authority)));
+    }
+
+    Ok(Some(change))
+}
+
+/// Build the change of an `UpdateMetadataAccount` v1/v2; both fields are optional
+fn legacy_update(
+    instruction: &'static str,
+    data: Option<(String, String, String)>,
+    update_authority: Option<[u8; 32]>,
+) -> MetadataChange {
+    let mut change = MetadataChange::new(instruction, 0, None);
+    if let Some((name, symbol, uri)) = data {
+        change = change.with_fields(name, symbol, uri);
+    }
+    if let Some(update_authority) = update_authority {
+        change = change.with_authority(AuthorityChange::Key(Some(update_authority)));
+    }
+    change
+}
+
+/// Decode a Token-2022 metadata extension instruction
+///
+/// Initialize:      accounts [0] metadata, [1] update authority, [2] mint, [3] mint authority
+/// UpdateField:     accounts [0] metadata, [1] update authority
+/// UpdateAuthority: accounts [0] metadata, [1] current update authority
+///
+/// The metadata account is usually the mint itself. Updates of additional keys
+/// and every other Token-2022 instruction return `Ok(None)`.
+pub fn decode_token_2022_metadata(data: &[u8]) -> Result<Option<MetadataChange>, DecodeError> {
+    if data.len() < 8 {
+        return Ok(None);
+    }
+    let (discriminator, args) = data.split_at(8);
+
+    let change = if discriminator == INITIALIZE_DISCRIMINATOR {
+        let args = deserialize::<InitializeArgs>(args)?;
+        MetadataChange::new("initialize", 0, Some(2))
+            .with_fields(args.name, args.symbol, args.uri)
+            .with_authority(AuthorityChange::Account(1))
+    } else if discriminator == UPDATE_FIELD_DISCRIMINATOR {
+        let args = deserialize::<UpdateFieldArgs>(args)?;
+        let mut change = MetadataChange::new("update_field", 0, None);
+        let value = Some(clean(args.value));
+        match args.field {
+            Field::Name => change.name = value,
+            Field::Symbol => change.symbol = value,
+            Field::Uri => change.uri = value,
+            Field::Key { .. } => return Ok(None),
+        }
+        change
+    } else if discriminator == UPDATE_AUTHORITY_DISCRIMINATOR {
+        // OptionalNonZeroPubkey: all zeros removes the authority
+        let new_authority = deserialize::<[u8; 32]>(args)?;
+        let new_authority = Some(new_authority).filter(|key| *key != [0; 32]);
+        MetadataChange::new("update_authority", 0, None)
+            .with_authority(AuthorityChange::Key(new_authority))
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(change))
+}
+
+/// Deserialize the leading Borsh fields of `args`, ignoring the rest
+#[inline]
+fn deserialize_prefix<T: BorshDeserialize>(mut args: &[u8]) -> Result<T, DecodeError> {
+    T::deserialize(&mut args).map_err(|err| DecodeError::InvalidArgs(err.to_string()))
+}
+
+/// Metaplex pads names and symbols with NUL bytes
+#[inline]
+fn clean(value: String) -> String {
+    value.trim_end_matches('\0').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHORITY: [u8; 32] = [9; 32];
+
+    fn push_string(data: &mut Vec<u8>, value: &str) {
+        data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        data.extend_from_slice(value.as_bytes());
+    }
+
+    /// name, symbol, uri, seller fee, one creator
+    fn push_data(data: &mut Vec<u8>) {
+        push_string(data, "Jupiter\0\0\0");
+        push_string(data, "JUP");
+        push_string(data, "https://example.com/jup.json");
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.extend_from_slice(&[1, 1, 0, 0, 0]);
+        data.extend_from_slice(&[4; 32]);
+        data.extend_from_slice(&[1, 100]);
+    }
+
+    fn create_test_accounts(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("account{}", i)).collect()
+    }
+
+    #[test]
+    fn test_decode_create_metadata_account_v3() {
+        let mut data = vec![CREATE_METADATA_ACCOUNT_V3];
+        push_data(&mut data);
+        // No collection, uses (Single, 1/1), mutable, no collection details
+        data.extend_from_slice(&[0, 1, 2]);
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&[1, 0]);
+
+        let change = decode_metaplex(&data).unwrap().unwrap();
+        let metadata = change.to_token_metadata(&create_test_accounts(7)).unwrap();
+
+        assert_eq!(metadata.instruction, "create_metadata_account_v3");
+        assert_eq!(metadata.metadata_account, "account0");
+        assert_eq!(metadata.mint, "account1");
+        assert_eq!(metadata.name.as_deref(), Some("Jupiter"));
+        assert_eq!(metadata.symbol.as_deref(), Some("JUP"));
+        assert_eq!(metadata.uri.as_deref(), Some("https://example.com/jup.json"));
+        assert_eq!(metadata.update_authority.as_deref(), Some("account4"));
+
+        // Too few accounts to name the update authority
+        assert_eq!(change.to_token_metadata(&create_test_accounts(4)), None);
+        // Trailing bytes don't match the layout
+        data.push(0);
+        assert!(decode_metaplex(&data).is_err());
+    }
+
+    #[test]
+    fn test_decode_update_metadata_account_v2() {
+        // No data, new update authority, no primary sale / mutability change
+        let mut data = vec![UPDATE_METADATA_ACCOUNT_V2, 0, 1];
+        data.extend_from_slice(&AUTHORITY);
+        data.extend_from_slice(&[0, 0]);
+
+        let metadata = decode_metaplex(&data)
+            .unwrap()
+            .unwrap()
+            .to_token_metadata(&create_test_accounts(2))
+            .unwrap();
+
+        assert_eq!(metadata.mint, "");
+        assert_eq!(metadata.metadata_account, "account0");
+        assert_eq!(metadata.name, None);
+        assert_eq!(metadata.update_authority, Some(base58::encode(AUTHORITY)));
+    }
+
+    #[test]
+    fn test_decode_create_and_update() {
+        // Create: V1 variant, then AssetData whose trailing fields are not read
+        let mut data = vec![CREATE, 0];
+        push_data(&mut data);
+        data.extend_from_slice(&[0, 1, 0, 0]);
+
+        let change = decode_metaplex(&data).unwrap().unwrap();
+        let metadata = change.to_token_metadata(&create_test_accounts(6)).unwrap();
+        assert_eq!(metadata.mint, "account2");
+        assert_eq!(metadata.update_authority.as_deref(), Some("account5"));
+        assert_eq!(metadata.symbol.as_deref(), Some("JUP"));
+
+        // Update as update authority: new authority, new data
+        let mut data = vec![UPDATE, UPDATE_AS_UPDATE_AUTHORITY_V2, 1];
+        data.extend_from_slice(&AUTHORITY);
+        data.push(1);
+        push_data(&mut data);
+        data.extend_from_slice(&[0, 0, 0]);
+
+        let metadata = decode_metaplex(&data)
+            .unwrap()
+            .unwrap()
+            .to_token_metadata(&create_test_accounts(5))
+            .unwrap();
+        assert_eq!(metadata.mint, "account3");
+        assert_eq!(metadata.metadata_account, "account4");
+        assert_eq!(metadata.name.as_deref(), Some("Jupiter"));
+        assert_eq!(metadata.update_authority, Some(base58::encode(AUTHORITY)));
+
+        // Collection delegate updates don't touch name/symbol/uri/authority
+        assert_eq!(decode_metaplex(&[UPDATE, 3, 0]), Ok(None));
+    }
+
+    #[test]
+    fn test_decode_token_2022_metadata() {
+        let mut data = INITIALIZE_DISCRIMINATOR.to_vec();
+        push_string(&mut data, "Token");
+        push_string(&mut data, "TKN");
+        push_string(&mut data, "ipfs://token");
+
+        let metadata = decode_token_2022_metadata(&data)
+            .unwrap()
+            .unwrap()
+            .to_token_metadata(&create_test_accounts(4))
+            .unwrap();
+        assert_eq!(metadata.mint, "account2");
+        assert_eq!(metadata.update_authority.as_deref(), Some("account1"));
+        assert_eq!(metadata.uri.as_deref(), Some("ipfs://token"));
+
+        // UpdateField(Symbol)
+        let mut data = UPDATE_FIELD_DISCRIMINATOR.to_vec();
+        data.push(1);
+        push_string(&mut data, "TKN2");
+        let change = decode_token_2022_metadata(&data).unwrap().unwrap();
+        assert_eq!(change.symbol.as_deref(), Some("TKN2"));
+        assert_eq!(change.name, None);
+        assert_eq!(change.mint_index, None);
+
+        // UpdateField(Key) is an additional field, not tracked
+        let mut data = UPDATE_FIELD_DISCRIMINATOR.to_vec();
+        data.push(3);
+        push_string(&mut data, "website");
+        push_string(&mut data, "https://example.com");
+        assert_eq!(decode_token_2022_metadata(&data), Ok(None));
+
+        // UpdateAuthority to zeros removes the authority
+        let mut data = UPDATE_AUTHORITY_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[0; 32]);
+        let metadata = decode_token_2022_metadata(&data)
+            .unwrap()
+            .unwrap()
+            .to_token_metadata(&create_test_accounts(2))
+            .unwrap();
+        assert_eq!(metadata.update_authority.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_ignores_other_instructions() {
+        // Metaplex Verify (7), empty data, Token-2022 TransferChecked
+        assert_eq!(decode_metaplex(&[7, 0]), Ok(None));
+        assert_eq!(decode_metaplex(&[]), Ok(None));
+        assert_eq!(decode_token_2022_metadata(&[12, 1, 0, 0, 0, 0, 0, 0, 0, 6]), Ok(None));
+        assert_eq!(metadata_mint_key("abc"), "metadata:abc");
+    }
+}
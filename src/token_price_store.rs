@@ -1,26 +1,655 @@
-use std::collections::HashSet;
+//! Token USD prices
+//!
+//! Prices the mints traded in a block from its successful swaps, using the
+//! decimal-normalized amounts (swaps with unknown decimals or tagged as price
+//! outliers are skipped):
+//!
+//! 1. USDC and USDT are anchors at $1.
+//! 2. Tokens swapped against an anchor are priced directly.
+//! 3. Remaining tokens are bridged through hub tokens priced in an earlier round
+//!    (usually SOL), for up to `MAX_BRIDGE_ROUNDS` rounds. `map_token_prices` also
+//!    bridges through hubs not traded in the block, at their last stored price; prices
+//!    resting on a hub price older than `STALE_AFTER_SECONDS` are flagged stale.
+//!
+//! Every price is volume weighted: the USD notional of the trades divided by the
+//! tokens traded. Prices backed by less than `MIN_LIQUIDITY_USD` are flagged as thin
+//! and never used as a hub.
+//!
+//! Prices are carried across blocks in two stores: `store_token_last_price` keeps
+//! each mint's latest price and the closing price of every hour, and
+//! `store_token_volume_usd` its USD volume per hour. `map_token_prices` reads both
+//! to fill `volume_24h` and `price_change_24h`.
+//!
+//! The stores only record prices the block derives on its own: a store can't read
+//! its own earlier values, and taking them from `map_token_prices` would be a cycle.
+//! A mint priced only through a carried hub (e.g. a BONK/SOL swap in a block without
+//! a SOL/USD trade) is in `map_token_prices`, but not in the stores until it is
+//! priced by a block's own trades; its rolling stats and stored USD value lag until then.
+//!
+//! Hourly buckets form a ring of `RING_HOURS` slots per mint, so a mint never holds
+//! more than a day of buckets and nothing has to be deleted. A slot is reused by
+//! the same hour a day later: readers check the closing price's timestamp against
+//! the hour they want, and the volume slot restarts when its closing price moves
+//! to a new hour.
 
-use crate::pb::sf::jupiter::v1::{TokenPrice, TokenPriceList, TradingDataList};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::constants::{USDC_MINT, USDT_MINT};
+use crate::mint_decimals::decimals_key;
+use crate::pb::sf::jupiter::v1::{
+    OutlierReason, PriceSource, TokenPrice, TokenPriceList, TokenPriceState, TradingData, TradingDataList,
+};
 use substreams::errors::Error;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::store::{
+    DeltaProto, Deltas, StoreGet, StoreGetFloat64, StoreGetInt64, StoreGetProto, StoreNew, StoreSet,
+    StoreSetProto, StoreSetSum, StoreSetSumFloat64,
+};
+
+/// Stablecoins priced at $1
+const USD_ANCHORS: [&str; 2] = [USDC_MINT, USDT_MINT];
+/// Rounds of bridging through priced hub tokens after the direct stablecoin prices
+const MAX_BRIDGE_ROUNDS: usize = 2;
+/// Minimum USD notional behind a price before it is trusted as a hub
+pub const MIN_LIQUIDITY_USD: f64 = 100.0;
+/// Age after which a price is stale
+pub const STALE_AFTER_SECONDS: u64 = 3_600;
+
+const SECONDS_PER_HOUR: u64 = 3_600;
+/// Hourly buckets in the rolling window
+const WINDOW_HOURS: u64 = 24;
+/// Hourly slots per mint: the window plus the hour its price change is measured from
+const RING_HOURS: u64 = WINDOW_HOURS + 1;
 
 #[substreams::handlers::map]
-pub fn map_token_prices(trading_data: TradingDataList) -> Result<TokenPriceList, Error> {
-    let mut seen = HashSet::new();
-    let mut prices = Vec::new();
-
-    for trade in trading_data.items {
-        if let Some(account) = trade.accounts.first() {
-            if seen.insert(account.clone()) {
-                prices.push(TokenPrice {
-                    mint_address: account.clone(),
-                    price_usd: 0.0,
-                    volume_24h: 0.0,
-                    price_change_24h: 0.0,
-                    slot: trade.slot,
-                });
+pub fn map_token_prices(
+    trading_data: TradingDataList,
+    last_prices: StoreGetProto<TokenPriceState>,
+    volumes_usd: StoreGetFloat64,
+) -> Result<TokenPriceList, Error> {
+    let mut items = compute_prices(&trading_data.items, |mint| last_prices.get_last(last_price_key(mint)));
+    for price in &mut items {
+        apply_rolling_stats(
+            price,
+            |key| volumes_usd.get_last(key),
+            |key| last_prices.get_last(key),
+        );
+    }
+
+    Ok(TokenPriceList { items })
+}
+
+/// Store handler for each mint's latest USD price and hourly closing prices
+///
+/// Key format: `mint:{address}` (latest) and `mint:{address}:hour:{hour_slot}` (last price of the hour)
+/// Value: TokenPriceState
+#[substreams::handlers::store]
+pub fn store_token_last_price(trading_data: TradingDataList, store: StoreSetProto<TokenPriceState>) {
+    for (ordinal, price) in stored_prices(&trading_data.items).iter().enumerate() {
+        let ordinal = ordinal as u64;
+        let hour = hour_start(price.timestamp);
+        let state = TokenPriceState {
+            price_usd: price.price_usd,
+            slot: price.slot,
+            timestamp: price.timestamp,
+            thin_liquidity: price.thin_liquidity,
+        };
+
+        store.set(ordinal, last_price_key(&price.mint_address), &state);
+        store.set(ordinal, hourly_key(&price.mint_address, hour), &state);
+    }
+}
+
+/// Store handler for each mint's USD volume per hour
+///
+/// Key format: `mint:{address}:hour:{hour_slot}`
+/// Value: USD volume, restarted when the slot's closing price moves to a new hour
+#[substreams::handlers::store]
+pub fn store_token_volume_usd(
+    trading_data: TradingDataList,
+    last_price_deltas: Deltas<DeltaProto<TokenPriceState>>,
+    store: StoreSetSumFloat64,
+) {
+    let new_hours = new_hour_keys(&last_price_deltas);
+
+    for (ordinal, price) in stored_prices(&trading_data.items).iter().enumerate() {
+        let ordinal = ordinal as u64;
+        let key = hourly_key(&price.mint_address, hour_start(price.timestamp));
+
+        if new_hours.contains(key.as_str()) {
+            store.set(ordinal, key, price.volume_usd);
+        } else {
+            store.sum(ordinal, key, price.volume_usd);
+        }
+    }
+}
+
+/// Prices the stores record for a block: those derived from its own trades only,
+/// without the carried hubs `map_token_prices` bridges through
+fn stored_prices(trades: &[TradingData]) -> Vec<TokenPrice> {
+    compute_prices(trades, |_| None)
+}
+
+/// Keys of `store_token_last_price` this block wrote for an hour other than the one
+/// they held before: their hourly slot starts over
+fn new_hour_keys(deltas: &Deltas<DeltaProto<TokenPriceState>>) -> HashSet<&str> {
+    deltas
+        .iter()
+        .filter(|delta| {
+            delta.operation == Operation::Create
+                || hour_start(delta.old_value.timestamp) != hour_start(delta.new_value.timestamp)
+        })
+        .map(|delta| delta.key.as_str())
+        .collect()
+}
+
+/// Key of a mint's latest price in `store_token_last_price`
+#[inline]
+pub fn last_price_key(mint: &str) -> String {
+    format!("mint:{}", mint)
+}
+
+/// Key of the ring slot holding a mint's hour in `store_token_last_price` and
+/// `store_token_volume_usd`
+#[inline]
+pub fn hourly_key(mint: &str, hour_start: u64) -> String {
+    format!("mint:{}:hour:{}", mint, hour_start / SECONDS_PER_HOUR % RING_HOURS)
+}
+
+/// Start of the hour containing `timestamp`
+#[inline]
+fn hour_start(timestamp: u64) -> u64 {
+    timestamp - timestamp % SECONDS_PER_HOUR
+}
+
+/// Fill `volume_24h` and `price_change_24h` from the hourly buckets of earlier blocks
+///
+/// The volume sums the current hour and the 23 before it. The change is measured
+/// against the oldest hourly close of the last 24 hours, and is 0 for mints
+/// without an earlier price. Slots last written for another hour are ignored.
+fn apply_rolling_stats(
+    price: &mut TokenPrice,
+    volume_at: impl Fn(&str) -> Option<f64>,
+    close_at: impl Fn(&str) -> Option<TokenPriceState>,
+) {
+    let hour = hour_start(price.timestamp);
+    // The slot's key and closing price, when the slot still holds `hours_ago`
+    let bucket = |hours_ago: u64| {
+        let bucket_hour = hour.checked_sub(hours_ago * SECONDS_PER_HOUR)?;
+        let key = hourly_key(&price.mint_address, bucket_hour);
+        let close = close_at(&key).filter(|state| hour_start(state.timestamp) == bucket_hour)?;
+        Some((key, close.price_usd))
+    };
+
+    let volume_24h = (0..WINDOW_HOURS)
+        .filter_map(|hours_ago| bucket(hours_ago).and_then(|(key, _)| volume_at(&key)))
+        .sum();
+    let reference = (1..=WINDOW_HOURS)
+        .rev()
+        .find_map(|hours_ago| bucket(hours_ago).map(|(_, close)| close))
+        .filter(|close| *close > 0.0);
+
+    price.volume_24h = volume_24h;
+    price.price_change_24h = reference
+        .map(|close| (price.price_usd - close) / close * 100.0)
+        .unwrap_or_default();
+}
+
+/// A successful swap with both amounts in whole tokens
+struct PricedLeg<'a> {
+    input_mint: &'a str,
+    input_amount: f64,
+    output_mint: &'a str,
+    output_amount: f64,
+}
+
+impl<'a> PricedLeg<'a> {
+    fn from_trade(trade: &'a TradingData) -> Option<Self> {
+        if !trade.success || trade.input_mint.is_empty() || trade.input_mint == trade.output_mint {
+            return None;
+        }
+        if trade.outlier_reason != OutlierReason::None as i32 {
+            return None;
+        }
+        let input_amount = trade.amount_in_normalized.parse::<f64>().ok()?;
+        let output_amount = trade.amount_out_normalized.parse::<f64>().ok()?;
+        if input_amount <= 0.0 || output_amount <= 0.0 || trade.output_mint.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            input_mint: &trade.input_mint,
+            input_amount,
+            output_mint: &trade.output_mint,
+            output_amount,
+        })
+    }
+
+    /// The (mint, amount) of both sides, input first
+    #[inline]
+    fn sides(&self) -> [(&'a str, f64); 2] {
+        [(self.input_mint, self.input_amount), (self.output_mint, self.output_amount)]
+    }
+}
+
+/// USD notional and token amount of the trades pricing one mint
+#[derive(Default)]
+struct PriceAccumulator<'a> {
+    usd: f64,
+    amount: f64,
+    trade_count: u32,
+    usd_by_quote: BTreeMap<&'a str, f64>,
+    /// Priced through a stale hub
+    stale: bool,
+}
+
+impl<'a> PriceAccumulator<'a> {
+    fn add(&mut self, quote_mint: &'a str, usd: f64, amount: f64) {
+        self.usd += usd;
+        self.amount += amount;
+        self.trade_count += 1;
+        *self.usd_by_quote.entry(quote_mint).or_default() += usd;
+    }
+
+    fn to_price(&self, mint: &str, source: PriceSource) -> TokenPrice {
+        // Ties go to the lowest mint, keeping the output deterministic
+        let quote_mint = self
+            .usd_by_quote
+            .iter()
+            .fold(None, |best: Option<(&str, f64)>, (mint, usd)| match best {
+                Some((_, best_usd)) if best_usd >= *usd => best,
+                _ => Some((mint, *usd)),
+            })
+            .map(|(mint, _)| mint.to_string())
+            .unwrap_or_default();
+
+        TokenPrice {
+            mint_address: mint.to_string(),
+            price_usd: self.usd / self.amount,
+            volume_usd: self.usd,
+            trade_count: self.trade_count,
+            source: source as i32,
+            quote_mint,
+            thin_liquidity: self.usd < MIN_LIQUIDITY_USD,
+            stale: self.stale,
+            ..Default::default()
+        }
+    }
+}
+
+/// Derive USD prices for the mints of a block's trades, ordered by mint
+///
+/// `carried` returns a mint's price from an earlier block, used as a hub when the
+/// mint isn't priced in this block.
+fn compute_prices(
+    trades: &[TradingData],
+    carried: impl Fn(&str) -> Option<TokenPriceState>,
+) -> Vec<TokenPrice> {
+    let legs: Vec<PricedLeg> = trades.iter().filter_map(PricedLeg::from_trade).collect();
+    let mut prices: BTreeMap<&str, TokenPrice> = BTreeMap::new();
+
+    // Every trade of a block shares its slot and time
+    let (slot, timestamp) = trades
+        .first()
+        .map(|trade| (trade.slot, trade.block_time))
+        .unwrap_or_default();
+
+    // Hubs carried from earlier blocks: (price, stale) of the block's unanchored mints
+    let carried_hubs: BTreeMap<&str, (f64, bool)> = legs
+        .iter()
+        .flat_map(|leg| leg.sides())
+        .map(|(mint, _)| mint)
+        .filter(|mint| !USD_ANCHORS.contains(mint))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|mint| {
+            let state = carried(mint).filter(|state| !state.thin_liquidity && state.price_usd > 0.0)?;
+            Some((mint, (state.price_usd, is_stale(state.timestamp, timestamp))))
+        })
+        .collect();
+
+    // Anchors: $1, with the traded amount as volume
+    let mut anchors: BTreeMap<&str, PriceAccumulator> = BTreeMap::new();
+    for leg in &legs {
+        for (mint, amount) in leg.sides() {
+            if USD_ANCHORS.contains(&mint) {
+                anchors.entry(mint).or_default().add(mint, amount, amount);
+            }
+        }
+    }
+    for (mint, accumulator) in &anchors {
+        prices.insert(mint, accumulator.to_price(mint, PriceSource::Anchor));
+    }
+
+    // Direct prices against anchors, then bridged prices through priced hubs
+    for round in 0..=MAX_BRIDGE_ROUNDS {
+        let (source, hubs): (PriceSource, HashMap<&str, (f64, bool)>) = if round == 0 {
+            (PriceSource::Direct, USD_ANCHORS.iter().map(|mint| (*mint, (1.0, false))).collect())
+        } else {
+            // Prices of this block win over carried ones
+            let hubs = carried_hubs
+                .iter()
+                .map(|(mint, hub)| (*mint, *hub))
+                .chain(
+                    prices
+                        .iter()
+                        .filter(|(_, price)| !price.thin_liquidity)
+                        .map(|(mint, price)| (*mint, (price.price_usd, price.stale))),
+                )
+                .collect();
+            (PriceSource::Bridged, hubs)
+        };
+
+        let mut round_prices: BTreeMap<&str, PriceAccumulator> = BTreeMap::new();
+        for leg in &legs {
+            let [(mint_a, amount_a), (mint_b, amount_b)] = leg.sides();
+            for ((quote, quote_amount), (mint, amount)) in
+                [((mint_a, amount_a), (mint_b, amount_b)), ((mint_b, amount_b), (mint_a, amount_a))]
+            {
+                if prices.contains_key(mint) {
+                    continue;
+                }
+                if let Some((quote_price, stale)) = hubs.get(quote) {
+                    let accumulator = round_prices.entry(mint).or_default();
+                    accumulator.add(quote, quote_amount * quote_price, amount);
+                    accumulator.stale |= *stale;
+                }
             }
         }
+
+        // Carried hubs can bridge a block without any direct price
+        if round_prices.is_empty() && round > 0 {
+            break;
+        }
+        for (mint, accumulator) in &round_prices {
+            prices.insert(mint, accumulator.to_price(mint, source));
+        }
+    }
+
+    prices
+        .into_values()
+        .map(|mut price| {
+            price.slot = slot;
+            price.timestamp = timestamp;
+            price
+        })
+        .collect()
+}
+
+/// Whether a price observed at `price_time` is stale at `now`
+#[inline]
+pub fn is_stale(price_time: u64, now: u64) -> bool {
+    now.saturating_sub(price_time) > STALE_AFTER_SECONDS
+}
+
+/// USD value at `now` of `amount` base units of a mint with `decimals`, at its stored price
+///
+/// `None` when the price is stale, so volumes never rest on an outdated price.
+pub fn usd_value(amount: u64, decimals: u32, price: &TokenPriceState, now: u64) -> Option<f64> {
+    if is_stale(price.timestamp, now) {
+        return None;
+    }
+    Some(amount as f64 / 10f64.powi(decimals as i32) * price.price_usd)
+}
+
+/// USD value at `now` of `amount` base units of `mint`, from its decimals in
+/// `store_mint_decimals` and its last price in `store_token_last_price`
+pub fn stored_usd_value(
+    mint: &str,
+    amount: u64,
+    now: u64,
+    mint_decimals: &StoreGetInt64,
+    last_prices: &StoreGetProto<TokenPriceState>,
+) -> Option<f64> {
+    let decimals = mint_decimals
+        .get_last(decimals_key(mint))
+        .and_then(|decimals| u32::try_from(decimals).ok())?;
+    let price = last_prices.get_last(last_price_key(mint))?;
+    usd_value(amount, decimals, &price, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::WSOL_MINT;
+
+    fn create_test_trade(input_mint: &str, amount_in: &str, output_mint: &str, amount_out: &str) -> TradingData {
+        TradingData {
+            slot: 1_000,
+            block_time: 1_700_000_000,
+            amount_in: 1,
+            amount_out: 1,
+            input_mint: input_mint.to_string(),
+            output_mint: output_mint.to_string(),
+            amount_in_normalized: amount_in.to_string(),
+            amount_out_normalized: amount_out.to_string(),
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn price_of<'a>(prices: &'a [TokenPrice], mint: &str) -> Option<&'a TokenPrice> {
+        prices.iter().find(|price| price.mint_address == mint)
+    }
+
+    #[test]
+    fn test_direct_prices_are_volume_weighted() {
+        let trades = vec![
+            // 1 SOL for 150 USDC, then 3 SOL bought with 480 USDT
+            create_test_trade(WSOL_MINT, "1", USDC_MINT, "150"),
+            create_test_trade(USDT_MINT, "480", WSOL_MINT, "3"),
+        ];
+
+        let prices = compute_prices(&trades, |_| None);
+
+        let sol = price_of(&prices, WSOL_MINT).unwrap();
+        assert_eq!(sol.price_usd, 630.0 / 4.0);
+        assert_eq!(sol.volume_usd, 630.0);
+        assert_eq!(sol.trade_count, 2);
+        assert_eq!(sol.source, PriceSource::Direct as i32);
+        assert_eq!(sol.quote_mint, USDT_MINT);
+        assert_eq!(sol.slot, 1_000);
+        assert!(!sol.thin_liquidity);
+        assert!(!sol.stale);
+
+        let usdc = price_of(&prices, USDC_MINT).unwrap();
+        assert_eq!(usdc.price_usd, 1.0);
+        assert_eq!(usdc.source, PriceSource::Anchor as i32);
+    }
+
+    #[test]
+    fn test_bridged_prices_through_hub() {
+        let trades = vec![
+            create_test_trade(USDC_MINT, "300", WSOL_MINT, "2"),
+            // 0.5 SOL buys 1000 BONK: 75 USD / 1000
+            create_test_trade(WSOL_MINT, "0.5", "bonk", "1000"),
+            // Tokens only traded against BONK are two hops from USD
+            create_test_trade("bonk", "10", "wif", "1"),
+        ];
+
+        let prices = compute_prices(&trades, |_| None);
+
+        let bonk = price_of(&prices, "bonk").unwrap();
+        assert_eq!(bonk.price_usd, 0.075);
+        assert_eq!(bonk.source, PriceSource::Bridged as i32);
+        assert_eq!(bonk.quote_mint, WSOL_MINT);
+        // $75 of volume is too thin to price tokens through BONK
+        assert!(bonk.thin_liquidity);
+        assert!(price_of(&prices, "wif").is_none());
+    }
+
+    #[test]
+    fn test_bridged_prices_through_carried_hub() {
+        // No SOL/USD trade in this block: SOL's last stored price bridges BONK
+        let trades = vec![create_test_trade(WSOL_MINT, "0.5", "bonk", "1000")];
+        let carried_sol = |timestamp: u64, thin_liquidity: bool| {
+            move |mint: &str| {
+                (mint == WSOL_MINT).then_some(TokenPriceState {
+                    price_usd: 150.0,
+                    timestamp,
+                    thin_liquidity,
+                    ..Default::default()
+                })
+            }
+        };
+
+        let prices = compute_prices(&trades, carried_sol(1_700_000_000 - 600, false));
+        let bonk = price_of(&prices, "bonk").unwrap();
+        assert_eq!(bonk.price_usd, 0.075);
+        assert_eq!(bonk.source, PriceSource::Bridged as i32);
+        assert!(!bonk.stale);
+        // The carried hub itself isn't repriced from its own trade
+        assert!(price_of(&prices, WSOL_MINT).is_none());
+
+        let prices = compute_prices(&trades, carried_sol(1_700_000_000 - 2 * STALE_AFTER_SECONDS, false));
+        assert!(price_of(&prices, "bonk").unwrap().stale);
+
+        // Thin prices are never hubs
+        assert!(compute_prices(&trades, carried_sol(1_700_000_000, true)).is_empty());
+    }
+
+    #[test]
+    fn test_stored_prices_skip_carried_hubs() {
+        let carried_sol = |mint: &str| {
+            (mint == WSOL_MINT).then_some(TokenPriceState {
+                price_usd: 150.0,
+                timestamp: 1_700_000_000 - 600,
+                ..Default::default()
+            })
+        };
+
+        // BONK priced only through SOL's carried price: in the map, not the stores
+        let trades = vec![create_test_trade(WSOL_MINT, "0.5", "bonk", "1000")];
+        assert!(price_of(&compute_prices(&trades, carried_sol), "bonk").is_some());
+        assert!(stored_prices(&trades).is_empty());
+
+        // With a SOL/USD trade in the block the stores price it too
+        let trades = vec![
+            create_test_trade(USDC_MINT, "150", WSOL_MINT, "1"),
+            create_test_trade(WSOL_MINT, "0.5", "bonk", "1000"),
+        ];
+        assert_eq!(price_of(&stored_prices(&trades), "bonk").unwrap().price_usd, 0.075);
+    }
+
+    #[test]
+    fn test_skips_failed_unnormalized_and_outlier_trades() {
+        let mut failed = create_test_trade(WSOL_MINT, "1", USDC_MINT, "150");
+        failed.success = false;
+        let unknown_decimals = create_test_trade("mintA", "", USDC_MINT, "10");
+        let mut outlier = create_test_trade(WSOL_MINT, "1", USDC_MINT, "15000");
+        outlier.outlier_reason = OutlierReason::PriceDeviation as i32;
+
+        assert!(compute_prices(&[failed, unknown_decimals, outlier], |_| None).is_empty());
+    }
+
+    #[test]
+    fn test_apply_rolling_stats() {
+        let now = 1_700_000_000;
+        let hour = hour_start(now);
+        let mut price = TokenPrice {
+            mint_address: "bonk".to_string(),
+            price_usd: 0.03,
+            volume_usd: 50.0,
+            timestamp: now,
+            ..Default::default()
+        };
+
+        let hours_ago = |hours: u64| hour - hours * SECONDS_PER_HOUR;
+        let state = |hour: u64, price_usd: f64| TokenPriceState {
+            price_usd,
+            timestamp: hour + 60,
+            ..Default::default()
+        };
+
+        let volumes = HashMap::from([
+            // The store already holds this block's volume
+            (hourly_key("bonk", hour), 80.0),
+            (hourly_key("bonk", hours_ago(3)), 20.0),
+            (hourly_key("bonk", hours_ago(5)), 1_000.0),
+        ]);
+        let closes = HashMap::from([
+            (hourly_key("bonk", hour), state(hour, 0.03)),
+            (hourly_key("bonk", hours_ago(3)), state(hours_ago(3), 0.025)),
+            (hourly_key("bonk", hours_ago(20)), state(hours_ago(20), 0.02)),
+            // Slot last written 30 hours ago, a day before the hour it holds now
+            (hourly_key("bonk", hours_ago(5)), state(hours_ago(30), 0.01)),
+        ]);
+
+        apply_rolling_stats(&mut price, |key| volumes.get(key).copied(), |key| closes.get(key).cloned());
+
+        assert_eq!(price.volume_24h, 100.0);
+        assert!((price.price_change_24h - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_apply_rolling_stats_without_history() {
+        let mut price = TokenPrice {
+            mint_address: "bonk".to_string(),
+            price_usd: 0.03,
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+
+        apply_rolling_stats(&mut price, |_| None, |_| None);
+
+        assert_eq!(price.volume_24h, 0.0);
+        assert_eq!(price.price_change_24h, 0.0);
+    }
+
+    #[test]
+    fn test_hourly_keys() {
+        assert_eq!(hour_start(1_700_000_000), 1_699_999_200);
+        assert_eq!(last_price_key("bonk"), "mint:bonk");
+        assert_eq!(hourly_key("bonk", 3_600), "mint:bonk:hour:1");
+        // The slot comes back once the window has passed
+        assert_eq!(hourly_key("bonk", 3_600 + RING_HOURS * SECONDS_PER_HOUR), "mint:bonk:hour:1");
+        assert_ne!(hourly_key("bonk", 3_600), hourly_key("bonk", 3_600 + WINDOW_HOURS * SECONDS_PER_HOUR));
     }
 
-    Ok(TokenPriceList { items: prices })
+    #[test]
+    fn test_new_hour_keys() {
+        let delta = |operation: Operation, key: &str, old: u64, new: u64| DeltaProto {
+            operation,
+            ordinal: 0,
+            key: key.to_string(),
+            old_value: TokenPriceState { timestamp: old, ..Default::default() },
+            new_value: TokenPriceState { timestamp: new, ..Default::default() },
+        };
+        let hour = hour_start(1_700_000_000);
+        let deltas = Deltas {
+            deltas: vec![
+                delta(Operation::Create, "mint:a:hour:1", 0, hour),
+                // Same hour, later block
+                delta(Operation::Update, "mint:b:hour:1", hour + 10, hour + 20),
+                // Slot reused a day later
+                delta(Operation::Update, "mint:c:hour:1", hour - RING_HOURS * SECONDS_PER_HOUR, hour),
+            ],
+        };
+
+        let keys = new_hour_keys(&deltas);
+
+        assert_eq!(keys, HashSet::from(["mint:a:hour:1", "mint:c:hour:1"]));
+    }
+
+    #[test]
+    fn test_is_stale() {
+        assert!(!is_stale(1_000, 1_000));
+        assert!(!is_stale(1_000, 1_000 + STALE_AFTER_SECONDS));
+        assert!(is_stale(1_000, 1_001 + STALE_AFTER_SECONDS));
+        // A price newer than the clock is never stale
+        assert!(!is_stale(2_000, 1_000));
+    }
+
+    #[test]
+    fn test_usd_value() {
+        let price = TokenPriceState {
+            price_usd: 150.0,
+            timestamp: 1_000,
+            ..Default::default()
+        };
+
+        // 2.5 SOL (9 decimals) at $150
+        assert_eq!(usd_value(2_500_000_000, 9, &price, 1_000), Some(375.0));
+        assert_eq!(usd_value(2_500_000_000, 9, &price, 1_001 + STALE_AFTER_SECONDS), None);
+    }
 }
@@ -0,0 +1,332 @@
+//! Failed transaction decoding
+//!
+//! Turns the bincode-encoded `TransactionError` in transaction meta into an error
+//! code and message. Anchor programs log the failing error by name, so the logs
+//! are preferred; otherwise custom codes of the failing Jupiter v6 instruction are
+//! named from the program's error table, and anything else falls back to the
+//! runtime error variant.
+
+use crate::constants::JUPITER_V6_PROGRAM_ID;
+use crate::transaction_view::TransactionAccounts;
+use substreams_solana::pb::sf::solana::r#type::v1::ConfirmedTransaction;
+
+// TransactionError::InstructionError(u8, InstructionError)
+const INSTRUCTION_ERROR_VARIANT: u32 = 8;
+// InstructionError::Custom(u32)
+const CUSTOM_ERROR_VARIANT: u32 = 25;
+
+/// Jupiter v6 program errors (Anchor codes start at 6000)
+const JUPITER_V6_ERRORS: [(&str, &str); 18] = [
+    ("EmptyRoute", "Empty route"),
+    ("SlippageToleranceExceeded", "Slippage tolerance exceeded"),
+    ("InvalidCalculation", "Invalid calculation"),
+    ("MissingPlatformFeeAccount", "Missing platform fee account"),
+    ("InvalidSlippage", "Invalid slippage"),
+    ("NotEnoughPercent", "Not enough percent to 100"),
+    ("InvalidInputIndex", "Token input index is invalid"),
+    ("InvalidOutputIndex", "Token output index is invalid"),
+    ("NotEnoughAccountKeys", "Not Enough Account keys"),
+    ("NonZeroMinimumOutAmountNotSupported", "Non zero minimum out amount not supported"),
+    ("InvalidRoutePlan", "Invalid route plan"),
+    ("InvalidReferralAuthority", "Invalid referral authority"),
+    ("LedgerTokenAccountDoesNotMatch", "Token account doesn't match the ledger"),
+    ("InvalidTokenLedger", "Invalid token ledger"),
+    ("IncorrectTokenProgramID", "Token program ID is invalid"),
+    ("TokenProgramNotProvided", "Token program not provided"),
+    ("SwapNotSupported", "Swap not supported"),
+    ("ExactOutAmountNotMatched", "Exact out amount doesn't match"),
+];
+
+/// Runtime InstructionError variants, indexed by their bincode tag
+///
+/// `Custom` (25) carries a code and is decoded separately; `BorshIoError` (44)
+/// carries a string, which is not read.
+const INSTRUCTION_ERRORS: [&str; 54] = [
+    "GenericError",
+    "InvalidArgument",
+    "InvalidInstructionData",
+    "InvalidAccountData",
+    "AccountDataTooSmall",
+    "InsufficientFunds",
+    "IncorrectProgramId",
+    "MissingRequiredSignature",
+    "AccountAlreadyInitialized",
+    "UninitializedAccount",
+    "UnbalancedInstruction",
+    "ModifiedProgramId",
+    "ExternalAccountLamportSpend",
+    "ExternalAccountDataModified",
+    "ReadonlyLamportChange",
+    "ReadonlyDataModified",
+    "DuplicateAccountIndex",
+    "ExecutableModified",
+    "RentEpochModified",
+    "NotEnoughAccountKeys",
+    "AccountDataSizeChanged",
+    "AccountNotExecutable",
+    "AccountBorrowFailed",
+    "AccountBorrowOutstanding",
+    "DuplicateAccountOutOfSync",
+    "Custom",
+    "InvalidError",
+    "ExecutableDataModified",
+    "ExecutableLamportChange",
+    "ExecutableAccountNotRentExempt",
+    "UnsupportedProgramId",
+    "CallDepth",
+    "MissingAccount",
+    "ReentrancyNotAllowed",
+    "MaxSeedLengthExceeded",
+    "InvalidSeeds",
+    "InvalidRealloc",
+    "ComputationalBudgetExceeded",
+    "PrivilegeEscalation",
+    "ProgramEnvironmentSetupFailure",
+    "ProgramFailedToComplete",
+    "ProgramFailedToCompile",
+    "Immutable",
+    "IncorrectAuthority",
+    "BorshIoError",
+    "AccountNotRentExempt",
+    "InvalidAccountOwner",
+    "ArithmeticOverflow",
+    "UnsupportedSysvar",
+    "IllegalOwner",
+    "MaxAccountsDataAllocationsExceeded",
+    "MaxAccountsResizeExceeded",
+    "MaxInstructionTraceLengthExceeded",
+    "BuiltinProgramsMustConsumeComputeUnits",
+];
+
+/// Runtime TransactionError variants, indexed by their bincode tag
+const TRANSACTION_ERRORS: [&str; 8] = [
+    "AccountInUse",
+    "AccountLoadedTwice",
+    "AccountNotFound",
+    "ProgramAccountNotFound",
+    "InsufficientFundsForFee",
+    "InvalidAccountForFee",
+    "AlreadyProcessed",
+    "BlockhashNotFound",
+];
+
+/// Decoded `TransactionError` from transaction meta
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionErrorKind {
+    /// A top-level instruction failed with a program-defined code
+    Custom { instruction_index: u8, code: u32 },
+    /// A top-level instruction failed with a runtime InstructionError variant
+    Instruction { instruction_index: u8, variant: u32 },
+    /// The transaction failed outside of any instruction
+    Transaction { variant: u32 },
+}
+
+/// Why a transaction failed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionFailure {
+    /// Program error name (e.g. `SlippageToleranceExceeded`) or runtime error variant
+    pub error_code: String,
+    pub error_message: String,
+}
+
+/// Decode the failure of a transaction, `None` if it succeeded
+pub fn transaction_failure(
+    trx: &ConfirmedTransaction,
+    account_keys: &TransactionAccounts,
+) -> Option<TransactionFailure> {
+    let meta = trx.meta.as_ref()?;
+    let err = meta.err.as_ref()?;
+    let kind = decode_transaction_error(&err.err);
+
+    let failed_program = match kind {
+        Some(TransactionErrorKind::Custom { instruction_index, .. }) => trx
+            .transaction
+            .as_ref()
+            .and_then(|tx| tx.message.as_ref())
+            .and_then(|message| message.instructions.get(instruction_index as usize))
+            .and_then(|instruction| account_keys.get(instruction.program_id_index)),
+        _ => None,
+    };
+
+    Some(describe_failure(kind.as_ref(), failed_program, &meta.log_messages))
+}
+
+/// Decode a bincode-encoded `TransactionError`
+///
+/// Enum tags are u32 little-endian; the instruction index is a u8.
+pub fn decode_transaction_error(err: &[u8]) -> Option<TransactionErrorKind> {
+    let variant = read_u32(err, 0)?;
+    if variant != INSTRUCTION_ERROR_VARIANT {
+        return Some(TransactionErrorKind::Transaction { variant });
+    }
+
+    let instruction_index = *err.get(4)?;
+    match read_u32(err, 5)? {
+        CUSTOM_ERROR_VARIANT => Some(TransactionErrorKind::Custom {
+            instruction_index,
+            code: read_u32(err, 9)?,
+        }),
+        variant => Some(TransactionErrorKind::Instruction { instruction_index, variant }),
+    }
+}
+
+/// Build the error code and message for a failed transaction
+///
+/// An Anchor error in the logs wins; then Jupiter v6 custom codes; then the
+/// runtime variant, with the last `Program ... failed:` log line as the message.
+fn describe_failure(
+    kind: Option<&TransactionErrorKind>,
+    failed_program: Option<&str>,
+    logs: &[String],
+) -> TransactionFailure {
+    if let Some(failure) = anchor_error_from_logs(logs) {
+        return failure;
+    }
+
+    let error_code = match kind {
+        Some(TransactionErrorKind::Custom { code, .. }) => {
+            match jupiter_v6_error(failed_program, *code) {
+                Some((name, message)) => {
+                    return TransactionFailure {
+                        error_code: name.to_string(),
+                        error_message: message.to_string(),
+                    }
+                }
+                None => format!("Custom({})", code),
+            }
+        }
+        Some(TransactionErrorKind::Instruction { variant, .. }) => {
+            variant_name(&INSTRUCTION_ERRORS, *variant, "InstructionError")
+        }
+        Some(TransactionErrorKind::Transaction { variant }) => {
+            variant_name(&TRANSACTION_ERRORS, *variant, "TransactionError")
+        }
+        None => "Unknown".to_string(),
+    };
+
+    let error_message = logs
+        .iter()
+        .rev()
+        .find_map(|line| line.split_once(" failed: ").map(|(_, reason)| reason.to_string()))
+        .unwrap_or_else(|| error_code.clone());
+
+    TransactionFailure { error_code, error_message }
+}
+
+/// Parse `Program log: AnchorError ... Error Code: X. Error Number: N. Error Message: M.`
+fn anchor_error_from_logs(logs: &[String]) -> Option<TransactionFailure> {
+    let line = logs.iter().rev().find(|line| line.contains("AnchorError"))?;
+
+    let (_, rest) = line.split_once("Error Code: ")?;
+    let (error_code, rest) = rest.split_once(". ")?;
+    let error_message = rest
+        .split_once("Error Message: ")
+        .map(|(_, message)| message.trim_end_matches('.'))
+        .unwrap_or(error_code);
+
+    Some(TransactionFailure {
+        error_code: error_code.to_string(),
+        error_message: error_message.to_string(),
+    })
+}
+
+/// Name and message of a Jupiter v6 custom error code
+#[inline]
+fn jupiter_v6_error(program_id: Option<&str>, code: u32) -> Option<(&'static str, &'static str)> {
+    if program_id != Some(JUPITER_V6_PROGRAM_ID) {
+        return None;
+    }
+    JUPITER_V6_ERRORS.get(code.checked_sub(6000)? as usize).copied()
+}
+
+#[inline]
+fn variant_name(names: &[&str], variant: u32, fallback: &str) -> String {
+    match names.get(variant as usize) {
+        Some(name) => name.to_string(),
+        None => format!("{}({})", fallback, variant),
+    }
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // bincode of InstructionError(2, Custom(6001)), as the runtime writes meta.err:
+    // TransactionError tag 8, instruction index, InstructionError tag 25, code
+    const SLIPPAGE_ERROR: [u8; 13] = [8, 0, 0, 0, 2, 25, 0, 0, 0, 0x71, 0x17, 0, 0];
+
+    #[test]
+    fn test_decode_transaction_error() {
+        assert_eq!(
+            decode_transaction_error(&SLIPPAGE_ERROR),
+            Some(TransactionErrorKind::Custom { instruction_index: 2, code: 6001 })
+        );
+        assert_eq!(
+            decode_transaction_error(&[8, 0, 0, 0, 0, 1, 0, 0, 0]),
+            Some(TransactionErrorKind::Instruction { instruction_index: 0, variant: 1 })
+        );
+        assert_eq!(
+            decode_transaction_error(&[4, 0, 0, 0]),
+            Some(TransactionErrorKind::Transaction { variant: 4 })
+        );
+        // DuplicateAccountOutOfSync is the last variant before Custom
+        assert_eq!(
+            decode_transaction_error(&[8, 0, 0, 0, 1, 24, 0, 0, 0]),
+            Some(TransactionErrorKind::Instruction { instruction_index: 1, variant: 24 })
+        );
+        assert_eq!(decode_transaction_error(&[8, 0, 0, 0, 2, 25]), None);
+    }
+
+    #[test]
+    fn test_instruction_error_names() {
+        assert_eq!(variant_name(&INSTRUCTION_ERRORS, 13, "InstructionError"), "ExternalAccountDataModified");
+        assert_eq!(variant_name(&INSTRUCTION_ERRORS, 14, "InstructionError"), "ReadonlyLamportChange");
+        assert_eq!(variant_name(&INSTRUCTION_ERRORS, 37, "InstructionError"), "ComputationalBudgetExceeded");
+        assert_eq!(variant_name(&INSTRUCTION_ERRORS, 54, "InstructionError"), "InstructionError(54)");
+    }
+
+    #[test]
+    fn test_describe_failure_prefers_anchor_log() {
+        let logs = vec![
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]".to_string(),
+            "Program log: AnchorError occurred. Error Code: SlippageToleranceExceeded. \
+             Error Number: 6001. Error Message: Slippage tolerance exceeded."
+                .to_string(),
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 failed: custom program error: 0x1771"
+                .to_string(),
+        ];
+        let kind = decode_transaction_error(&SLIPPAGE_ERROR);
+
+        let failure = describe_failure(kind.as_ref(), None, &logs);
+
+        assert_eq!(failure.error_code, "SlippageToleranceExceeded");
+        assert_eq!(failure.error_message, "Slippage tolerance exceeded");
+    }
+
+    #[test]
+    fn test_describe_failure_without_anchor_log() {
+        let kind = decode_transaction_error(&SLIPPAGE_ERROR);
+
+        // Truncated logs: name the code from the Jupiter v6 error table
+        let failure = describe_failure(kind.as_ref(), Some(JUPITER_V6_PROGRAM_ID), &[]);
+        assert_eq!(failure.error_code, "SlippageToleranceExceeded");
+        assert_eq!(failure.error_message, "Slippage tolerance exceeded");
+
+        // Other programs keep the raw code and the runtime log reason
+        let logs = vec!["Program abc failed: custom program error: 0x1771".to_string()];
+        let failure = describe_failure(kind.as_ref(), Some("abc"), &logs);
+        assert_eq!(failure.error_code, "Custom(6001)");
+        assert_eq!(failure.error_message, "custom program error: 0x1771");
+
+        let kind = TransactionErrorKind::Transaction { variant: 4 };
+        let failure = describe_failure(Some(&kind), None, &[]);
+        assert_eq!(failure.error_code, "InsufficientFundsForFee");
+        assert_eq!(failure.error_message, "InsufficientFundsForFee");
+    }
+}
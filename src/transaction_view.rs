@@ -0,0 +1,325 @@
+//! Account-resolved transaction instructions
+//!
+//! v0 transactions reference most accounts through address lookup tables: an
+//! instruction's account indices run over the static message keys first, then
+//! the addresses loaded writable, then the addresses loaded readonly. This module
+//! resolves indices against that combined list once per transaction and walks
+//! top-level and inner instructions in execution order. Unlike the substreams
+//! `InstructionView`, an index outside the loaded keys is reported instead of
+//! panicking, so a malformed transaction can be skipped.
+
+use std::collections::HashMap;
+use std::iter;
+
+use substreams_solana::base58;
+use substreams_solana::pb::sf::solana::r#type::v1::ConfirmedTransaction;
+
+/// Base58 account keys of a transaction, in account index order
+///
+/// Static keys, then lookup-table addresses loaded writable, then readonly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionAccounts {
+    keys: Vec<String>,
+}
+
+impl TransactionAccounts {
+    pub fn new(trx: &ConfirmedTransaction) -> Self {
+        let keys = raw_account_keys(trx).map(base58::encode).collect();
+
+        Self { keys }
+    }
+
+    /// Account at `index`, `None` if the index is past the loaded keys
+    #[inline]
+    pub fn get(&self, index: u32) -> Option<&str> {
+        self.keys.get(index as usize).map(String::as_str)
+    }
+
+    /// Resolve an instruction's account indices; `None` if any index is out of range
+    pub fn resolve(&self, indices: &[u8]) -> Option<Vec<String>> {
+        indices
+            .iter()
+            .map(|index| self.get(*index as u32).map(str::to_string))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// Raw account keys of a transaction, in account index order
+fn raw_account_keys(trx: &ConfirmedTransaction) -> impl Iterator<Item = &Vec<u8>> {
+    let static_keys = trx
+        .transaction
+        .as_ref()
+        .and_then(|tx| tx.message.as_ref())
+        .map(|message| message.account_keys.as_slice())
+        .unwrap_or_default();
+    let (loaded_writable, loaded_readonly) = match trx.meta.as_ref() {
+        Some(meta) => (
+            meta.loaded_writable_addresses.as_slice(),
+            meta.loaded_readonly_addresses.as_slice(),
+        ),
+        None => (&[][..], &[][..]),
+    };
+
+    static_keys.iter().chain(loaded_writable).chain(loaded_readonly)
+}
+
+/// Whether the transaction loads any of the raw `keys`, statically or through a
+/// lookup table
+///
+/// Compares raw bytes, so transactions that can't invoke a program are skipped
+/// before their keys are base58-encoded.
+pub fn references_any(trx: &ConfirmedTransaction, keys: &[Vec<u8>]) -> bool {
+    raw_account_keys(trx).any(|key| keys.contains(key))
+}
+
+/// A top-level or inner instruction with unresolved account indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionRef<'a> {
+    pub program_id_index: u32,
+    pub accounts: &'a [u8],
+    pub data: &'a [u8],
+    /// Top-level instructions report 1; inner instructions recorded before stack
+    /// heights existed report 0
+    pub stack_height: u32,
+    pub is_root: bool,
+    /// Index of the top-level instruction (the instruction itself when root)
+    pub instruction_index: u32,
+    /// Position among the inner instructions of the top-level instruction, `None` when root
+    pub inner_index: Option<u32>,
+}
+
+/// Programs on the invocation stack while walking a transaction's instructions
+///
+/// Entering every walked instruction in order yields the program that invoked it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallStack {
+    /// Program at each stack height, from the top-level instruction down
+    programs: Vec<String>,
+}
+
+impl CallStack {
+    /// Enter an instruction; returns the invoking program, `None` when top-level
+    ///
+    /// Inner instructions without a stack height (recorded before stack heights
+    /// existed) are attributed to the top-level program.
+    pub fn enter(&mut self, instruction: &InstructionRef, program_id: &str) -> Option<&str> {
+        if instruction.is_root {
+            self.programs.clear();
+            self.programs.push(program_id.to_string());
+            return None;
+        }
+
+        let caller = if instruction.stack_height >= 2 {
+            self.programs.truncate(instruction.stack_height as usize - 1);
+            let caller = self.programs.len().checked_sub(1);
+            self.programs.push(program_id.to_string());
+            caller
+        } else {
+            (!self.programs.is_empty()).then_some(0)
+        };
+
+        caller.map(|index| self.programs[index].as_str())
+    }
+}
+
+/// Stable ID of an instruction: `{signature}:{instruction_index}` when top-level,
+/// `{signature}:{instruction_index}:{inner_index}` when inner
+pub fn instruction_id(signature: &str, instruction_index: u32, inner_index: Option<u32>) -> String {
+    match inner_index {
+        Some(inner_index) => format!("{}:{}:{}", signature, instruction_index, inner_index),
+        None => format!("{}:{}", signature, instruction_index),
+    }
+}
+
+/// Walk top-level instructions, each followed by its inner instructions
+pub fn walk_instructions(trx: &ConfirmedTransaction) -> impl Iterator<Item = InstructionRef<'_>> {
+    let instructions = trx
+        .transaction
+        .as_ref()
+        .and_then(|tx| tx.message.as_ref())
+        .map(|message| message.instructions.as_slice())
+        .unwrap_or_default();
+
+    let mut inner_by_parent = HashMap::new();
+    if let Some(meta) = trx.meta.as_ref() {
+        for inner in &meta.inner_instructions {
+            inner_by_parent.insert(inner.index as usize, inner.instructions.as_slice());
+        }
+    }
+
+    instructions.iter().enumerate().flat_map(move |(index, instruction)| {
+        let root = InstructionRef {
+            program_id_index: instruction.program_id_index,
+            accounts: &instruction.accounts,
+            data: &instruction.data,
+            stack_height: 1,
+            is_root: true,
+            instruction_index: index as u32,
+            inner_index: None,
+        };
+        let inner = inner_by_parent.get(&index).copied().unwrap_or_default();
+
+        iter::once(root).chain(inner.iter().enumerate().map(move |(inner_index, instruction)| {
+            InstructionRef {
+                program_id_index: instruction.program_id_index,
+                accounts: &instruction.accounts,
+                data: &instruction.data,
+                stack_height: instruction.stack_height.unwrap_or(0),
+                is_root: false,
+                instruction_index: index as u32,
+                inner_index: Some(inner_index as u32),
+            }
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substreams_solana::pb::sf::solana::r#type::v1::{
+        CompiledInstruction, InnerInstruction, InnerInstructions, Message, Transaction,
+        TransactionStatusMeta,
+    };
+
+    fn key(byte: u8) -> Vec<u8> {
+        vec![byte; 32]
+    }
+
+    /// Two static keys, two loaded writable and one loaded readonly address
+    fn create_test_transaction() -> ConfirmedTransaction {
+        ConfirmedTransaction {
+            transaction: Some(Transaction {
+                signatures: vec![vec![1; 64]],
+                message: Some(Message {
+                    account_keys: vec![key(1), key(2)],
+                    instructions: vec![
+                        CompiledInstruction {
+                            program_id_index: 1,
+                            accounts: vec![0, 2, 3, 4],
+                            data: vec![7],
+                        },
+                        CompiledInstruction {
+                            program_id_index: 4,
+                            accounts: vec![0],
+                            data: vec![8],
+                        },
+                    ],
+                    versioned: true,
+                    ..Default::default()
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                inner_instructions: vec![InnerInstructions {
+                    index: 0,
+                    instructions: vec![InnerInstruction {
+                        program_id_index: 4,
+                        accounts: vec![3, 2],
+                        data: vec![9],
+                        stack_height: Some(2),
+                    }],
+                }],
+                loaded_writable_addresses: vec![key(3), key(4)],
+                loaded_readonly_addresses: vec![key(5)],
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_resolve_lookup_table_accounts() {
+        let accounts = TransactionAccounts::new(&create_test_transaction());
+
+        assert_eq!(accounts.len(), 5);
+        assert_eq!(accounts.get(1), Some(base58::encode(key(2)).as_str()));
+        // Writable lookups follow the static keys, readonly lookups come last
+        assert_eq!(accounts.get(3), Some(base58::encode(key(4)).as_str()));
+        assert_eq!(accounts.get(4), Some(base58::encode(key(5)).as_str()));
+
+        let resolved = accounts.resolve(&[4, 0, 2]).unwrap();
+        assert_eq!(
+            resolved,
+            vec![base58::encode(key(5)), base58::encode(key(1)), base58::encode(key(3))]
+        );
+        assert_eq!(accounts.resolve(&[0, 5]), None);
+    }
+
+    #[test]
+    fn test_references_any() {
+        let trx = create_test_transaction();
+
+        assert!(references_any(&trx, &[key(9), key(1)]));
+        // Readonly lookup table address
+        assert!(references_any(&trx, &[key(5)]));
+        assert!(!references_any(&trx, &[key(9)]));
+        assert!(!references_any(&ConfirmedTransaction::default(), &[key(1)]));
+    }
+
+    #[test]
+    fn test_walk_instructions_in_execution_order() {
+        let trx = create_test_transaction();
+
+        let walked: Vec<InstructionRef> = walk_instructions(&trx).collect();
+
+        assert_eq!(walked.len(), 3);
+        assert_eq!(walked[0].data, &[7]);
+        assert!(walked[0].is_root);
+        assert_eq!(walked[0].stack_height, 1);
+        assert_eq!((walked[0].instruction_index, walked[0].inner_index), (0, None));
+        // Inner instruction of the first top-level instruction precedes the second
+        assert_eq!(walked[1].data, &[9]);
+        assert!(!walked[1].is_root);
+        assert_eq!(walked[1].stack_height, 2);
+        assert_eq!((walked[1].instruction_index, walked[1].inner_index), (0, Some(0)));
+        assert_eq!(walked[2].program_id_index, 4);
+        assert!(walked[2].is_root);
+        assert_eq!((walked[2].instruction_index, walked[2].inner_index), (1, None));
+    }
+
+    #[test]
+    fn test_call_stack() {
+        let instruction = |is_root: bool, stack_height: u32| InstructionRef {
+            program_id_index: 0,
+            accounts: &[],
+            data: &[],
+            stack_height,
+            is_root,
+            instruction_index: 0,
+            inner_index: None,
+        };
+        let mut stack = CallStack::default();
+
+        // bot -> jupiter -> amm, then jupiter's self-CPI event
+        assert_eq!(stack.enter(&instruction(true, 1), "bot"), None);
+        assert_eq!(stack.enter(&instruction(false, 2), "jupiter"), Some("bot"));
+        assert_eq!(stack.enter(&instruction(false, 3), "amm"), Some("jupiter"));
+        assert_eq!(stack.enter(&instruction(false, 3), "jupiter"), Some("jupiter"));
+        assert_eq!(stack.enter(&instruction(false, 2), "token"), Some("bot"));
+
+        // A new top-level instruction resets the stack
+        assert_eq!(stack.enter(&instruction(true, 1), "jupiter"), None);
+        // Missing stack height: attributed to the top-level program
+        assert_eq!(stack.enter(&instruction(false, 0), "amm"), Some("jupiter"));
+    }
+
+    #[test]
+    fn test_instruction_id() {
+        assert_eq!(instruction_id("sig", 2, None), "sig:2");
+        assert_eq!(instruction_id("sig", 2, Some(0)), "sig:2:0");
+        assert_ne!(instruction_id("sig", 1, Some(2)), instruction_id("sig", 12, None));
+    }
+
+    #[test]
+    fn test_missing_message_or_meta() {
+        let trx = ConfirmedTransaction::default();
+        assert!(TransactionAccounts::new(&trx).is_empty());
+        assert_eq!(walk_instructions(&trx).count(), 0);
+    }
+}